@@ -23,27 +23,22 @@ use bevy::{
 };
 
 /// An implementation of the classic game "Breakout"
-pub fn run() {
-    App::build()
-        //This does a lot of stuff, setting up sprite & UI rendering among many other things
-        //https://docs.rs/bevy/0.1.3/src/bevy/add_default_plugins.rs.html#7-37
-        //UI Plugin setup: https://docs.rs/bevy_ui/0.1.3/src/bevy_ui/lib.rs.html#39-55
-        //Sprite Setup: https://docs.rs/bevy_sprite/0.1.3/src/bevy_sprite/lib.rs.html#43-67
-        //Sprite System: https://docs.rs/bevy_sprite/0.1.3/src/bevy_sprite/sprite.rs.html#21-34
-        //Sprites added to the Render graph here: https://docs.rs/bevy_sprite/0.1.3/src/bevy_sprite/lib.rs.html#55
-        //The specifics of how the rendering plugin sets up rendering systems: https://docs.rs/bevy_render/0.1.3/src/bevy_render/lib.rs.html#80-179
-        .add_default_plugins()
+//Upstream builds the app in main, here it's a plugin so the launcher can hand it a window (or no window at all in a test)
+pub struct BreakoutPlugin;
+
+impl Plugin for BreakoutPlugin {
+    fn build(&self, app: &mut AppBuilder) {
         //Scoreboard state
-        .add_resource(Scoreboard { score: 0 })
-        //Kind of a silvery color -- ClearColor resources are the background color of the window
-        //https://github.com/jamadazi/bevy-cheatsheet/blob/master/bevy-cheatsheet.md#configuration-resources
-        .add_resource(ClearColor(Color::rgb(0.7, 0.7, 0.7)))
-        .add_startup_system(setup.system())
-        .add_system(paddle_movement_system.system())
-        .add_system(ball_collision_system.system())
-        .add_system(ball_movement_system.system())
-        .add_system(scoreboard_system.system())
-        .run();
+        app.add_resource(Scoreboard { score: 0 })
+            //Kind of a silvery color -- ClearColor resources are the background color of the window
+            //https://github.com/jamadazi/bevy-cheatsheet/blob/master/bevy-cheatsheet.md#configuration-resources
+            .add_resource(ClearColor(Color::rgb(0.7, 0.7, 0.7)))
+            .add_startup_system(setup.system())
+            .add_system(paddle_movement_system.system())
+            .add_system(ball_collision_system.system())
+            .add_system(ball_movement_system.system())
+            .add_system(scoreboard_system.system());
+    }
 }
 
 struct Paddle {
@@ -90,10 +85,8 @@ fn setup(
         // paddle
         .spawn(SpriteComponents {
             material: materials.add(Color::rgb(0.2, 0.2, 0.8).into()),
-            translation: Translation(Vec3::new(0.0, -215.0, 0.0)),
-            sprite: Sprite {
-                size: Vec2::new(120.0, 30.0),
-            },
+            transform: Transform::from_translation(Vec3::new(0.0, -215.0, 0.0)),
+            sprite: Sprite::new(Vec2::new(120.0, 30.0)),
             //Looks like this is inserting the default values for SpriteComponents that wasn't set
             ..Default::default()
         })
@@ -102,10 +95,8 @@ fn setup(
         // ball
         .spawn(SpriteComponents {
             material: materials.add(Color::rgb(0.8, 0.2, 0.2).into()),
-            translation: Translation(Vec3::new(0.0, -50.0, 1.0)),
-            sprite: Sprite {
-                size: Vec2::new(30.0, 30.0),
-            },
+            transform: Transform::from_translation(Vec3::new(0.0, -50.0, 1.0)),
+            sprite: Sprite::new(Vec2::new(30.0, 30.0)),
             ..Default::default()
         })
         .with(Ball {
@@ -142,40 +133,32 @@ fn setup(
         // left
         .spawn(SpriteComponents {
             material: wall_material,
-            translation: Translation(Vec3::new(-bounds.x() / 2.0, 0.0, 0.0)),
-            sprite: Sprite {
-                size: Vec2::new(wall_thickness, bounds.y() + wall_thickness),
-            },
+            transform: Transform::from_translation(Vec3::new(-bounds.x() / 2.0, 0.0, 0.0)),
+            sprite: Sprite::new(Vec2::new(wall_thickness, bounds.y() + wall_thickness)),
             ..Default::default()
         })
         .with(Collider::Solid)
         // right
         .spawn(SpriteComponents {
             material: wall_material,
-            translation: Translation(Vec3::new(bounds.x() / 2.0, 0.0, 0.0)),
-            sprite: Sprite {
-                size: Vec2::new(wall_thickness, bounds.y() + wall_thickness),
-            },
+            transform: Transform::from_translation(Vec3::new(bounds.x() / 2.0, 0.0, 0.0)),
+            sprite: Sprite::new(Vec2::new(wall_thickness, bounds.y() + wall_thickness)),
             ..Default::default()
         })
         .with(Collider::Solid)
         // bottom
         .spawn(SpriteComponents {
             material: wall_material,
-            translation: Translation(Vec3::new(0.0, -bounds.y() / 2.0, 0.0)),
-            sprite: Sprite {
-                size: Vec2::new(bounds.x() + wall_thickness, wall_thickness),
-            },
+            transform: Transform::from_translation(Vec3::new(0.0, -bounds.y() / 2.0, 0.0)),
+            sprite: Sprite::new(Vec2::new(bounds.x() + wall_thickness, wall_thickness)),
             ..Default::default()
         })
         .with(Collider::Solid)
         // top
         .spawn(SpriteComponents {
             material: wall_material,
-            translation: Translation(Vec3::new(0.0, bounds.y() / 2.0, 0.0)),
-            sprite: Sprite {
-                size: Vec2::new(bounds.x() + wall_thickness, wall_thickness),
-            },
+            transform: Transform::from_translation(Vec3::new(0.0, bounds.y() / 2.0, 0.0)),
+            sprite: Sprite::new(Vec2::new(bounds.x() + wall_thickness, wall_thickness)),
             ..Default::default()
         })
         .with(Collider::Solid);
@@ -201,8 +184,8 @@ fn setup(
                 // brick
                 .spawn(SpriteComponents {
                     material: materials.add(Color::rgb(0.2, 0.2, 0.8).into()),
-                    sprite: Sprite { size: brick_size },
-                    translation: Translation(brick_position),
+                    sprite: Sprite::new(brick_size),
+                    transform: Transform::from_translation(brick_position),
                     ..Default::default()
                 })
                 .with(Collider::Scorable);
@@ -213,9 +196,9 @@ fn setup(
 fn paddle_movement_system(
     time: Res<Time>,
     keyboard_input: Res<Input<KeyCode>>,
-    mut query: Query<(&Paddle, &mut Translation)>,
+    mut query: Query<(&Paddle, &mut Transform)>,
 ) {
-    for (paddle, mut translation) in &mut query.iter() {
+    for (paddle, mut transform) in &mut query.iter() {
         let mut direction = 0.0;
         if keyboard_input.pressed(KeyCode::Left) {
             direction -= 1.0;
@@ -225,19 +208,20 @@ fn paddle_movement_system(
             direction += 1.0;
         }
 
-        *translation.0.x_mut() += time.delta_seconds * direction * paddle.speed;
+        let translation = transform.translation_mut();
+        *translation.x_mut() += time.delta_seconds * direction * paddle.speed;
 
         // bound the paddle within the walls
-        *translation.0.x_mut() = f32::max(-380.0, f32::min(380.0, translation.0.x()));
+        *translation.x_mut() = f32::max(-380.0, f32::min(380.0, translation.x()));
     }
 }
 
-fn ball_movement_system(time: Res<Time>, mut ball_query: Query<(&Ball, &mut Translation)>) {
+fn ball_movement_system(time: Res<Time>, mut ball_query: Query<(&Ball, &mut Transform)>) {
     // clamp the timestep to stop the ball from escaping when the game starts
     let delta_seconds = f32::min(0.2, time.delta_seconds);
 
-    for (ball, mut translation) in &mut ball_query.iter() {
-        translation.0 += ball.velocity * delta_seconds;
+    for (ball, mut transform) in &mut ball_query.iter() {
+        transform.translate(ball.velocity * delta_seconds);
     }
 }
 
@@ -253,16 +237,21 @@ fn scoreboard_system(scoreboard: Res<Scoreboard>, mut query: Query<&mut Text>) {
 fn ball_collision_system(
     mut commands: Commands,
     mut scoreboard: ResMut<Scoreboard>,
-    mut ball_query: Query<(&mut Ball, &Translation, &Sprite)>,
-    mut collider_query: Query<(Entity, &Collider, &Translation, &Sprite)>,
+    mut ball_query: Query<(&mut Ball, &Transform, &Sprite)>,
+    mut collider_query: Query<(Entity, &Collider, &Transform, &Sprite)>,
 ) {
-    for (mut ball, ball_translation, sprite) in &mut ball_query.iter() {
+    for (mut ball, ball_transform, sprite) in &mut ball_query.iter() {
         let ball_size = sprite.size;
         let velocity = &mut ball.velocity;
 
         // check collision with walls
-        for (collider_entity, collider, translation, sprite) in &mut collider_query.iter() {
-            let collision = collide(ball_translation.0, ball_size, translation.0, sprite.size);
+        for (collider_entity, collider, transform, sprite) in &mut collider_query.iter() {
+            let collision = collide(
+                ball_transform.translation(),
+                ball_size,
+                transform.translation(),
+                sprite.size,
+            );
             if let Some(collision) = collision {
                 // scorable colliders should be despawned and increment the scoreboard on collision
                 if let Collider::Scorable = *collider {
@@ -0,0 +1,435 @@
+use bevy::prelude::{Sprite, Transform, Vec2};
+
+///An axis aligned bounding box, which is what every sprite in the game collides as
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Aabb {
+    pub min: Vec2,
+    pub max: Vec2,
+}
+
+impl Aabb {
+    pub fn new(min: Vec2, max: Vec2) -> Self {
+        Aabb { min, max }
+    }
+
+    pub fn from_center_size(center: Vec2, size: Vec2) -> Self {
+        Aabb {
+            min: center - size / 2.0,
+            max: center + size / 2.0,
+        }
+    }
+
+    ///The box a sprite takes up, ignoring any rotation or scale on the transform
+    pub fn from_sprite(transform: &Transform, sprite: &Sprite) -> Self {
+        Aabb::from_center_size(transform.translation().truncate(), sprite.size)
+    }
+
+    pub fn center(&self) -> Vec2 {
+        (self.min + self.max) / 2.0
+    }
+
+    pub fn size(&self) -> Vec2 {
+        self.max - self.min
+    }
+
+    pub fn half_size(&self) -> Vec2 {
+        self.size() / 2.0
+    }
+
+    ///The box grown by the given amount on every side
+    pub fn expand(&self, amount: Vec2) -> Aabb {
+        Aabb {
+            min: self.min - amount,
+            max: self.max + amount,
+        }
+    }
+
+    pub fn contains(&self, point: Vec2) -> bool {
+        point.x() >= self.min.x()
+            && point.x() <= self.max.x()
+            && point.y() >= self.min.y()
+            && point.y() <= self.max.y()
+    }
+
+    ///Whether the boxes intersect. Boxes that only touch along an edge don't count
+    pub fn overlaps(&self, other: &Aabb) -> bool {
+        self.min.x() < other.max.x()
+            && self.max.x() > other.min.x()
+            && self.min.y() < other.max.y()
+            && self.max.y() > other.min.y()
+    }
+
+    ///The shortest move that pushes this box out of the other one, along whichever axis is the least overlapped. None if they don't overlap
+    pub fn penetration(&self, other: &Aabb) -> Option<Vec2> {
+        if !self.overlaps(other) {
+            return None;
+        }
+
+        let left = other.max.x() - self.min.x();
+        let right = self.max.x() - other.min.x();
+        let down = other.max.y() - self.min.y();
+        let up = self.max.y() - other.min.y();
+
+        let x = if left < right { left } else { -right };
+        let y = if down < up { down } else { -up };
+        if x.abs() < y.abs() {
+            Some(Vec2::new(x, 0.0))
+        } else {
+            Some(Vec2::new(0.0, y))
+        }
+    }
+
+    ///The point in (or on) the box closest to the given point
+    pub fn closest_point(&self, point: Vec2) -> Vec2 {
+        point.max(self.min).min(self.max)
+    }
+}
+
+///Where along a path something first hits a box
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Hit {
+    ///How far along the path the hit happens, with 0 being its start and 1 its end
+    pub time: f32,
+    ///The unit normal of the face that was hit, pointing back out of the box
+    pub normal: Vec2,
+}
+
+///A ray from `origin` along `direction`, where `direction`'s length is how far the ray goes by time 1
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Ray {
+    pub origin: Vec2,
+    pub direction: Vec2,
+}
+
+impl Ray {
+    pub fn new(origin: Vec2, direction: Vec2) -> Self {
+        Ray { origin, direction }
+    }
+
+    pub fn at(&self, time: f32) -> Vec2 {
+        self.origin + self.direction * time
+    }
+
+    ///Where the ray first enters the box between time 0 and `max_time`. A ray starting inside the box hits it at time 0, and so
+    ///does one starting on its edge if it's heading in rather than away or along the edge
+    pub fn intersect_aabb(&self, aabb: &Aabb, max_time: f32) -> Option<Hit> {
+        if aabb.contains(self.origin) {
+            let faces = [
+                (self.origin.x() == aabb.min.x(), -Vec2::unit_x()),
+                (self.origin.x() == aabb.max.x(), Vec2::unit_x()),
+                (self.origin.y() == aabb.min.y(), -Vec2::unit_y()),
+                (self.origin.y() == aabb.max.y(), Vec2::unit_y()),
+            ];
+            //of the faces we start on (two of them in a corner) report whichever we're heading into the most head on
+            let mut face_normal: Option<Vec2> = None;
+            for (on_face, normal) in faces.iter() {
+                if !on_face {
+                    continue;
+                }
+                let heading = self.direction.dot(*normal);
+                if heading >= 0.0 {
+                    return None;
+                }
+                if face_normal.map_or(true, |best| heading < self.direction.dot(best)) {
+                    face_normal = Some(*normal);
+                }
+            }
+
+            //otherwise we're properly inside, where there's no face to speak of, so push back against the way we're heading
+            let normal = face_normal.unwrap_or_else(|| {
+                if self.direction.length_squared() > 0.0 {
+                    -self.direction.normalize()
+                } else {
+                    Vec2::zero()
+                }
+            });
+            return Some(Hit { time: 0.0, normal });
+        }
+
+        //the slab method: the ray is inside the box while it's inside both the x slab and the y slab
+        let mut entry = std::f32::NEG_INFINITY;
+        let mut exit = std::f32::INFINITY;
+        let mut normal = Vec2::zero();
+        let axes = [
+            (
+                self.origin.x(),
+                self.direction.x(),
+                aabb.min.x(),
+                aabb.max.x(),
+                Vec2::unit_x(),
+            ),
+            (
+                self.origin.y(),
+                self.direction.y(),
+                aabb.min.y(),
+                aabb.max.y(),
+                Vec2::unit_y(),
+            ),
+        ];
+        for (origin, direction, min, max, axis) in axes.iter() {
+            if *direction == 0.0 {
+                //running parallel to the slab, so it's either always in it or never
+                if origin < min || origin > max {
+                    return None;
+                }
+                continue;
+            }
+
+            let near = (min - origin) / direction;
+            let far = (max - origin) / direction;
+            let (near, far, face) = if near < far {
+                (near, far, -*axis)
+            } else {
+                (far, near, *axis)
+            };
+            if near > entry {
+                entry = near;
+                normal = face;
+            }
+            exit = exit.min(far);
+        }
+
+        if entry > exit || entry < 0.0 || entry > max_time {
+            return None;
+        }
+        Some(Hit {
+            time: entry,
+            normal,
+        })
+    }
+}
+
+///Where a box moving by `displacement` first hits a stationary one, with the hit time as a fraction of the displacement
+pub fn sweep_aabb(moving: &Aabb, displacement: Vec2, stationary: &Aabb) -> Option<Hit> {
+    //shrinking the moving box to a point and growing the other by as much turns it into a ray cast
+    let expanded = stationary.expand(moving.half_size());
+    Ray::new(moving.center(), displacement).intersect_aabb(&expanded, 1.0)
+}
+
+///How a circle overlaps a box
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct CircleContact {
+    ///The unit normal pointing from the box towards the circle's center
+    pub normal: Vec2,
+    ///How far the circle has to move along the normal to stop overlapping
+    pub depth: f32,
+}
+
+///Whether a circle overlaps (or is touching) the box, and if so how to push it back out
+pub fn circle_aabb(center: Vec2, radius: f32, aabb: &Aabb) -> Option<CircleContact> {
+    let closest = aabb.closest_point(center);
+    let offset = center - closest;
+    let distance_squared = offset.length_squared();
+
+    if distance_squared > 0.0 {
+        if distance_squared > radius * radius {
+            return None;
+        }
+        let distance = distance_squared.sqrt();
+        return Some(CircleContact {
+            normal: offset / distance,
+            depth: radius - distance,
+        });
+    }
+
+    //the center is inside the box, so push out through the nearest face
+    let to_left = center.x() - aabb.min.x();
+    let to_right = aabb.max.x() - center.x();
+    let to_bottom = center.y() - aabb.min.y();
+    let to_top = aabb.max.y() - center.y();
+    let faces = [
+        (to_left, Vec2::new(-1.0, 0.0)),
+        (to_right, Vec2::new(1.0, 0.0)),
+        (to_bottom, Vec2::new(0.0, -1.0)),
+        (to_top, Vec2::new(0.0, 1.0)),
+    ];
+    let (distance, normal) =
+        faces.iter().fold(
+            faces[0],
+            |nearest, face| if face.0 < nearest.0 { *face } else { nearest },
+        );
+    Some(CircleContact {
+        normal,
+        depth: distance + radius,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn unit_box() -> Aabb {
+        Aabb::new(Vec2::new(0.0, 0.0), Vec2::new(10.0, 10.0))
+    }
+
+    #[test]
+    fn boxes_only_touching_dont_overlap() {
+        let touching = Aabb::new(Vec2::new(10.0, 0.0), Vec2::new(20.0, 10.0));
+        assert!(!unit_box().overlaps(&touching));
+        assert_eq!(unit_box().penetration(&touching), None);
+
+        let overlapping = Aabb::new(Vec2::new(9.0, 9.0), Vec2::new(20.0, 20.0));
+        assert!(unit_box().overlaps(&overlapping));
+        assert!(overlapping.overlaps(&unit_box()));
+    }
+
+    #[test]
+    fn penetration_pushes_out_along_the_least_overlapped_axis() {
+        //sticking 2 into the box's right side and 6 into its top, so it's pushed back out to the right
+        let right = Aabb::new(Vec2::new(8.0, 4.0), Vec2::new(18.0, 14.0));
+        assert_eq!(right.penetration(&unit_box()), Some(Vec2::new(2.0, 0.0)));
+
+        //1 into the bottom
+        let below = Aabb::new(Vec2::new(2.0, -9.0), Vec2::new(8.0, 1.0));
+        assert_eq!(below.penetration(&unit_box()), Some(Vec2::new(0.0, -1.0)));
+
+        //moving by the penetration leaves them only touching
+        let pushed = Aabb::new(
+            right.min + Vec2::new(2.0, 0.0),
+            right.max + Vec2::new(2.0, 0.0),
+        );
+        assert!(!pushed.overlaps(&unit_box()));
+    }
+
+    #[test]
+    fn ray_hits_the_face_it_enters_through() {
+        let hit = Ray::new(Vec2::new(-5.0, 5.0), Vec2::new(10.0, 0.0))
+            .intersect_aabb(&unit_box(), 1.0)
+            .unwrap();
+        assert_eq!(hit.time, 0.5);
+        assert_eq!(hit.normal, Vec2::new(-1.0, 0.0));
+
+        let hit = Ray::new(Vec2::new(5.0, 20.0), Vec2::new(0.0, -20.0))
+            .intersect_aabb(&unit_box(), 1.0)
+            .unwrap();
+        assert_eq!(hit.time, 0.5);
+        assert_eq!(hit.normal, Vec2::new(0.0, 1.0));
+
+        //coming in at an angle it's the later of the two slab entries that counts
+        let hit = Ray::new(Vec2::new(-1.0, -10.0), Vec2::new(4.0, 20.0))
+            .intersect_aabb(&unit_box(), 1.0)
+            .unwrap();
+        assert_eq!(hit.time, 0.5);
+        assert_eq!(hit.normal, Vec2::new(0.0, -1.0));
+    }
+
+    #[test]
+    fn ray_misses_what_it_passes_by_or_doesnt_reach() {
+        let aabb = unit_box();
+        //passes above
+        assert_eq!(
+            Ray::new(Vec2::new(-5.0, 15.0), Vec2::new(20.0, 0.0)).intersect_aabb(&aabb, 1.0),
+            None
+        );
+        //heading away
+        assert_eq!(
+            Ray::new(Vec2::new(-5.0, 5.0), Vec2::new(-10.0, 0.0)).intersect_aabb(&aabb, 1.0),
+            None
+        );
+        //would get there, but not before max_time
+        assert_eq!(
+            Ray::new(Vec2::new(-5.0, 5.0), Vec2::new(1.0, 0.0)).intersect_aabb(&aabb, 1.0),
+            None
+        );
+        //misses diagonally past the corner
+        assert_eq!(
+            Ray::new(Vec2::new(-5.0, 5.0), Vec2::new(10.0, 20.0)).intersect_aabb(&aabb, 1.0),
+            None
+        );
+    }
+
+    #[test]
+    fn ray_starting_inside_hits_straight_away() {
+        let hit = Ray::new(Vec2::new(5.0, 5.0), Vec2::new(3.0, 4.0))
+            .intersect_aabb(&unit_box(), 1.0)
+            .unwrap();
+        assert_eq!(hit.time, 0.0);
+        assert!((hit.normal - Vec2::new(-0.6, -0.8)).length() < 1e-6);
+    }
+
+    #[test]
+    fn ray_starting_on_the_edge_only_hits_when_heading_in() {
+        let aabb = unit_box();
+        let hit = Ray::new(Vec2::new(0.0, 5.0), Vec2::new(1.0, 0.5))
+            .intersect_aabb(&aabb, 1.0)
+            .unwrap();
+        assert_eq!(hit.time, 0.0);
+        assert_eq!(hit.normal, Vec2::new(-1.0, 0.0));
+
+        //away from the face and along it
+        assert_eq!(
+            Ray::new(Vec2::new(0.0, 5.0), Vec2::new(-1.0, 0.5)).intersect_aabb(&aabb, 1.0),
+            None
+        );
+        assert_eq!(
+            Ray::new(Vec2::new(0.0, 5.0), Vec2::new(0.0, 1.0)).intersect_aabb(&aabb, 1.0),
+            None
+        );
+        assert_eq!(
+            Ray::new(Vec2::new(10.0, 10.0), Vec2::zero()).intersect_aabb(&aabb, 1.0),
+            None
+        );
+
+        //in a corner it has to be heading in through both faces
+        let hit = Ray::new(Vec2::new(10.0, 10.0), Vec2::new(-1.0, -3.0))
+            .intersect_aabb(&aabb, 1.0)
+            .unwrap();
+        assert_eq!(hit.normal, Vec2::new(0.0, 1.0));
+        assert_eq!(
+            Ray::new(Vec2::new(10.0, 10.0), Vec2::new(-1.0, 3.0)).intersect_aabb(&aabb, 1.0),
+            None
+        );
+    }
+
+    #[test]
+    fn sweep_finds_when_the_boxes_first_touch() {
+        let moving = Aabb::from_center_size(Vec2::new(-10.0, 5.0), Vec2::new(4.0, 4.0));
+        let hit = sweep_aabb(&moving, Vec2::new(16.0, 0.0), &unit_box()).unwrap();
+        assert_eq!(hit.time, 0.5);
+        assert_eq!(hit.normal, Vec2::new(-1.0, 0.0));
+
+        //passes by above it
+        let moving = Aabb::from_center_size(Vec2::new(-10.0, 13.0), Vec2::new(4.0, 4.0));
+        assert_eq!(sweep_aabb(&moving, Vec2::new(16.0, 0.0), &unit_box()), None);
+        //already touching and moving apart
+        let moving = Aabb::from_center_size(Vec2::new(-2.0, 5.0), Vec2::new(4.0, 4.0));
+        assert_eq!(sweep_aabb(&moving, Vec2::new(-5.0, 0.0), &unit_box()), None);
+        //too short a move to get there
+        let moving = Aabb::from_center_size(Vec2::new(-10.0, 5.0), Vec2::new(4.0, 4.0));
+        assert_eq!(sweep_aabb(&moving, Vec2::new(4.0, 0.0), &unit_box()), None);
+    }
+
+    #[test]
+    fn circle_outside_the_box_doesnt_touch() {
+        assert_eq!(circle_aabb(Vec2::new(-3.0, 5.0), 2.0, &unit_box()), None);
+        //beyond the corner, even though it's within the radius of both faces' lines
+        assert_eq!(circle_aabb(Vec2::new(-2.0, -2.0), 2.5, &unit_box()), None);
+    }
+
+    #[test]
+    fn circle_overlapping_an_edge_or_corner_is_pushed_straight_out() {
+        let contact = circle_aabb(Vec2::new(5.0, 11.0), 2.0, &unit_box()).unwrap();
+        assert_eq!(contact.normal, Vec2::new(0.0, 1.0));
+        assert_eq!(contact.depth, 1.0);
+
+        //just touching counts, with nothing to push out
+        let contact = circle_aabb(Vec2::new(12.0, 5.0), 2.0, &unit_box()).unwrap();
+        assert_eq!(contact.normal, Vec2::new(1.0, 0.0));
+        assert_eq!(contact.depth, 0.0);
+
+        let contact = circle_aabb(Vec2::new(13.0, -4.0), 6.0, &unit_box()).unwrap();
+        assert!((contact.normal - Vec2::new(0.6, -0.8)).length() < 1e-6);
+        assert!((contact.depth - 1.0).abs() < 1e-6);
+    }
+
+    #[test]
+    fn circle_centered_inside_leaves_through_the_nearest_face() {
+        let contact = circle_aabb(Vec2::new(8.0, 4.0), 1.0, &unit_box()).unwrap();
+        assert_eq!(contact.normal, Vec2::new(1.0, 0.0));
+        assert_eq!(contact.depth, 3.0);
+
+        let contact = circle_aabb(Vec2::new(4.0, 1.0), 1.0, &unit_box()).unwrap();
+        assert_eq!(contact.normal, Vec2::new(0.0, -1.0));
+        assert_eq!(contact.depth, 2.0);
+    }
+}
@@ -1,29 +1,99 @@
-use bevy::prelude::*;
+use bevy::{
+    app::ScheduleRunnerPlugin, core::CorePlugin, prelude::*, type_registry::TypeRegistryPlugin,
+};
+use serde::Deserialize;
+use std::{
+    fs,
+    io::{self, BufRead},
+    path::{Path, PathBuf},
+    sync::{
+        mpsc::{channel, Receiver},
+        Mutex,
+    },
+    thread,
+    time::Duration,
+};
 
-pub fn run() {
+pub fn run(plugin: HelloPlugin) {
     //Being able to turn a method into a system is done via "trait extension"
     //http://xion.io/post/code/rust-extension-traits.html
     App::build()
         //adds default things like Windowing, Input Controls, and Core Plugin (an event loop)
         .add_default_plugins()
-        .add_plugin(HelloPlugin)
+        .add_plugin(plugin)
+        .run();
+}
+
+//Greeting people doesn't need a window, just time passing. So instead of the default plugins this only adds the Core Plugin
+//(for Time) and a runner that loops the schedule 60 times a second, which is what the window's event loop was doing for us
+pub fn run_headless(plugin: HelloPlugin) {
+    App::build()
+        //the core plugin registers its types, so it needs the registry in place first
+        .add_plugin(TypeRegistryPlugin::default())
+        .add_plugin(CorePlugin)
+        .add_plugin(ScheduleRunnerPlugin::run_loop(Duration::from_secs_f64(
+            1.0 / 60.0,
+        )))
+        .add_plugin(plugin)
         .run();
 }
 
 //Plugins represent the unit of modularity into Brevvy, portions of functionality that can be assembled together (or sliced out and replaced)
 //This is demonstrating moving our hello logic into such a plugin
-pub struct HelloPlugin;
+//Its fields are the plugin's configuration, so the same plugin can greet different people in different ways
+pub struct HelloPlugin {
+    /// File to load the people from (see assets/people.ron). Without one the three people from the Bevy book get greeted
+    pub people_path: Option<PathBuf>,
+    /// Seconds between greetings
+    pub interval_seconds: f32,
+    /// How to greet someone, with {name} swapped for their name (or nickname if they have one)
+    pub template: String,
+    /// Whether to take commands from stdin to add and remove people while they're being greeted
+    pub console: bool,
+    /// Where the greetings go
+    pub sink: GreetingSink,
+}
+
+impl Default for HelloPlugin {
+    fn default() -> Self {
+        HelloPlugin {
+            people_path: None,
+            interval_seconds: 2.0,
+            template: "hello {name}!".to_string(),
+            console: false,
+            sink: GreetingSink::Stdout,
+        }
+    }
+}
 
 impl Plugin for HelloPlugin {
     //this build method now consolidates the initial state (people names), how to great them, and also
     //a timer to indicate when to execute the greeting. By putting this all into a plugin it's a self-contained aspect of our app: Greeting people
     fn build(&self, app: &mut AppBuilder) {
+        //Loading happens here rather than in the startup system since the plugin is what knows where to load from,
+        //the startup system then gets whatever was loaded as a resource
+        let people = match &self.people_path {
+            Some(path) => load_people(path).unwrap_or_else(|error| {
+                log::warn!("Couldn't load people from {}: {}", path.display(), error);
+                default_people()
+            }),
+            None => default_people(),
+        };
+
         // the reason we call from_seconds with the true flag is to make the timer repeat itself
-        app.add_resource(GreetTimer(Timer::from_seconds(2.0, true)))
+        app.add_resource(GreetTimer(Timer::from_seconds(self.interval_seconds, true)))
+            .add_resource(GreetTemplate(self.template.clone()))
+            .add_resource(People(people))
+            .add_resource(self.sink.clone())
             //Startup System is only ran once - at startup
             .add_startup_system(add_people.system())
             //normal systems run continuously once event looped
             .add_system(greet_people.system());
+
+        if self.console {
+            app.add_resource(Console::start())
+                .add_system(console_system.system());
+        }
     }
 }
 
@@ -56,16 +126,31 @@ impl Plugin for HelloPlugin {
 //Res<Time> gives us access to time as a global resource
 //ResMut<GreetTimer> gives us an accumulator of delta time to determine when we've accumulated enough
 //Query<(&Person, &Name)> is the query that needs to be executed to find all relevant Entities having the specified components to then operate upon
+//The Option<&...> parts match whether or not the entity has that component, handing back None when it doesn't
 fn greet_people(
     time: Res<Time>,
     mut timer: ResMut<GreetTimer>,
-    mut query: Query<(&Person, &Name)>,
+    template: Res<GreetTemplate>,
+    mut sink: ResMut<GreetingSink>,
+    mut query: Query<(
+        &Person,
+        &Name,
+        Option<&Nickname>,
+        Option<&Greeting>,
+        Option<&Pet>,
+    )>,
 ) {
     timer.0.tick(time.delta_seconds);
     if timer.0.finished {
-        for (_person, name) in &mut query.iter() {
+        for (_person, name, nickname, greeting, pet) in &mut query.iter() {
             //can also use With<Component> and Without<Component> to narrow down / branch within the given query
-            println!("hello {}!", name.0);
+            //someone's own greeting wins over the plugin's one
+            let template = greeting.map_or(&template.0, |greeting| &greeting.0);
+            let called = nickname.map_or(&name.0, |nickname| &nickname.0);
+            sink.write(template.replace("{name}", called));
+            if let Some(pet) = pet {
+                sink.write(format!("  and hello to {} too!", pet.0));
+            }
         }
     }
 }
@@ -74,6 +159,30 @@ fn greet_people(
 //This needs to be a distinct type so it is can be refenced as a unique type for Resource consumption
 struct GreetTimer(Timer);
 
+//The greeting for everyone without one of their own
+struct GreetTemplate(String);
+
+//Greetings used to go straight to println, which is fine to watch but not something a test can look at.
+//Going through a resource lets whoever builds the app decide where they end up
+#[derive(Clone, Debug)]
+pub enum GreetingSink {
+    Stdout,
+    /// Kept in order, to be looked at afterwards
+    Memory(Vec<String>),
+    /// Through the log crate at info level, to whichever logger the app set up
+    Log,
+}
+
+impl GreetingSink {
+    fn write(&mut self, line: String) {
+        match self {
+            GreetingSink::Stdout => println!("{}", line),
+            GreetingSink::Memory(lines) => lines.push(line),
+            GreetingSink::Log => log::info!("{}", line),
+        }
+    }
+}
+
 //A person component
 struct Person;
 
@@ -81,11 +190,295 @@ struct Person;
 //So ECS practice is to separate Name to its own component so it can be composed into mutiple entities
 struct Name(String);
 
+//The optional parts of a person. Only the people that have one get the component,
+//rather than everyone carrying an Option field that's mostly None
+struct Nickname(String);
+struct Greeting(String);
+struct Pet(String);
+
+//How a person is written down in a people file, e.g. (name: "Renzo Hume", pet: Some("Biscuit"))
+#[derive(Clone, Debug, Deserialize)]
+struct PersonRecord {
+    name: String,
+    #[serde(default)]
+    nickname: Option<String>,
+    #[serde(default)]
+    greeting: Option<String>,
+    #[serde(default)]
+    pet: Option<String>,
+}
+
+impl PersonRecord {
+    fn named(name: &str) -> Self {
+        PersonRecord {
+            name: name.to_string(),
+            nickname: None,
+            greeting: None,
+            pet: None,
+        }
+    }
+}
+
+//The people to add at startup
+struct People(Vec<PersonRecord>);
+
+fn load_people(path: &Path) -> io::Result<Vec<PersonRecord>> {
+    let contents = fs::read_to_string(path)?;
+    ron::de::from_str(&contents)
+        .map_err(|error| io::Error::new(io::ErrorKind::InvalidData, error.to_string()))
+}
+
+fn default_people() -> Vec<PersonRecord> {
+    vec![
+        PersonRecord::named("Elaina Proctor"),
+        PersonRecord::named("Renzo Hume"),
+        PersonRecord::named("Zayna Nieves"),
+    ]
+}
+
 //Initializing a set of People components to our App
-fn add_people(mut commands: Commands) {
-    commands
-        //spawning Entities that have a Person and Name components
-        .spawn((Person, Name("Elaina Proctor".to_string())))
-        .spawn((Person, Name("Renzo Hume".to_string())))
-        .spawn((Person, Name("Zayna Nieves".to_string())));
+fn add_people(mut commands: Commands, people: Res<People>) {
+    for person in people.0.iter() {
+        spawn_person(&mut commands, person);
+    }
+}
+
+//Spawns an Entity with Person and Name components, plus whichever of the optional components the person has
+//`with` adds a component onto whatever was spawned last
+fn spawn_person(commands: &mut Commands, person: &PersonRecord) {
+    commands.spawn((Person, Name(person.name.clone())));
+    if let Some(nickname) = &person.nickname {
+        commands.with(Nickname(nickname.clone()));
+    }
+    if let Some(greeting) = &person.greeting {
+        commands.with(Greeting(greeting.clone()));
+    }
+    if let Some(pet) = &person.pet {
+        commands.with(Pet(pet.clone()));
+    }
+}
+
+const CONSOLE_HELP: &str = "Type \"add <name>\", \"remove <name>\" or \"list\"";
+
+//Reading stdin blocks until a whole line comes in, which would stall every system, so a thread of its own does the reading
+//and hands each line over through a channel. Resources have to be shareable between threads (Sync) which a channel's
+//receiving end isn't, so it sits behind a Mutex
+struct Console(Mutex<Receiver<String>>);
+
+impl Console {
+    fn start() -> Self {
+        let (sender, receiver) = channel();
+        thread::spawn(move || {
+            let stdin = io::stdin();
+            for line in stdin.lock().lines() {
+                //stdin closing or the app going away both mean there's nothing left to do
+                let line = match line {
+                    Ok(line) => line,
+                    Err(_) => break,
+                };
+                if sender.send(line).is_err() {
+                    break;
+                }
+            }
+        });
+        println!("{}", CONSOLE_HELP);
+        Console(Mutex::new(receiver))
+    }
+}
+
+//Spawning and despawning people while greet_people is running. Commands are queued up and only applied once the stage is done,
+//so greet_people never sees a half added person
+fn console_system(
+    mut commands: Commands,
+    console: Res<Console>,
+    mut query: Query<(Entity, &Person, &Name)>,
+) {
+    let lines: Vec<String> = console.0.lock().unwrap().try_iter().collect();
+    for line in lines {
+        for reply in run_console_line(&line, &mut commands, &mut query) {
+            println!("{}", reply);
+        }
+    }
+}
+
+//What a line typed into the console does, handing back what to say about it
+fn run_console_line(
+    line: &str,
+    commands: &mut Commands,
+    query: &mut Query<(Entity, &Person, &Name)>,
+) -> Vec<String> {
+    let line = line.trim();
+    let (command, argument) = match line.find(' ') {
+        Some(space) => (&line[..space], line[space + 1..].trim()),
+        None => (line, ""),
+    };
+
+    match command {
+        "add" if !argument.is_empty() => {
+            spawn_person(commands, &PersonRecord::named(argument));
+            vec![format!("Added {}", argument)]
+        }
+        "remove" if !argument.is_empty() => {
+            let mut removed = false;
+            for (entity, _person, name) in &mut query.iter() {
+                if name.0 == argument {
+                    commands.despawn(entity);
+                    removed = true;
+                }
+            }
+            if removed {
+                vec![format!("Removed {}", argument)]
+            } else {
+                vec![format!("There's nobody called {}", argument)]
+            }
+        }
+        "list" => {
+            let mut names = Vec::new();
+            for (_entity, _person, name) in &mut query.iter() {
+                names.push(format!("  {}", name.0));
+            }
+            names
+        }
+        "" => Vec::new(),
+        _ => vec![CONSOLE_HELP.to_string()],
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::mpsc::Sender;
+
+    //For checking what gets greeted without waiting on the clock. There's no runner and no time system here, so nothing happens
+    //until advance is called, and then exactly as much time passes as it's told
+    struct SteppedHelloApp {
+        app: App,
+    }
+
+    impl SteppedHelloApp {
+        /// Builds the plugin into a bare app and runs its startup systems, so everyone's been added before the first step
+        fn new(plugin: HelloPlugin) -> Self {
+            let mut builder = App::build();
+            builder.add_resource(Time::default()).add_plugin(plugin);
+            Self::start(builder)
+        }
+
+        /// Like new, but with a console reading lines from the sender handed back rather than from stdin
+        fn with_console(plugin: HelloPlugin) -> (Self, Sender<String>) {
+            let (sender, receiver) = channel();
+            let mut builder = App::build();
+            builder
+                .add_resource(Time::default())
+                .add_plugin(plugin)
+                .add_resource(Console(Mutex::new(receiver)))
+                .add_system(console_system.system());
+            (Self::start(builder), sender)
+        }
+
+        fn start(builder: AppBuilder) -> Self {
+            let mut app = builder.app;
+            app.startup_schedule
+                .initialize(&mut app.world, &mut app.resources);
+            app.startup_executor.run(
+                &mut app.startup_schedule,
+                &mut app.world,
+                &mut app.resources,
+            );
+            SteppedHelloApp { app }
+        }
+
+        /// Moves time on by the given seconds and runs the systems once
+        fn advance(&mut self, seconds: f32) {
+            {
+                let mut time = self.app.resources.get_mut::<Time>().unwrap();
+                time.delta = Duration::from_secs_f32(seconds);
+                time.delta_seconds = seconds;
+                time.delta_seconds_f64 = seconds as f64;
+                time.seconds_since_startup += seconds as f64;
+            }
+            self.app.update();
+        }
+
+        /// Everything greeted so far, if the plugin's sink is GreetingSink::Memory
+        fn greetings(&self) -> Vec<String> {
+            match &*self.app.resources.get::<GreetingSink>().unwrap() {
+                GreetingSink::Memory(lines) => lines.clone(),
+                _ => Vec::new(),
+            }
+        }
+    }
+
+    //Steps the three default people through a few intervals in small steps, checking each of them got greeted exactly once per interval
+    #[test]
+    fn everyone_is_greeted_once_per_interval() {
+        const INTERVALS: usize = 3;
+        //a power of two, so the steps add back up to exactly the interval rather than falling a rounding error short
+        const STEPS_PER_INTERVAL: usize = 8;
+        let plugin = HelloPlugin {
+            template: "{name}".to_string(),
+            sink: GreetingSink::Memory(Vec::new()),
+            ..Default::default()
+        };
+        let step = plugin.interval_seconds / STEPS_PER_INTERVAL as f32;
+        let mut app = SteppedHelloApp::new(plugin);
+        let names: Vec<String> = default_people()
+            .into_iter()
+            .map(|person| person.name)
+            .collect();
+
+        let mut expected = Vec::new();
+        for interval in 1..=INTERVALS {
+            for step_in_interval in 1..=STEPS_PER_INTERVAL {
+                app.advance(step);
+                //nobody's greeted until the interval's up
+                if step_in_interval < STEPS_PER_INTERVAL {
+                    assert_eq!(app.greetings(), expected, "during interval {}", interval);
+                }
+            }
+            expected.extend(names.iter().cloned());
+            assert_eq!(app.greetings(), expected, "after interval {}", interval);
+        }
+    }
+
+    //The names greeted over the next interval, sorted since the order people are greeted in isn't fixed
+    fn greeted_next_interval(app: &mut SteppedHelloApp, interval_seconds: f32) -> Vec<String> {
+        let before = app.greetings().len();
+        app.advance(interval_seconds);
+        let mut greeted = app.greetings().split_off(before);
+        greeted.sort();
+        greeted
+    }
+
+    #[test]
+    fn console_adds_and_removes_who_gets_greeted() {
+        let plugin = HelloPlugin {
+            template: "{name}".to_string(),
+            sink: GreetingSink::Memory(Vec::new()),
+            ..Default::default()
+        };
+        let interval_seconds = plugin.interval_seconds;
+        let (mut app, console) = SteppedHelloApp::with_console(plugin);
+
+        console.send("add Ada Lovelace".to_string()).unwrap();
+        //people are only added (or removed) once the stage is over, so they're in place before the next interval starts
+        app.advance(0.0);
+        assert_eq!(
+            greeted_next_interval(&mut app, interval_seconds),
+            vec![
+                "Ada Lovelace",
+                "Elaina Proctor",
+                "Renzo Hume",
+                "Zayna Nieves"
+            ]
+        );
+
+        console.send("remove Renzo Hume".to_string()).unwrap();
+        //nobody by that name, so nobody else goes
+        console.send("remove Renzo".to_string()).unwrap();
+        app.advance(0.0);
+        assert_eq!(
+            greeted_next_interval(&mut app, interval_seconds),
+            vec!["Ada Lovelace", "Elaina Proctor", "Zayna Nieves"]
+        );
+    }
 }
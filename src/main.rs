@@ -1,13 +1,391 @@
-//mod intro_example;
-//mod breakout_example;
+mod breakout_example;
+mod intro_example;
 mod my_breakout;
 
 //To do use crate::vec3_extension::*; in my_breakout we must first "mod" in vec3_extension here
 //main.rs and lib.rs have special rules for "bringing" in things though somehow we're able to "use" Bevy in without a mod here
+mod geometry;
 mod vec3_extension;
 
+use bevy::prelude::*;
+use std::{
+    env,
+    path::PathBuf,
+    process,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+//Every example that can be launched from the command line. Whatever gets run when no example is named goes first
+const EXAMPLES: &[Example] = &[
+    Example {
+        name: "breakout",
+        description: "The classic game, or the level from --level",
+        headless: false,
+        plugins: Some(breakout_plugins),
+        run: |options| run_windowed(options, "Breakout", breakout_plugins),
+    },
+    Example {
+        name: "endless",
+        description: "Breakout over generated levels from --seed (a random one otherwise)",
+        headless: false,
+        plugins: Some(endless_plugins),
+        run: |options| run_windowed(options, "Breakout - Endless", endless_plugins),
+    },
+    Example {
+        name: "versus",
+        description: "Two players on one keyboard",
+        headless: false,
+        plugins: Some(versus_plugins),
+        run: |options| run_windowed(options, "Breakout - Versus", versus_plugins),
+    },
+    Example {
+        name: "online",
+        description: "Versus over the network, saving the match to --replay if given",
+        headless: false,
+        plugins: Some(online_plugins),
+        run: |options| run_windowed(options, "Breakout - Online", online_plugins),
+    },
+    Example {
+        name: "replay",
+        description: "Plays back the online match saved in --replay and prints how it went",
+        headless: true,
+        plugins: None,
+        run: |options| {
+            let replay = options
+                .replay
+                .as_ref()
+                .ok_or_else(|| "The replay example needs a --replay file".to_string())?;
+            my_breakout::run_replay(replay)
+                .map_err(|error| format!("Couldn't load {}: {}", replay.display(), error))
+        },
+    },
+    Example {
+        name: "editor",
+        description: "Edits the level in --level (levels/custom.level otherwise)",
+        headless: false,
+        plugins: Some(editor_plugins),
+        run: |options| run_windowed(options, "Breakout - Editor", editor_plugins),
+    },
+    Example {
+        name: "unattended",
+        description: "The computer plays the classic game by itself and quits when it's done, running the console commands in --script first",
+        headless: true,
+        plugins: Some(unattended_plugins),
+        run: |options| {
+            if options.headless {
+                let mut app = App::build();
+                my_breakout::add_headless(&mut app);
+                unattended_plugins(&mut app, options)?;
+                app.run();
+                Ok(())
+            } else {
+                run_windowed(options, "Breakout - Unattended", unattended_plugins)
+            }
+        },
+    },
+    Example {
+        name: "benchmark",
+        description: "Times the ball collision checks of a headless game on a dense level, with and without the broadphase grid",
+        headless: true,
+        plugins: None,
+        run: |_options| {
+            my_breakout::run_broadphase_benchmark();
+            Ok(())
+        },
+    },
+    Example {
+        name: "upstream",
+        description: "Bevy's own breakout example, which my_breakout started from",
+        headless: false,
+        plugins: Some(upstream_plugins),
+        run: |options| run_windowed(options, "Breakout - Upstream", upstream_plugins),
+    },
+    Example {
+        name: "intro",
+        description: "The Bevy book's intro, greeting the people in --people (assets/people.ron otherwise) every couple of seconds",
+        headless: true,
+        plugins: Some(intro_plugins),
+        run: |options| {
+            if options.headless {
+                intro_example::run_headless(intro_plugin(options));
+            } else {
+                intro_example::run(intro_plugin(options));
+            }
+            Ok(())
+        },
+    },
+];
+
+/// Adds what an example is made of to an app that's already been given its window, or been set up to run without one
+type Plugins = fn(&mut AppBuilder, &Options) -> Result<(), String>;
+
+struct Example {
+    name: &'static str,
+    description: &'static str,
+    /// Whether it can run without a window
+    headless: bool,
+    /// What it adds to an app, for the examples that run one
+    plugins: Option<Plugins>,
+    run: fn(&Options) -> Result<(), String>,
+}
+
+fn breakout_plugins(app: &mut AppBuilder, options: &Options) -> Result<(), String> {
+    match &options.level {
+        Some(level) => my_breakout::add_level(app, level)
+            .map_err(|error| format!("Couldn't load {}: {}", level.display(), error)),
+        None => {
+            my_breakout::add_classic(app);
+            Ok(())
+        }
+    }
+}
+
+fn endless_plugins(app: &mut AppBuilder, options: &Options) -> Result<(), String> {
+    let seed = options.seed.unwrap_or_else(random_seed);
+    println!("Seed {}", seed);
+    my_breakout::add_endless(app, seed);
+    Ok(())
+}
+
+fn versus_plugins(app: &mut AppBuilder, _options: &Options) -> Result<(), String> {
+    my_breakout::add_versus(app);
+    Ok(())
+}
+
+fn online_plugins(app: &mut AppBuilder, options: &Options) -> Result<(), String> {
+    my_breakout::add_online(app, options.replay.clone());
+    Ok(())
+}
+
+fn editor_plugins(app: &mut AppBuilder, options: &Options) -> Result<(), String> {
+    let level = options
+        .level
+        .clone()
+        .unwrap_or_else(|| PathBuf::from("levels/custom.level"));
+    my_breakout::add_editor(app, level);
+    Ok(())
+}
+
+fn unattended_plugins(app: &mut AppBuilder, options: &Options) -> Result<(), String> {
+    my_breakout::add_unattended(app, my_breakout::AiSkill::perfect(), options.script.clone());
+    Ok(())
+}
+
+fn upstream_plugins(app: &mut AppBuilder, _options: &Options) -> Result<(), String> {
+    app.add_plugin(breakout_example::BreakoutPlugin);
+    Ok(())
+}
+
+fn intro_plugins(app: &mut AppBuilder, options: &Options) -> Result<(), String> {
+    app.add_plugin(intro_plugin(options));
+    Ok(())
+}
+
+fn intro_plugin(options: &Options) -> intro_example::HelloPlugin {
+    intro_example::HelloPlugin {
+        people_path: Some(
+            options
+                .people
+                .clone()
+                .unwrap_or_else(|| PathBuf::from("assets/people.ron")),
+        ),
+        console: true,
+        ..Default::default()
+    }
+}
+
+/// Runs an example in a window titled after it
+fn run_windowed(options: &Options, title: &str, plugins: Plugins) -> Result<(), String> {
+    let mut app = App::build();
+    //The window settings have to be in place before the window plugin (part of the default plugins) opens the window
+    app.add_resource(options.window(title))
+        //This does a lot of stuff, setting up sprite & UI rendering among many other things
+        //https://docs.rs/bevy/0.1.3/src/bevy/add_default_plugins.rs.html#7-37
+        //UI Plugin setup: https://docs.rs/bevy_ui/0.1.3/src/bevy_ui/lib.rs.html#39-55
+        //Sprite Setup: https://docs.rs/bevy_sprite/0.1.3/src/bevy_sprite/lib.rs.html#43-67
+        //Sprite System: https://docs.rs/bevy_sprite/0.1.3/src/bevy_sprite/sprite.rs.html#21-34
+        //Sprites added to the Render graph here: https://docs.rs/bevy_sprite/0.1.3/src/bevy_sprite/lib.rs.html#55
+        //The specifics of how the rendering plugin sets up rendering systems: https://docs.rs/bevy_render/0.1.3/src/bevy_render/lib.rs.html#80-179
+        .add_default_plugins();
+    plugins(&mut app, options)?;
+    app.run();
+    Ok(())
+}
+
+/// Everything that can be set from the command line
+#[derive(Default)]
+struct Options {
+    example: Option<String>,
+    list: bool,
+    help: bool,
+    window_size: Option<(u32, u32)>,
+    seed: Option<u64>,
+    level: Option<PathBuf>,
+    replay: Option<PathBuf>,
+    people: Option<PathBuf>,
+    script: Option<PathBuf>,
+    headless: bool,
+}
+
+impl Options {
+    fn parse(mut args: impl Iterator<Item = String>) -> Result<Options, String> {
+        let mut options = Options::default();
+        let mut width = None;
+        let mut height = None;
+        while let Some(arg) = args.next() {
+            let mut value =
+                |name: &str| args.next().ok_or_else(|| format!("{} needs a value", name));
+            match arg.as_str() {
+                "-h" | "--help" | "help" => options.help = true,
+                "-l" | "--list" | "list" => options.list = true,
+                "-e" | "--example" => options.example = Some(value(&arg)?),
+                "--width" => width = Some(parse_number(&arg, &value(&arg)?)?),
+                "--height" => height = Some(parse_number(&arg, &value(&arg)?)?),
+                "--seed" => options.seed = Some(parse_number(&arg, &value(&arg)?)?),
+                "--level" => options.level = Some(PathBuf::from(value(&arg)?)),
+                "--replay" => options.replay = Some(PathBuf::from(value(&arg)?)),
+                "--people" => options.people = Some(PathBuf::from(value(&arg)?)),
+                "--script" => options.script = Some(PathBuf::from(value(&arg)?)),
+                "--headless" => options.headless = true,
+                flag if flag.starts_with('-') => return Err(format!("Unknown option {}", flag)),
+                //a bare word is the example to run, so `run endless` works as well as `run --example endless`
+                name if options.example.is_none() => options.example = Some(name.to_string()),
+                extra => {
+                    return Err(format!(
+                        "Only one example can be run at a time, got {} too",
+                        extra
+                    ))
+                }
+            }
+        }
+
+        options.window_size = match (width, height) {
+            (None, None) => None,
+            (Some(width), Some(height)) => Some((width, height)),
+            _ => return Err("--width and --height have to be given together".to_string()),
+        };
+        Ok(options)
+    }
+
+    /// The window an example should open, titled after it
+    fn window(&self, title: &str) -> WindowDescriptor {
+        let mut window = WindowDescriptor {
+            title: title.to_string(),
+            ..Default::default()
+        };
+        if let Some((width, height)) = self.window_size {
+            window.width = width;
+            window.height = height;
+        }
+        window
+    }
+}
+
+fn parse_number<T: std::str::FromStr>(name: &str, value: &str) -> Result<T, String> {
+    value
+        .parse()
+        .map_err(|_| format!("{} expects a number, got {}", name, value))
+}
+
+fn random_seed() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |time| time.as_nanos() as u64)
+}
+
+fn print_usage() {
+    println!("Usage: bevy_playground [EXAMPLE] [OPTIONS]");
+    println!();
+    println!(
+        "Runs the given example, or {} if none is given",
+        EXAMPLES[0].name
+    );
+    println!();
+    println!("Options:");
+    println!("  -e, --example <NAME>     The example to run");
+    println!("  -l, --list               Lists the examples");
+    println!("      --width <PIXELS>     Window width (needs --height too)");
+    println!("      --height <PIXELS>    Window height (needs --width too)");
+    println!("      --seed <NUMBER>      Seed for generated levels");
+    println!("      --level <FILE>       Level file to play or edit");
+    println!("      --replay <FILE>      Replay file to record to or play back");
+    println!("      --people <FILE>      People file for the intro to greet");
+    println!("      --script <FILE>      Console commands for an unattended game to run");
+    println!("      --headless           Runs without a window, for the examples that can");
+    println!("  -h, --help               Prints this");
+    println!();
+    print_examples();
+}
+
+fn print_examples() {
+    println!("Examples:");
+    for example in EXAMPLES {
+        let headless = if example.headless {
+            " (can run headless)"
+        } else {
+            ""
+        };
+        println!("  {:<12} {}{}", example.name, example.description, headless);
+    }
+}
+
 fn main() {
-    //intro_example::run();
-    //breakout_example::run();
-    my_breakout::run();
+    let options = match Options::parse(env::args().skip(1)) {
+        Ok(options) => options,
+        Err(error) => {
+            eprintln!("{}", error);
+            eprintln!("Run with --help to see the options");
+            process::exit(2);
+        }
+    };
+
+    if options.help {
+        print_usage();
+        return;
+    }
+    if options.list {
+        print_examples();
+        return;
+    }
+
+    let name = options.example.as_deref().unwrap_or(EXAMPLES[0].name);
+    let example = match EXAMPLES.iter().find(|example| example.name == name) {
+        Some(example) => example,
+        None => {
+            eprintln!("There's no example called {}", name);
+            print_examples();
+            process::exit(2);
+        }
+    };
+    if options.headless && !example.headless {
+        eprintln!(
+            "The {} example needs a window so it can't run headless",
+            example.name
+        );
+        process::exit(2);
+    }
+
+    if let Err(error) = (example.run)(&options) {
+        eprintln!("{}", error);
+        process::exit(1);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn every_example_builds_headless() {
+        let options = Options::default();
+        for example in EXAMPLES {
+            if let Some(plugins) = example.plugins {
+                let mut app = App::build();
+                app.add_plugin(my_breakout::HeadlessPlugin::default());
+                if let Err(error) = plugins(&mut app, &options) {
+                    panic!("The {} example didn't build: {}", example.name, error);
+                }
+            }
+        }
+    }
 }
@@ -8,12 +8,12 @@ Things to try out
   - Pressing a button spawns another ball
   - A pause screen to freeze the game
   - A restart button
-  - A start screen for when the game starts instead of immediately starting as soon as launched
+  - [Done] A start screen for when the game starts instead of immediately starting as soon as launched
   - "You win" after all bars are broken
      - A continue screen which then starts with more bars / faster ball scaling
   - "You lose" screen when the ball hits the bottom
     ^ or alternatively hitting the backboard decrements the score and respawns a bar (hitting the backboard while at 0 causes the lose screen)
-  - Replace the collide method to using bevy_rapier (https://github.com/dimforge/bevy_rapier / https://rapier.rs/docs/) a physics plugin
+  - [Done] Replace the collide method to using bevy_rapier (https://github.com/dimforge/bevy_rapier / https://rapier.rs/docs/) a physics plugin
       - https://rapier.rs/docs/user_guides/rust_bevy_plugin/getting_started
   - Saving game state from pause screen (and having a load save file file picker?)
   - Background music? https://www.zapsplat.com/
@@ -24,57 +24,402 @@ Things to try out
 
 */
 
-use bevy::{
-    prelude::*,
-    render::pass::ClearColor,
-    sprite::collide_aabb::{collide, Collision},
-};
+use bevy::{app::ScheduleRunnerPlugin, prelude::*};
+
+use crate::geometry::{circle_aabb, sweep_aabb, Aabb, Ray};
+use crate::vec3_extension::*;
 
-//use crate::vec3_extension::*;
+mod achievements;
+mod ai;
+mod broadphase;
+mod console;
+mod debug_overlay;
+mod editor;
+mod hazards;
+mod headless;
+mod level;
+mod level_generator;
+mod locale;
+mod moving_bricks;
+mod net;
+mod players;
+mod progress;
+#[cfg(feature = "rapier")]
+mod rapier_backend;
+mod scaling;
+mod simulation;
+mod skins;
+mod stats;
+mod theme;
+mod title;
+mod trail;
+
+use achievements::AchievementsPlugin;
+pub use ai::AiSkill;
+use ai::{ai_paddle_system, ai_skill, unattended_exit_system, AiPaddle, PaddleInput};
+use broadphase::{brick_grid_removal_system, brick_grid_system, swept_bounds, BrickGrid};
+use console::{ConsoleScript, ConsoleState, DevConsolePlugin};
+use debug_overlay::DebugOverlayPlugin;
+use editor::EditorPlugin;
+use hazards::{Hazard, HazardsPlugin};
+use headless::Headless;
+pub use headless::HeadlessPlugin;
+use level::{
+    spawn_level, versus_layout, Brick, BrickMovement, BrickType, LevelLayout, LevelProgress,
+    LevelSource,
+};
+use locale::{LocalePlugin, Localization};
+use moving_bricks::MovingBricksPlugin;
+use net::OnlinePlugin;
+use players::{paddle_y, PlayerBindings, PlayerScore, Players, STARTING_LIVES};
+use progress::ProgressPlugin;
+#[cfg(feature = "rapier")]
+use rapier_backend::RapierBackendPlugin;
+use scaling::{ArenaCamera, ScalingPlugin};
+use simulation::{step, Replay, SimulationState};
+use skins::SkinsPlugin;
+use stats::StatsPlugin;
+use std::{
+    io,
+    path::{Path, PathBuf},
+    time::Duration,
+};
+use theme::{Accessibility, ColorRole, ThemePlugin};
+use title::{title_setup, title_system, title_text_system, TitleScreen};
+use trail::TrailPlugin;
 
 const BOUNDS: (f32, f32) = (900.0 / 2.0, 600.0 / 2.0);
 const WALL_THICKNESS: f32 = 10.0;
+const BALL_START: (f32, f32) = (0.0, -50.0);
+const BALL_SPEED: f32 = 400.0;
+const BALL_SIZE: f32 = 30.0;
+const PADDLE_SIZE: (f32, f32) = (120.0, 30.0);
+const PADDLE_SPEED: f32 = 500.0;
+//how much faster the ball goes every time it breaks a brick
+const BRICK_SPEED_UP: f32 = 1.05;
+//Moving bricks are moved, then the grid catches up with where they went, and only then does the ball look for what it hit,
+//so it's never bounced off of where a brick was last frame. All of it happens before UPDATE
+const BRICK_MOVEMENT_STAGE: &str = "brick_movement";
+const BRICK_GRID_STAGE: &str = "brick_grid";
+const BALL_COLLISION_STAGE: &str = "ball_collision";
+
+//Each way of playing is added onto an app that's already been given a window (or been set up to run without one), and the
+//launcher in main.rs then runs it
 
 /// An implementation of the classic game "Breakout"
-pub fn run() {
-    App::build()
-        //This does a lot of stuff, setting up sprite & UI rendering among many other things
-        //https://docs.rs/bevy/0.1.3/src/bevy/add_default_plugins.rs.html#7-37
-        //UI Plugin setup: https://docs.rs/bevy_ui/0.1.3/src/bevy_ui/lib.rs.html#39-55
-        //Sprite Setup: https://docs.rs/bevy_sprite/0.1.3/src/bevy_sprite/lib.rs.html#43-67
-        //Sprite System: https://docs.rs/bevy_sprite/0.1.3/src/bevy_sprite/sprite.rs.html#21-34
-        //Sprites added to the Render graph here: https://docs.rs/bevy_sprite/0.1.3/src/bevy_sprite/lib.rs.html#55
-        //The specifics of how the rendering plugin sets up rendering systems: https://docs.rs/bevy_render/0.1.3/src/bevy_render/lib.rs.html#80-179
-        .add_default_plugins()
-        //Scoreboard state
-        .add_resource(Scoreboard { score: 0 })
-        //Kind of a silvery color -- ClearColor resources are the background color of the window
-        //https://github.com/jamadazi/bevy-cheatsheet/blob/master/bevy-cheatsheet.md#configuration-resources
-        .add_resource(ClearColor(Color::rgb(0.7, 0.7, 0.7)))
-        .add_startup_system(setup.system())
-        .add_system(paddle_movement_system.system())
-        .add_system(ball_collision_system.system())
-        .add_system(ball_movement_system.system())
-        .add_system(scoreboard_system.system())
-        .run();
+pub fn add_classic(app: &mut AppBuilder) {
+    add_levels(app, LevelSource::Classic);
+}
+
+/// Breakout played on a level made in the editor
+pub fn add_level(app: &mut AppBuilder, level_path: &Path) -> io::Result<()> {
+    let layout = LevelLayout::load(level_path)?;
+    add_levels(app, LevelSource::Custom(layout));
+    Ok(())
+}
+
+/// Breakout played over an endless chain of generated levels. Every level is derived from the given seed so a run can be shared and replayed
+pub fn add_endless(app: &mut AppBuilder, seed: u64) {
+    add_levels(app, LevelSource::Endless { seed });
+}
+
+/// The classic game played by the computer, quitting once it clears the level. Handy for letting full games run without anyone around.
+/// Any console commands in `script` are run as the game starts, to set things up for it
+pub fn add_unattended(app: &mut AppBuilder, skill: AiSkill, script: Option<PathBuf>) {
+    if let Some(script) = script {
+        app.add_resource(ConsoleScript(script));
+    }
+    app.add_plugin(BreakoutPlugin {
+        level_source: LevelSource::Classic,
+        players: vec![PaddleInput::Ai(skill)],
+    });
+}
+
+/// Sets an app up to run without a window or sound. There's nothing to watch, so it runs as fast as it can be simulated
+pub fn add_headless(app: &mut AppBuilder) {
+    //no waiting between updates, the fixed clock's step is what sets the pace of the game
+    app.add_plugin(HeadlessPlugin::default())
+        .add_plugin(ScheduleRunnerPlugin::run_loop(Duration::from_secs(0)));
+}
+
+/// Two players on one keyboard, one defending the bottom of the arena (arrow keys) and the other the top (A and D)
+pub fn add_versus(app: &mut AppBuilder) {
+    app.add_plugin(BreakoutPlugin {
+        level_source: LevelSource::Versus,
+        players: vec![PaddleInput::Keyboard, PaddleInput::Keyboard],
+    });
+}
+
+/// Versus against someone on another machine (or another copy of the game on this one), playing over UDP.
+/// Once the match is decided its inputs are saved to `replay_path`, if there is one
+pub fn add_online(app: &mut AppBuilder, replay_path: Option<PathBuf>) {
+    app.add_plugin(BreakoutPlugin {
+        //the online plugin lays out its own bricks, driven by its simulation rather than the usual collision system
+        level_source: LevelSource::Custom(LevelLayout::default()),
+        players: vec![PaddleInput::Keyboard, PaddleInput::Keyboard],
+    })
+    .add_plugin(OnlinePlugin { replay_path });
+}
+
+/// Plays back an online match saved by `add_online`, printing how it went. There's nothing to draw so it runs without an app
+pub fn run_replay(replay_path: &Path) -> io::Result<()> {
+    let replay = Replay::load(replay_path)?;
+    let layout = versus_layout();
+    let mut state = SimulationState::new(&layout, STARTING_LIVES);
+    for (tick, inputs) in replay.inputs.iter().enumerate() {
+        step(&mut state, &layout, *inputs);
+        if state.winner().is_some() {
+            println!("Decided on tick {}", tick);
+            break;
+        }
+    }
+
+    for player in 0..state.scores.len() {
+        println!(
+            "Player {}: {} points, {} lives left",
+            player + 1,
+            state.scores[player],
+            state.lives[player]
+        );
+    }
+    match state.winner() {
+        Some(winner) => println!("Player {} wins", winner + 1),
+        None => println!("Undecided after {} ticks", replay.inputs.len()),
+    }
+    println!("Checksum {:016x}", state.checksum());
+    Ok(())
+}
+
+/// Prints how long the ball's collision checks take each frame with and without the brick grid, for a dense level with lots
+/// of balls
+pub fn run_broadphase_benchmark() {
+    broadphase::benchmark();
+}
+
+/// The level editor on the given level file (starting from a blank level if it doesn't exist yet)
+pub fn add_editor(app: &mut AppBuilder, level_path: PathBuf) {
+    app.add_plugin(BreakoutPlugin {
+        level_source: LevelSource::Classic,
+        players: vec![PaddleInput::Keyboard],
+    })
+    //The editor replaces the level and game state set up by the BreakoutPlugin so it has to come after it
+    .add_plugin(EditorPlugin { level_path });
+}
+
+fn add_levels(app: &mut AppBuilder, level_source: LevelSource) {
+    app.add_plugin(BreakoutPlugin {
+        level_source,
+        players: vec![PaddleInput::Keyboard],
+    });
+}
+
+/// The game itself, kept as a plugin so that other modes (like the editor) can be layered on top of it
+pub struct BreakoutPlugin {
+    pub level_source: LevelSource,
+    /// How each player's paddle is moved, one entry per player
+    pub players: Vec<PaddleInput>,
+}
+
+impl Plugin for BreakoutPlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        let players = Players(self.players.clone());
+        //Someone at the keyboard starts at the title screen, while the computer can get right to it
+        let starting_state = if players.has_keyboard_player() {
+            GameState::Title
+        } else {
+            GameState::Playing
+        };
+
+        app.add_stage_before(bevy::app::stage::UPDATE, BRICK_MOVEMENT_STAGE)
+            .add_stage_after(BRICK_MOVEMENT_STAGE, BRICK_GRID_STAGE)
+            .add_stage_after(BRICK_GRID_STAGE, BALL_COLLISION_STAGE)
+            //Scoreboard state
+            .add_resource(Scoreboard::new(players.count()))
+            //Which level we're on and where the levels come from
+            .add_resource(LevelProgress::new(self.level_source.clone()))
+            .add_resource(starting_state)
+            .add_resource(players)
+            .init_resource::<PlayerBindings>()
+            .init_resource::<TitleScreen>()
+            //Where every brick is, so the ball only has to check the ones near it
+            .init_resource::<BrickGrid>()
+            .add_event::<RestartGame>()
+            .add_event::<GameEvent>()
+            //the palette everything's drawn in, which sets the ClearColor (the background color of the window) too
+            //https://github.com/jamadazi/bevy-cheatsheet/blob/master/bevy-cheatsheet.md#configuration-resources
+            .add_plugin(ThemePlugin)
+            //every string the player sees, in the language from the settings the theme plugin loaded
+            .add_plugin(LocalePlugin)
+            //paddles, balls and bricks drawn from the palette's sprite sheet, when it has one
+            .add_plugin(SkinsPlugin)
+            .add_plugin(TrailPlugin)
+            //bricks following paths from the level, which whichever collision code is in use has to keep up with
+            .add_plugin(MovingBricksPlugin)
+            //gravity wells, portals and the like from the level, pushing the ball about on top of either collision code
+            .add_plugin(HazardsPlugin)
+            .add_startup_system(setup.system())
+            .add_startup_system(title_setup.system())
+            .add_system(title_system.system())
+            .add_system(restart_system.system())
+            .add_system(paddle_movement_system.system())
+            .add_system(ai_paddle_system.system());
+
+        //portals check where they send the ball against the grid, so it's kept up to date whichever collision code is in use
+        app.add_system_to_stage(BRICK_GRID_STAGE, brick_grid_system.system())
+            .add_system_to_stage(
+                bevy::app::stage::POST_UPDATE,
+                brick_grid_removal_system.system(),
+            );
+
+        //The ball is either moved and collided by our own code or handed over to a physics engine
+        #[cfg(not(feature = "rapier"))]
+        app.add_system_to_stage(BALL_COLLISION_STAGE, ball_collision_system.system())
+            .add_system(ball_movement_system.system());
+        #[cfg(feature = "rapier")]
+        app.add_plugin(RapierBackendPlugin);
+
+        app.add_system(scoreboard_system.system())
+            .add_system(level_progress_system.system())
+            .add_system(level_text_system.system())
+            .add_system(title_text_system.system())
+            .add_system(unattended_exit_system.system())
+            //F3 shows what the collision code is working with
+            .add_plugin(DebugOverlayPlugin)
+            .add_plugin(DevConsolePlugin)
+            .add_plugin(StatsPlugin)
+            //the high scores and unlocked achievements kept between runs, which achievements need in place first
+            .add_plugin(ProgressPlugin)
+            .add_plugin(AchievementsPlugin)
+            .add_plugin(ScalingPlugin);
+
+        //a headless game may well be running somewhere without anything to play sound on
+        if app.resources().get::<Headless>().is_none() {
+            app.add_startup_system(break_sound_setup.system())
+                .add_system(break_sound_system.system());
+        }
+    }
+}
+
+/// What the game is currently doing
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum GameState {
+    Title,
+    /// The computer playing a demo game on the title screen
+    Attract,
+    Playing,
+    /// Every brick of the level has been broken
+    Cleared,
+    /// Somebody ran out of lives
+    GameOver,
+    Editing,
+    /// Playing against someone over the network, where the online plugin runs the game instead of the usual systems
+    Online,
+}
+
+impl GameState {
+    /// Whether the ball and paddles should be moving
+    pub fn is_simulating(self) -> bool {
+        match self {
+            GameState::Playing | GameState::Attract => true,
+            GameState::Title
+            | GameState::Cleared
+            | GameState::GameOver
+            | GameState::Editing
+            | GameState::Online => false,
+        }
+    }
+}
+
+/// Event to put the level, ball and score back to how they were at the start of the game
+pub struct RestartGame;
+
+/// Event for the things that happen during play, for anything keeping track of how a game is going
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum GameEvent {
+    /// `brick_type` is None for anything scorable that isn't a brick. `brick` has already been despawned by the time this is read
+    BrickBroken {
+        brick: Entity,
+        brick_type: Option<BrickType>,
+        player: usize,
+        /// Bricks broken in a row, this one included
+        combo: u32,
+    },
+    /// The ball bounced off of a player's paddle
+    PaddleHit {
+        player: usize,
+    },
+    LifeLost {
+        player: usize,
+    },
+    /// Every breakable brick of the zero based level is gone
+    LevelCleared {
+        level: u32,
+    },
+    /// A game someone was playing is over, `outcome` being the state it ended in (GameOver or Cleared)
+    GameEnded {
+        outcome: GameState,
+    },
+}
+
+/// Ends the game (or starts the demo over if that's what was running)
+fn end_game(
+    game_state: &mut GameState,
+    restart_events: &mut Events<RestartGame>,
+    game_events: &mut Events<GameEvent>,
+    outcome: GameState,
+) {
+    match *game_state {
+        GameState::Attract => restart_events.send(RestartGame),
+        _ => {
+            *game_state = outcome;
+            game_events.send(GameEvent::GameEnded { outcome });
+        }
+    }
 }
 
 struct Paddle {
     speed: f32,
+    input: PaddleInput,
+    player: usize,
 }
 
 struct Ball {
     velocity: Vec3,
+    //the player whose paddle the ball last bounced off of, they get the points for what it breaks
+    last_hit_by: usize,
 }
 
+/// Per player scores and lives, indexed by player id
 struct Scoreboard {
-    score: usize,
+    players: Vec<PlayerScore>,
+    /// Bricks broken in a row without the ball going back to a paddle (or being lost)
+    combo: u32,
+}
+
+impl Scoreboard {
+    fn new(player_count: usize) -> Self {
+        Scoreboard {
+            players: (0..player_count).map(|_| PlayerScore::default()).collect(),
+            combo: 0,
+        }
+    }
 }
 
+#[derive(Clone, Copy)]
 enum Collider {
     Solid,
     Scorable,
+    /// The wall behind a player's paddle. The ball getting there costs that player a life
+    Goal {
+        player: usize,
+    },
+}
+
+//Marker components to tell our text entities apart
+struct ScoreText {
+    player: usize,
 }
+struct LevelText;
 
 struct BreakSound {
     asset: Handle<AudioSource>,
@@ -84,6 +429,8 @@ fn setup(
     mut commands: Commands,
     mut materials: ResMut<Assets<ColorMaterial>>,
     asset_server: Res<AssetServer>,
+    level_progress: Res<LevelProgress>,
+    players: Res<Players>,
 ) {
     // Add the game's entities to our world
     //Looks like SpriteComponents describes the attributes that are used by the
@@ -95,6 +442,8 @@ fn setup(
         //
         //https://docs.rs/bevy_render/0.1.3/src/bevy_render/entity.rs.html#76
         .spawn(Camera2dComponents::default())
+        //so it can be zoomed to fit the arena in the window
+        .with(ArenaCamera)
         //Looks like this creates the default UI Camera settings per:
         //https://docs.rs/bevy_ui/0.1.3/src/bevy_ui/entity.rs.html#204
         //The UiCameraComponent's default seems very similar to the Camera2dComponents but it changes the projection
@@ -103,51 +452,104 @@ fn setup(
         //Instead of stuff like bottom left being -x_width/2, -y_width/2 when being assigned an hp bar, etc
         //TextComponent: https://docs.rs/bevy_ui/0.1.3/src/bevy_ui/entity.rs.html#114
         //ImageComponent: https://docs.rs/bevy_ui/0.1.3/src/bevy_ui/entity.rs.html#114
-        .spawn(UiCameraComponents::default())
-        // paddle
-        .spawn(SpriteComponents {
-            material: materials.add(Color::rgb(0.2, 0.2, 0.8).into()),
-            transform: Transform::from_translation(Vec3::new(0.0, -215.0, 0.0)),
-            sprite: Sprite::new(Vec2::new(120.0, 30.0)),
-            //Looks like this is inserting the default values for SpriteComponents that wasn't set
-            ..Default::default()
-        })
-        .with(Paddle { speed: 500.0 })
-        .with(Collider::Solid)
+        .spawn(UiCameraComponents::default());
+
+    // paddles and scoreboards, one of each per player
+    for (player, input) in players.0.iter().enumerate() {
+        //the first player's scoreboard sits in the top left, the second's in the top right
+        let position = if player == 0 {
+            Rect {
+                top: Val::Px(5.0),
+                left: Val::Px(5.0),
+                ..Default::default()
+            }
+        } else {
+            Rect {
+                top: Val::Px(5.0),
+                right: Val::Px(5.0),
+                ..Default::default()
+            }
+        };
+
+        commands
+            .spawn(SpriteComponents {
+                material: materials.add(Color::rgb(1.0, 1.0, 1.0).into()),
+                transform: Transform::from_translation(Vec3::new(0.0, paddle_y(player), 0.0)),
+                sprite: Sprite::new(Vec2::new(PADDLE_SIZE.0, PADDLE_SIZE.1)),
+                //Looks like this is inserting the default values for SpriteComponents that wasn't set
+                ..Default::default()
+            })
+            .with(Paddle {
+                speed: PADDLE_SPEED,
+                input: *input,
+                player,
+            })
+            .with(AiPaddle::new(player as u64))
+            .with(Collider::Solid)
+            .with(ColorRole::Paddle { player })
+            .spawn(TextComponents {
+                text: Text {
+                    font: asset_server.load("assets/fonts/FiraSans-Bold.ttf").unwrap(),
+                    value: "".to_string(),
+                    style: TextStyle {
+                        color: Color::rgb(1.0, 1.0, 1.0),
+                        font_size: 40.0,
+                    },
+                },
+                style: Style {
+                    position_type: PositionType::Absolute,
+                    position,
+                    ..Default::default()
+                },
+                ..Default::default()
+            })
+            .with(ScoreText { player })
+            .with(ColorRole::Paddle { player });
+    }
+
+    let (ball_position, ball_velocity) = serve(players.count(), 0);
+    commands
         // ball
         .spawn(SpriteComponents {
-            material: materials.add(Color::rgb(0.8, 0.2, 0.2).into()),
-            transform: Transform::from_translation(Vec3::new(0.0, -50.0, 1.0)),
-            sprite: Sprite::new(Vec2::new(30.0, 30.0)),
+            material: materials.add(ColorMaterial::modulated_texture(
+                asset_server.load("assets/textures/ball.png").unwrap(),
+                Color::rgb(1.0, 1.0, 1.0),
+            )),
+            transform: Transform::from_translation(ball_position.extend(1.0)),
+            sprite: Sprite::new(Vec2::splat(BALL_SIZE)),
             ..Default::default()
         })
         .with(Ball {
-            velocity: 400.0 * Vec3::new(0.5, -0.5, 0.0).normalize(),
+            velocity: ball_velocity,
+            last_hit_by: server(players.count(), 0),
         })
-        // scoreboard
+        .with(ColorRole::Ball)
+        // level and seed, so an interesting generated layout can be shared
         .spawn(TextComponents {
             text: Text {
                 font: asset_server.load("assets/fonts/FiraSans-Bold.ttf").unwrap(),
-                value: "Score:".to_string(),
+                value: "".to_string(),
                 style: TextStyle {
-                    color: Color::rgb(0.2, 0.2, 0.8),
-                    font_size: 40.0,
+                    color: Color::rgb(1.0, 1.0, 1.0),
+                    font_size: 30.0,
                 },
             },
             style: Style {
                 position_type: PositionType::Absolute,
                 position: Rect {
                     top: Val::Px(5.0),
-                    left: Val::Px(5.0),
+                    right: Val::Px(5.0),
                     ..Default::default()
                 },
                 ..Default::default()
             },
             ..Default::default()
-        });
+        })
+        .with(LevelText)
+        .with(ColorRole::Text);
 
-    // Add walls
-    let wall_material = materials.add(Color::rgb(0.5, 0.5, 0.5).into());
+    // Add walls, colored in by the theme like everything else
+    let wall_material = materials.add(Color::rgb(1.0, 1.0, 1.0).into());
     let bounds = Vec2::new(900.0, 600.0);
 
     commands
@@ -159,6 +561,7 @@ fn setup(
             ..Default::default()
         })
         .with(Collider::Solid)
+        .with(ColorRole::Wall)
         // right
         .spawn(SpriteComponents {
             material: wall_material,
@@ -167,69 +570,94 @@ fn setup(
             ..Default::default()
         })
         .with(Collider::Solid)
-        // bottom
+        .with(ColorRole::Wall)
+        // bottom, behind the first player's paddle
         .spawn(SpriteComponents {
             material: wall_material,
             transform: Transform::from_translation(Vec3::new(0.0, -bounds.y() / 2.0, 0.0)),
             sprite: Sprite::new(Vec2::new(bounds.x() + WALL_THICKNESS, WALL_THICKNESS)),
             ..Default::default()
         })
-        .with(Collider::Solid)
-        // top
+        .with(if has_lives(&level_progress.source, players.count()) {
+            Collider::Goal { player: 0 }
+        } else {
+            Collider::Solid
+        })
+        .with(ColorRole::Wall)
+        // top, which is only a goal if there's a second player up there defending it
         .spawn(SpriteComponents {
             material: wall_material,
             transform: Transform::from_translation(Vec3::new(0.0, bounds.y() / 2.0, 0.0)),
             sprite: Sprite::new(Vec2::new(bounds.x() + WALL_THICKNESS, WALL_THICKNESS)),
             ..Default::default()
         })
-        .with(Collider::Solid);
+        .with(if players.count() > 1 {
+            Collider::Goal { player: 1 }
+        } else {
+            Collider::Solid
+        })
+        .with(ColorRole::Wall);
 
     // Add bricks
-    let brick_rows = 4;
-    let brick_columns = 5;
-    let brick_spacing = 20.0;
-    let brick_size = Vec2::new(150.0, 30.0);
-    let bricks_width = brick_columns as f32 * (brick_size.x() + brick_spacing) - brick_spacing;
-    // center the bricks and move them up a bit
-    let bricks_offset = Vec3::new(-(bricks_width - brick_size.x()) / 2.0, 100.0, 0.0);
-
-    for row in 0..brick_rows {
-        let y_position = row as f32 * (brick_size.y() + brick_spacing);
-        for column in 0..brick_columns {
-            let brick_position = Vec3::new(
-                column as f32 * (brick_size.x() + brick_spacing),
-                y_position,
-                0.0,
-            ) + bricks_offset;
-            commands
-                // brick
-                .spawn(SpriteComponents {
-                    material: materials.add(Color::rgb(0.2, 0.2, 0.8).into()),
-                    sprite: Sprite::new(brick_size),
-                    transform: Transform::from_translation(brick_position),
-                    ..Default::default()
-                })
-                .with(Collider::Scorable);
-        }
-    }
+    spawn_level(
+        &mut commands,
+        &mut materials,
+        &level_progress.current_layout(),
+    );
+}
 
+fn break_sound_setup(mut commands: Commands, asset_server: Res<AssetServer>) {
     //load the audio file
     let break_sound = asset_server.load("assets/sounds/break.mp3").unwrap();
     commands.insert_resource(BreakSound { asset: break_sound });
 }
 
+/// Plays the break sound for every brick broken, however it was broken
+fn break_sound_system(
+    audio_output: Res<AudioOutput>,
+    break_sound: Res<BreakSound>,
+    game_events: Res<Events<GameEvent>>,
+    mut game_event_reader: Local<EventReader<GameEvent>>,
+) {
+    for event in game_event_reader.iter(&game_events) {
+        if let GameEvent::BrickBroken { .. } = event {
+            //https://github.com/RustAudio/rodio/issues/229
+            //Looks like playing mp3 on Windows can panic and kill the audio library if running a debug build
+            audio_output.play(break_sound.asset);
+        }
+    }
+}
+
 fn paddle_movement_system(
     time: Res<Time>,
+    game_state: Res<GameState>,
     keyboard_input: Res<Input<KeyCode>>,
+    console: Res<ConsoleState>,
+    bindings: Res<PlayerBindings>,
     mut query: Query<(&Paddle, &mut Transform, &Sprite)>,
 ) {
+    //whatever's typed into the console is meant for it rather than the paddles
+    if !game_state.is_simulating() || console.open {
+        return;
+    }
+
     for (paddle, mut transform, sprite) in &mut query.iter() {
+        //the computer moves its own paddles
+        if ai_skill(paddle, *game_state).is_some() {
+            continue;
+        }
+
+        let bindings = match bindings.0.get(paddle.player) {
+            Some(bindings) => bindings,
+            None => continue,
+        };
+
         let mut direction = 0.0;
-        if keyboard_input.pressed(KeyCode::Left) {
+        if keyboard_input.pressed(bindings.left) {
             direction -= 1.0;
         }
 
-        if keyboard_input.pressed(KeyCode::Right) {
+        if keyboard_input.pressed(bindings.right) {
             direction += 1.0;
         }
 
@@ -240,24 +668,33 @@ fn paddle_movement_system(
     }
 }
 
-fn clamp_movement_within_bounds(sprite_size: &Vec2, transform: &mut Mut<Transform>) {
+/// The nearest position to the given one that keeps a sprite of the given size within the walls
+fn within_bounds(position: Vec2, sprite_size: Vec2) -> Vec2 {
     //TODO make bound calculations a constant left/right/bottom/top bounds
-    let sprite_width = sprite_size.x() / 2.0;
-    let sprite_height = sprite_size.y() / 2.0;
+    let limit = Vec2::new(BOUNDS.0, BOUNDS.1) - sprite_size / 2.0;
+    position.clamp_to_aabb(-limit, limit)
+}
+
+fn clamp_movement_within_bounds(sprite_size: &Vec2, transform: &mut Mut<Transform>) {
     let translation = transform.translation_mut();
-    *translation.x_mut() = translation
-        .x()
-        .min(BOUNDS.0 - sprite_width)
-        .max(BOUNDS.0 * -1.0 + sprite_width);
-    *translation.y_mut() = translation
-        .y()
-        .min(BOUNDS.1 - sprite_height)
-        .max(BOUNDS.1 * -1.0 + sprite_height);
+    let clamped = within_bounds(translation.truncate(), *sprite_size);
+    *translation.x_mut() = clamped.x();
+    *translation.y_mut() = clamped.y();
 }
 
-fn ball_movement_system(time: Res<Time>, mut ball_query: Query<(&Ball, &mut Transform, &Sprite)>) {
-    // clamp the timestep to stop the ball from escaping when the game starts
-    let delta_seconds = f32::min(0.2, time.delta_seconds);
+#[cfg_attr(feature = "rapier", allow(dead_code))]
+fn ball_movement_system(
+    time: Res<Time>,
+    game_state: Res<GameState>,
+    accessibility: Res<Accessibility>,
+    mut ball_query: Query<(&Ball, &mut Transform, &Sprite)>,
+) {
+    if !game_state.is_simulating() {
+        return;
+    }
+
+    // clamp the timestep to stop the ball from escaping when the game starts, then slow it down if it's been asked to be
+    let delta_seconds = f32::min(0.2, time.delta_seconds) * accessibility.game_speed;
 
     for (ball, mut transform, sprite) in &mut ball_query.iter() {
         transform.translate(ball.velocity * delta_seconds);
@@ -266,106 +703,612 @@ fn ball_movement_system(time: Res<Time>, mut ball_query: Query<(&Ball, &mut Tran
     }
 }
 
-fn scoreboard_system(scoreboard: Res<Scoreboard>, mut query: Query<&mut Text>) {
-    for mut text in &mut query.iter() {
-        text.value = format!("Score: {}", scoreboard.score);
+/// Where a ball is served from and how fast it's going, heading towards the given player.
+/// With a single player it's always the same serve, with two it comes from the center line
+fn serve(player_count: usize, toward_player: usize) -> (Vec2, Vec3) {
+    if player_count < 2 {
+        return (
+            Vec2::new(BALL_START.0, BALL_START.1),
+            BALL_SPEED * Vec3::new(0.5, -0.5, 0.0).normalize(),
+        );
     }
+
+    let direction = paddle_y(toward_player).signum();
+    (
+        Vec2::zero(),
+        BALL_SPEED * Vec3::new(0.5, 0.5 * direction, 0.0).normalize(),
+    )
+}
+
+/// Who gets the credit for a serve towards the given player, which is their opponent when there is one
+fn server(player_count: usize, toward_player: usize) -> usize {
+    (toward_player + 1) % player_count.max(1)
+}
+
+/// Puts a ball back at its serving point and speed
+fn serve_ball(
+    ball: &mut Ball,
+    transform: &mut Transform,
+    player_count: usize,
+    toward_player: usize,
+) {
+    let (position, velocity) = serve(player_count, toward_player);
+    ball.velocity = velocity;
+    ball.last_hit_by = server(player_count, toward_player);
+    let translation = transform.translation_mut();
+    *translation.x_mut() = position.x();
+    *translation.y_mut() = position.y();
+}
+
+/// Puts a ball back at its starting point and speed for a single player game
+fn reset_ball(ball: &mut Ball, transform: &mut Transform) {
+    serve_ball(ball, transform, 1, 0);
+}
+
+fn restart_system(
+    mut commands: Commands,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+    mut restart_reader: Local<EventReader<RestartGame>>,
+    restart_events: Res<Events<RestartGame>>,
+    mut scoreboard: ResMut<Scoreboard>,
+    mut level_progress: ResMut<LevelProgress>,
+    mut brick_query: Query<(Entity, &Brick)>,
+    mut hazard_query: Query<With<Hazard, Entity>>,
+    mut ball_query: Query<(&mut Ball, &mut Transform)>,
+) {
+    //several restarts in the same frame still only need one
+    if restart_reader.iter(&restart_events).count() == 0 {
+        return;
+    }
+
+    for (entity, _brick) in &mut brick_query.iter() {
+        commands.despawn(entity);
+    }
+    for entity in &mut hazard_query.iter() {
+        commands.despawn(entity);
+    }
+    level_progress.level = 0;
+    spawn_level(
+        &mut commands,
+        &mut materials,
+        &level_progress.current_layout(),
+    );
+
+    let player_count = scoreboard.players.len();
+    for (mut ball, mut transform) in &mut ball_query.iter() {
+        serve_ball(&mut ball, &mut transform, player_count, 0);
+    }
+    *scoreboard = Scoreboard::new(player_count);
+}
+
+/// Whether the ball getting past a paddle costs a life. Classic breakout on your own never had lives, the bottom's just
+/// another wall there
+fn has_lives(level_source: &LevelSource, player_count: usize) -> bool {
+    player_count > 1 || !matches!(level_source, LevelSource::Classic)
+}
+
+fn scoreboard_system(
+    localization: Res<Localization>,
+    level_progress: Res<LevelProgress>,
+    scoreboard: Res<Scoreboard>,
+    mut query: Query<(&ScoreText, &mut Text)>,
+) {
+    let versus = scoreboard.players.len() > 1;
+    let lives = has_lives(&level_progress.source, scoreboard.players.len());
+    for (score_text, mut text) in &mut query.iter() {
+        if let Some(player) = scoreboard.players.get(score_text.player) {
+            let args = [
+                ("player", (score_text.player + 1).to_string()),
+                ("score", localization.number(player.score as u64)),
+                ("lives", localization.number(player.lives as u64)),
+            ];
+            text.value = if versus {
+                localization.format("hud.score_versus", &args)
+            } else if lives {
+                localization.format("hud.score", &args)
+            } else {
+                localization.format("hud.score_classic", &args)
+            };
+        }
+    }
+}
+
+fn level_text_system(
+    localization: Res<Localization>,
+    level_progress: Res<LevelProgress>,
+    mut query: Query<(&LevelText, &mut Text)>,
+) {
+    for (_level_text, mut text) in &mut query.iter() {
+        text.value = match level_progress.source {
+            //the seed is left as it is, without separators, so it can be typed back in
+            LevelSource::Endless { seed } => localization.format(
+                "hud.endless",
+                &[
+                    ("seed", seed.to_string()),
+                    (
+                        "level",
+                        localization.number(level_progress.level as u64 + 1),
+                    ),
+                ],
+            ),
+            _ => "".to_string(),
+        };
+    }
+}
+
+/// Once every breakable brick is gone move on to the next level. Only endless mode has a next level to move on to
+fn level_progress_system(
+    mut commands: Commands,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+    mut game_state: ResMut<GameState>,
+    mut level_progress: ResMut<LevelProgress>,
+    mut restart_events: ResMut<Events<RestartGame>>,
+    mut game_events: ResMut<Events<GameEvent>>,
+    mut brick_query: Query<(Entity, &Brick)>,
+    mut hazard_query: Query<With<Hazard, Entity>>,
+) {
+    if !game_state.is_simulating() {
+        return;
+    }
+    let mut remaining = 0;
+    let mut unbreakable = Vec::new();
+    for (entity, brick) in &mut brick_query.iter() {
+        if brick.brick_type.is_breakable() {
+            remaining += 1;
+        } else {
+            unbreakable.push(entity);
+        }
+    }
+    if remaining > 0 {
+        return;
+    }
+
+    //versus games are decided on lives, so play just carries on once the walls are down
+    if level_progress.source == LevelSource::Versus {
+        return;
+    }
+    game_events.send(GameEvent::LevelCleared {
+        level: level_progress.level,
+    });
+
+    match level_progress.source {
+        LevelSource::Endless { .. } => {}
+        _ => {
+            //anyone actually playing has won
+            end_game(
+                &mut game_state,
+                &mut restart_events,
+                &mut game_events,
+                GameState::Cleared,
+            );
+            return;
+        }
+    }
+
+    //clear out what's left of the finished level before laying out the next one
+    for entity in unbreakable {
+        commands.despawn(entity);
+    }
+    for entity in &mut hazard_query.iter() {
+        commands.despawn(entity);
+    }
+    level_progress.level += 1;
+    spawn_level(
+        &mut commands,
+        &mut materials,
+        &level_progress.current_layout(),
+    );
+}
+
+/// Takes a hit off of a brick, and once it's out of hits breaks it and gives its points to whoever hit the ball last.
+/// Returns whether it broke
+fn hit_brick(
+    commands: &mut Commands,
+    scoreboard: &mut Scoreboard,
+    game_events: &mut Events<GameEvent>,
+    entity: Entity,
+    brick: Option<&mut Brick>,
+    last_hit_by: usize,
+) -> bool {
+    //tough bricks take a few hits before they break
+    let broken = match brick {
+        Some(brick) => {
+            brick.hits_remaining = brick.hits_remaining.saturating_sub(1);
+            if brick.hits_remaining == 0 {
+                Some((brick.brick_type.points(), Some(brick.brick_type)))
+            } else {
+                None
+            }
+        }
+        None => Some((1, None)),
+    };
+
+    let (points, brick_type) = match broken {
+        Some(broken) => broken,
+        None => return false,
+    };
+    if let Some(player) = scoreboard.players.get_mut(last_hit_by) {
+        player.score += points;
+    }
+    scoreboard.combo += 1;
+    commands.despawn(entity);
+    game_events.send(GameEvent::BrickBroken {
+        brick: entity,
+        brick_type,
+        player: last_hit_by,
+        combo: scoreboard.combo,
+    });
+    true
+}
+
+/// The ball bounced off of a player's paddle, which ends the combo
+fn hit_paddle(scoreboard: &mut Scoreboard, game_events: &mut Events<GameEvent>, player: usize) {
+    scoreboard.combo = 0;
+    game_events.send(GameEvent::PaddleHit { player });
+}
+
+/// The ball's velocity after touching something moving at `collider_velocity`, with `normal` pointing out towards the ball.
+/// None if the two are already moving apart as seen from what it touched, so a moving brick can catch up with a ball heading
+/// away from it. It's reflected about the actual contact normal, so clipping a corner sends it off at an angle, and a moving
+/// brick carries it along while only changing its direction. It keeps its speed, otherwise a fast brick could knock it
+/// about faster than the paddles can keep up with
+#[cfg_attr(feature = "rapier", allow(dead_code))]
+fn bounce(velocity: Vec3, collider_velocity: Vec3, normal: Vec2) -> Option<Vec3> {
+    let relative_velocity = velocity - collider_velocity;
+    if relative_velocity.truncate().dot(normal) >= 0.0 {
+        return None;
+    }
+    let bounced = relative_velocity.reflect(normal.extend(0.0)) + collider_velocity;
+    Some(if bounced.length_squared() > 0.0 {
+        bounced.normalize() * velocity.length()
+    } else {
+        velocity.reflect(normal.extend(0.0))
+    })
+}
+
+/// The ball got past a player's paddle so they lose a life, which ends the game if it was their last
+fn lose_life(
+    scoreboard: &mut Scoreboard,
+    game_state: &mut GameState,
+    restart_events: &mut Events<RestartGame>,
+    game_events: &mut Events<GameEvent>,
+    player: usize,
+) {
+    if let Some(player_score) = scoreboard.players.get_mut(player) {
+        player_score.lives = player_score.lives.saturating_sub(1);
+    }
+    scoreboard.combo = 0;
+    game_events.send(GameEvent::LifeLost { player });
+    if scoreboard.players.iter().any(|player| player.lives == 0) {
+        end_game(game_state, restart_events, game_events, GameState::GameOver);
+    }
+}
+
+/// Where a ball moving by `displacement` first reaches something it would otherwise go straight through without ever touching,
+/// if there is anything. Whatever it only gets to by the end of the move is left to be touched next frame
+#[cfg_attr(feature = "rapier", allow(dead_code))]
+fn tunneling_stop(center: Vec2, radius: f32, displacement: Vec2, bounds: &[Aabb]) -> Option<Vec2> {
+    let ball = Aabb::from_center_size(center, Vec2::splat(radius * 2.0));
+    bounds
+        .iter()
+        .filter(|bounds| circle_aabb(center + displacement, radius, bounds).is_none())
+        .filter_map(|bounds| sweep_aabb(&ball, displacement, bounds))
+        .map(|hit| hit.time)
+        .filter(|time| *time > 0.0)
+        .fold(None, |first: Option<f32>, time| {
+            Some(first.map_or(time, |first| first.min(time)))
+        })
+        .map(|time| Ray::new(center, displacement).at(time))
 }
 
 //So take in the scoreboard resource (so we can increment the score if needed)
 //query for the ball (though if we don't care to do anything on a batch of balls, why query instead of pass in?)
-//then do a query for all the colliders in the game and check for collisions
+//then do a query for the colliders near the ball and check for collisions
 //TODO when we improve the collision with the physics plugin it seems like we should be sending an event to fire off the sound
+#[cfg_attr(feature = "rapier", allow(dead_code))]
 fn ball_collision_system(
     mut commands: Commands,
+    time: Res<Time>,
+    accessibility: Res<Accessibility>,
+    brick_grid: Res<BrickGrid>,
     mut scoreboard: ResMut<Scoreboard>,
-    audio_output: Res<AudioOutput>,
-    break_sound: Res<BreakSound>, //TODO this seems like it's going to do an additional for each for each break_out resource?
-    mut ball_query: Query<(&mut Ball, &Transform, &Sprite)>,
-    mut collider_query: Query<(Entity, &Collider, &Transform, &Sprite)>,
+    mut game_state: ResMut<GameState>,
+    mut restart_events: ResMut<Events<RestartGame>>,
+    mut game_events: ResMut<Events<GameEvent>>,
+    mut ball_query: Query<(&mut Ball, &mut Transform, &Sprite)>,
+    //walls and paddles, which are few enough to just check them all. Bricks are looked up through the grid
+    mut collider_query: Query<Without<Brick, (Entity, &Collider, &Transform, &Sprite)>>,
+    mut brick_query: Query<(&mut Brick, &Collider)>,
+    mut movement_query: Query<&BrickMovement>,
+    mut paddle_query: Query<&Paddle>,
 ) {
-    for (mut ball, ball_transform, sprite) in &mut ball_query.iter() {
-        let ball_size = sprite.size;
-        let velocity = &mut ball.velocity;
+    if !game_state.is_simulating() {
+        return;
+    }
 
-        // check collision with walls
-        for (collider_entity, collider, transform, sprite) in &mut collider_query.iter() {
-            let collision = collide(
-                ball_transform.translation(),
-                ball_size,
-                transform.translation(),
-                sprite.size,
-            );
-            if let Some(collision) = collision {
-                // scorable colliders should be despawned and increment the scoreboard on collision
-                if let Collider::Scorable = *collider {
-                    scoreboard.score += 1;
-                    commands.despawn(collider_entity);
+    for (mut ball, mut ball_transform, sprite) in &mut ball_query.iter() {
+        //the ball is drawn as a circle filling its sprite so it collides as one too
+        let ball_radius = sprite.size.x() / 2.0;
+        let mut last_hit_by = ball.last_hit_by;
+        let mut goal_reached = None;
+        let velocity = &mut ball.velocity;
 
-                    //https://github.com/RustAudio/rodio/issues/229
-                    //Looks like playing mp3 on Windows can panic and kill the audio library if running a debug build
+        //everything the ball could run into this frame
+        let mut nearby = Vec::new();
+        for (entity, collider, transform, sprite) in &mut collider_query.iter() {
+            //the paddles move too, but the ball's backed out of them and they only ever hit it from one side
+            nearby.push((
+                entity,
+                *collider,
+                Aabb::from_sprite(&transform, &sprite),
+                Vec3::zero(),
+            ));
+        }
+        let ball_center = ball_transform.translation().truncate();
+        //the same clamped timestep the ball moves by
+        let delta_seconds = f32::min(0.2, time.delta_seconds) * accessibility.game_speed;
+        let displacement = velocity.truncate() * delta_seconds;
+        let area = swept_bounds(ball_center, ball_radius, displacement);
+        for entity in brick_grid.query(&area) {
+            //anything despawned since the grid was last updated won't be found
+            if let (Ok(collider), Some(bounds)) = (
+                brick_query.get::<Collider>(entity),
+                brick_grid.bounds(entity),
+            ) {
+                let collider_velocity = movement_query
+                    .get::<BrickMovement>(entity)
+                    .map_or(Vec3::zero(), |movement| movement.velocity.extend(0.0));
+                nearby.push((entity, *collider, *bounds, collider_velocity));
+            }
+        }
 
-                    audio_output.play(break_sound.asset);
+        //a ball fast enough to get right past something this frame is brought up to it, so it's caught below
+        let all_bounds: Vec<Aabb> = nearby.iter().map(|(_, _, bounds, _)| *bounds).collect();
+        if let Some(position) = tunneling_stop(ball_center, ball_radius, displacement, &all_bounds)
+        {
+            let translation = ball_transform.translation_mut();
+            *translation.x_mut() = position.x();
+            *translation.y_mut() = position.y();
+        }
 
-                    //We've broken a bar so speed up the ball
-                    *velocity *= 1.05;
+        // check collision with walls
+        for (collider_entity, collider, bounds, collider_velocity) in nearby {
+            let contact = circle_aabb(
+                ball_transform.translation().truncate(),
+                ball_radius,
+                &bounds,
+            );
+            if let Some(contact) = contact {
+                if let Collider::Goal { player } = collider {
+                    goal_reached = Some(player);
+                    break;
                 }
 
-                // reflect the ball when it collides
-                let mut reflect_x = false;
-                let mut reflect_y = false;
-
-                // only reflect if the ball's velocity is going in the opposite direction of the collision
-                match collision {
-                    Collision::Left => reflect_x = velocity.x() > 0.0,
-                    Collision::Right => reflect_x = velocity.x() < 0.0,
-                    Collision::Top => reflect_y = velocity.y() < 0.0,
-                    Collision::Bottom => reflect_y = velocity.y() > 0.0,
+                //remember who hit the ball so they get the points for whatever it breaks
+                let paddle_player = paddle_query
+                    .get::<Paddle>(collider_entity)
+                    .ok()
+                    .map(|paddle| paddle.player);
+                if let Some(player) = paddle_player {
+                    last_hit_by = player;
                 }
 
-                // reflect velocity on the x-axis if we hit something on the x-axis
-                if reflect_x {
-                    *velocity.x_mut() = -velocity.x();
+                let bounced = bounce(*velocity, collider_velocity, contact.normal);
+                let reflect = bounced.is_some();
+                if let (Some(player), true) = (paddle_player, reflect) {
+                    hit_paddle(&mut scoreboard, &mut game_events, player);
+                }
+                if let Some(bounced) = bounced {
+                    *velocity = bounced;
                 }
 
-                // reflect velocity on the y-axis if we hit something on the y-axis
-                if reflect_y {
-                    negate_y(velocity);
+                // scorable colliders should be despawned and increment the scoreboard on collision
+                // (only counting the hit if we're bouncing off, otherwise a tough brick would lose a hit every frame we overlap it)
+                if matches!(collider, Collider::Scorable) && reflect {
+                    let mut brick = brick_query.get_mut::<Brick>(collider_entity).ok();
+                    let broken = hit_brick(
+                        &mut commands,
+                        &mut scoreboard,
+                        &mut game_events,
+                        collider_entity,
+                        brick.as_deref_mut(),
+                        last_hit_by,
+                    );
+                    if broken {
+                        //We've broken a bar so speed up the ball
+                        *velocity *= BRICK_SPEED_UP;
+                    }
                 }
 
+                //and back the ball out so it's only touching, otherwise it can end up stuck inside a moving paddle
+                let push_out = contact.normal * contact.depth;
+                let translation = ball_transform.translation_mut();
+                *translation.x_mut() += push_out.x();
+                *translation.y_mut() += push_out.y();
+
                 break;
             }
+        }
+        ball.last_hit_by = last_hit_by;
+
+        //the ball got past a player's paddle, so they lose a life and it gets served back to them
+        if let Some(player) = goal_reached {
+            lose_life(
+                &mut scoreboard,
+                &mut game_state,
+                &mut restart_events,
+                &mut game_events,
+                player,
+            );
+            let player_count = scoreboard.players.len();
+            serve_ball(&mut ball, &mut ball_transform, player_count, player);
+        }
+    }
+}
 
-            //The collision logic only considers rectangle intersection not contact so consider contact here
-            let a_pos = ball_transform.translation();
-            let a_size = ball_size;
-            let b_pos = transform.translation();
-            let b_size = sprite.size;
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use progress::Progress;
+    use stats::StatsExport;
+    use std::{env, fs, process};
 
-            let a_min = a_pos.truncate() - a_size / 2.0;
-            let a_max = a_pos.truncate() + a_size / 2.0;
+    //a computer player that can't keep up with the ball, so it's sure to run out of lives before long
+    fn clumsy() -> AiSkill {
+        AiSkill {
+            reaction_delay: 1.0,
+            aim_error: 300.0,
+            max_speed: 60.0,
+        }
+    }
 
-            let b_min = b_pos.truncate() - b_size / 2.0;
-            let b_max = b_pos.truncate() + b_size / 2.0;
+    #[test]
+    fn fast_balls_stop_at_what_they_would_skip_over() {
+        let brick = Aabb::from_center_size(Vec2::new(0.0, 100.0), Vec2::new(60.0, 10.0));
+        //from well below to well above the brick in one frame, so it's never touching it at either end
+        let stop = tunneling_stop(Vec2::zero(), 15.0, Vec2::new(0.0, 200.0), &[brick]);
+        assert_eq!(stop, Some(Vec2::new(0.0, 80.0)));
 
-            // check to see if the two rectangles are touching
-            if (a_max.y() - b_max.y()).abs() < 0.5 || (a_min.y() - b_min.y()).abs() < 0.5 {
-                //we've touched the top or bottom so reflect y
-                negate_y(velocity);
-            }
+        //the nearer of two gets it
+        let nearer = Aabb::from_center_size(Vec2::new(0.0, 50.0), Vec2::new(60.0, 10.0));
+        let stop = tunneling_stop(Vec2::zero(), 15.0, Vec2::new(0.0, 200.0), &[brick, nearer]);
+        assert_eq!(stop, Some(Vec2::new(0.0, 30.0)));
+    }
 
-            if (a_max.x() - b_max.x()).abs() < 0.5 || (a_min.x() - b_min.x()).abs() < 0.5 {
-                //we've touched the left or right so reflect x
-                negate_x(velocity);
-            }
-        }
+    #[test]
+    fn slow_balls_are_left_to_touch() {
+        let brick = Aabb::from_center_size(Vec2::new(0.0, 100.0), Vec2::new(60.0, 10.0));
+        //ends the frame touching the brick, which the contact check deals with
+        assert_eq!(
+            tunneling_stop(Vec2::new(0.0, 70.0), 15.0, Vec2::new(0.0, 20.0), &[brick]),
+            None
+        );
+        //heading away from it
+        assert_eq!(
+            tunneling_stop(Vec2::zero(), 15.0, Vec2::new(0.0, -200.0), &[brick]),
+            None
+        );
+        //passing it by
+        assert_eq!(
+            tunneling_stop(Vec2::new(100.0, 0.0), 15.0, Vec2::new(0.0, 200.0), &[brick]),
+            None
+        );
     }
-}
 
-fn negate_y(to_reflect: &mut Vec3) {
-    *to_reflect.y_mut() = -to_reflect.y();
-}
+    #[test]
+    fn bouncing_off_still_things_reflects() {
+        let bounced = bounce(
+            Vec3::new(100.0, -200.0, 0.0),
+            Vec3::zero(),
+            Vec2::new(0.0, 1.0),
+        );
+        assert_eq!(bounced, Some(Vec3::new(100.0, 200.0, 0.0)));
+        //already on its way out
+        assert_eq!(
+            bounce(
+                Vec3::new(100.0, 200.0, 0.0),
+                Vec3::zero(),
+                Vec2::new(0.0, 1.0)
+            ),
+            None
+        );
+    }
+
+    #[test]
+    fn moving_bricks_bounce_relative_to_themselves() {
+        //a brick moving up faster than the ball catches it from below and sends it back up at the speed it had
+        let velocity = Vec3::new(0.0, 100.0, 0.0);
+        let bounced = bounce(velocity, Vec3::new(0.0, 300.0, 0.0), Vec2::new(0.0, 1.0)).unwrap();
+        assert!(bounced.y() > 0.0);
+        assert!((bounced.length() - velocity.length()).abs() < 0.001);
+
+        //but one moving away faster than the ball never gets hit
+        assert_eq!(
+            bounce(
+                Vec3::new(0.0, -100.0, 0.0),
+                Vec3::new(0.0, -300.0, 0.0),
+                Vec2::new(0.0, 1.0)
+            ),
+            None
+        );
 
-fn negate_x(to_reflect: &mut Vec3) {
-    *to_reflect.x_mut() = -to_reflect.x();
+        //one coming up into a ball at an angle sends it off steeper than a still one would
+        let velocity = Vec3::new(100.0, -100.0, 0.0);
+        let bounced = bounce(velocity, Vec3::new(0.0, 100.0, 0.0), Vec2::new(0.0, 1.0)).unwrap();
+        assert!(bounced.y() > bounced.x() && bounced.x() > 0.0);
+        assert!((bounced.length() - velocity.length()).abs() < 0.001);
+    }
+
+    //a headless game for the computer to play, stepped by hand rather than with a runner so it can be stopped whenever.
+    //High scores, achievements and stats go somewhere they won't mix with anyone's real ones, which is also handed back so
+    //it can be cleared away
+    fn headless_game(name: &str, level_source: LevelSource) -> (App, PathBuf) {
+        let save_directory = env::temp_dir().join(format!("breakout-{}-{}", name, process::id()));
+
+        let mut builder = App::build();
+        builder
+            .add_plugin(HeadlessPlugin::default())
+            .add_resource(Progress::load(&save_directory.join("progress.ron")).unwrap())
+            .add_resource(StatsExport {
+                directory: save_directory.join("stats"),
+                ..Default::default()
+            })
+            .add_plugin(BreakoutPlugin {
+                level_source,
+                players: vec![PaddleInput::Ai(clumsy())],
+            });
+        let mut app = builder.app;
+        app.startup_schedule
+            .initialize(&mut app.world, &mut app.resources);
+        app.startup_executor.run(
+            &mut app.startup_schedule,
+            &mut app.world,
+            &mut app.resources,
+        );
+        (app, save_directory)
+    }
+
+    fn goals(app: &mut App) -> usize {
+        app.world
+            .query::<&Collider>()
+            .iter()
+            .filter(|collider| matches!(collider, Collider::Goal { .. }))
+            .count()
+    }
+
+    #[test]
+    fn classic_has_a_plain_bottom_wall() {
+        let (mut app, save_directory) = headless_game("classic", LevelSource::Classic);
+        let classic_goals = goals(&mut app);
+        let _ = fs::remove_dir_all(&save_directory);
+        assert_eq!(classic_goals, 0);
+
+        let (mut app, save_directory) = headless_game("endless", LevelSource::Endless { seed: 29 });
+        let endless_goals = goals(&mut app);
+        let _ = fs::remove_dir_all(&save_directory);
+        assert_eq!(endless_goals, 1);
+    }
+
+    #[test]
+    fn unattended_game_ends() {
+        //five minutes of game time, which is far longer than it should take
+        const MAX_FRAMES: usize = 60 * 60 * 5;
+        //endless levels, as it's those that have lives to run out of
+        let (mut app, save_directory) =
+            headless_game("unattended", LevelSource::Endless { seed: 29 });
+
+        let mut frames = 0;
+        while frames < MAX_FRAMES && app.resources.get::<GameState>().unwrap().is_simulating() {
+            app.update();
+            frames += 1;
+        }
+        let game_state = *app.resources.get::<GameState>().unwrap();
+        let _ = fs::remove_dir_all(&save_directory);
+        assert!(
+            matches!(game_state, GameState::GameOver | GameState::Cleared),
+            "the game was still {:?} after {} frames",
+            game_state,
+            frames
+        );
+    }
 }
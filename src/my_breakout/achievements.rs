@@ -0,0 +1,212 @@
+//Achievements, each one a name and a condition listed in ACHIEVEMENTS. The conditions are checked against the game's events
+//(bricks breaking, lives lost, levels cleared, games ending), with a look at the ball's speed while it's being played.
+//Unlocking one pops up a toast for a few seconds and saves it with the rest of the progress.
+//Like the stats, the attract mode demo doesn't get to unlock anything
+
+use bevy::prelude::*;
+use std::collections::VecDeque;
+
+use super::locale::Localization;
+use super::progress::Progress;
+use super::{Ball, GameEvent, GameState, RestartGame, BALL_SPEED};
+
+const TOAST_SECONDS: f32 = 3.0;
+
+/// What has to happen for an achievement to unlock
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Condition {
+    /// Clear a level without losing a life on it
+    FlawlessLevel,
+    /// Break this many bricks in a row without the ball going back to a paddle
+    Combo(u32),
+    /// Get the ball going at least this fast
+    BallSpeed(f32),
+    /// Clear a game's last level. Only games with a last level count, so endless mode never does
+    FinishCampaign,
+}
+
+pub struct Achievement {
+    /// What it's saved as, so it mustn't change once released. Its name is the `achievement.<id>` string
+    pub id: &'static str,
+    pub condition: Condition,
+}
+
+pub const ACHIEVEMENTS: &[Achievement] = &[
+    Achievement {
+        id: "flawless_level",
+        condition: Condition::FlawlessLevel,
+    },
+    Achievement {
+        id: "combo_20",
+        condition: Condition::Combo(20),
+    },
+    Achievement {
+        id: "ball_speed_800",
+        condition: Condition::BallSpeed(BALL_SPEED * 2.0),
+    },
+    Achievement {
+        id: "finish_campaign",
+        condition: Condition::FinishCampaign,
+    },
+];
+
+pub struct AchievementsPlugin;
+
+impl Plugin for AchievementsPlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        app.init_resource::<AchievementTracker>()
+            .init_resource::<Toasts>()
+            .add_startup_system(toast_setup.system())
+            .add_system(achievement_event_system.system())
+            .add_system(achievement_state_system.system())
+            .add_system(toast_system.system());
+    }
+}
+
+/// Resource with what the conditions need to know about the game so far
+#[derive(Default)]
+struct AchievementTracker {
+    lost_life_this_level: bool,
+}
+
+/// Resource with the ids of achievements waiting to be shown, the first one being on screen
+#[derive(Default)]
+struct Toasts {
+    queue: VecDeque<&'static str>,
+    shown_for: f32,
+}
+
+struct ToastText;
+
+/// Unlocks every achievement whose condition is met, if it isn't already
+fn unlock(progress: &mut Progress, toasts: &mut Toasts, met: impl Fn(&Condition) -> bool) {
+    let mut unlocked_any = false;
+    for achievement in ACHIEVEMENTS.iter() {
+        if met(&achievement.condition) && !progress.achievements.contains(achievement.id) {
+            progress.achievements.insert(achievement.id.to_string());
+            toasts.queue.push_back(achievement.id);
+            unlocked_any = true;
+        }
+    }
+    if unlocked_any {
+        progress.save_or_warn();
+    }
+}
+
+fn achievement_event_system(
+    game_state: Res<GameState>,
+    mut restart_reader: Local<EventReader<RestartGame>>,
+    restart_events: Res<Events<RestartGame>>,
+    mut game_event_reader: Local<EventReader<GameEvent>>,
+    game_events: Res<Events<GameEvent>>,
+    mut tracker: ResMut<AchievementTracker>,
+    mut progress: ResMut<Progress>,
+    mut toasts: ResMut<Toasts>,
+) {
+    if restart_reader.iter(&restart_events).count() > 0 {
+        *tracker = AchievementTracker::default();
+    }
+
+    for event in game_event_reader.iter(&game_events) {
+        if *game_state == GameState::Attract {
+            continue;
+        }
+        match event {
+            GameEvent::BrickBroken { combo, .. } => {
+                let combo = *combo;
+                unlock(
+                    &mut progress,
+                    &mut toasts,
+                    |condition| matches!(condition, Condition::Combo(needed) if combo >= *needed),
+                );
+            }
+            GameEvent::PaddleHit { .. } => {}
+            GameEvent::LifeLost { .. } => tracker.lost_life_this_level = true,
+            GameEvent::LevelCleared { .. } => {
+                if !tracker.lost_life_this_level {
+                    unlock(&mut progress, &mut toasts, |condition| {
+                        *condition == Condition::FlawlessLevel
+                    });
+                }
+                tracker.lost_life_this_level = false;
+            }
+            //a single player getting every brick is what ends a game as cleared
+            GameEvent::GameEnded {
+                outcome: GameState::Cleared,
+            } => unlock(&mut progress, &mut toasts, |condition| {
+                *condition == Condition::FinishCampaign
+            }),
+            GameEvent::GameEnded { .. } => {}
+        }
+    }
+}
+
+/// Checks the conditions that depend on how the game is going rather than on something happening
+fn achievement_state_system(
+    game_state: Res<GameState>,
+    mut progress: ResMut<Progress>,
+    mut toasts: ResMut<Toasts>,
+    mut ball_query: Query<&Ball>,
+) {
+    if *game_state != GameState::Playing {
+        return;
+    }
+    let mut speed: f32 = 0.0;
+    for ball in &mut ball_query.iter() {
+        speed = speed.max(ball.velocity.length());
+    }
+    unlock(
+        &mut progress,
+        &mut toasts,
+        |condition| matches!(condition, Condition::BallSpeed(needed) if speed >= *needed),
+    );
+}
+
+fn toast_setup(mut commands: Commands, asset_server: Res<AssetServer>) {
+    commands
+        .spawn(TextComponents {
+            text: Text {
+                font: asset_server.load("assets/fonts/FiraSans-Bold.ttf").unwrap(),
+                value: "".to_string(),
+                style: TextStyle {
+                    color: Color::rgb(0.9, 0.6, 0.1),
+                    font_size: 28.0,
+                },
+            },
+            style: Style {
+                position_type: PositionType::Absolute,
+                position: Rect {
+                    bottom: Val::Px(40.0),
+                    left: Val::Px(200.0),
+                    ..Default::default()
+                },
+                ..Default::default()
+            },
+            ..Default::default()
+        })
+        .with(ToastText);
+}
+
+/// Shows each unlocked achievement for a few seconds, one after another
+fn toast_system(
+    localization: Res<Localization>,
+    time: Res<Time>,
+    mut toasts: ResMut<Toasts>,
+    mut query: Query<(&ToastText, &mut Text)>,
+) {
+    if !toasts.queue.is_empty() {
+        toasts.shown_for += time.delta_seconds;
+        if toasts.shown_for >= TOAST_SECONDS {
+            toasts.queue.pop_front();
+            toasts.shown_for = 0.0;
+        }
+    }
+
+    let value = toasts.queue.front().map_or("".to_string(), |id| {
+        let name = localization.text(&format!("achievement.{}", id));
+        localization.format("toast.unlocked", &[("name", name)])
+    });
+    for (_toast_text, mut text) in &mut query.iter() {
+        text.value = value.clone();
+    }
+}
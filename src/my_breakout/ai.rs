@@ -0,0 +1,279 @@
+//A computer controlled paddle
+//It predicts where the ball will come down by folding the ball's straight line path back and forth between the side walls,
+//then heads there with human-like flaws: it only re-reads the ball every so often, misjudges by a bit and has a top speed
+
+use bevy::{app::AppExit, prelude::*};
+
+use super::level_generator::SeededRng;
+use super::{clamp_movement_within_bounds, Ball, GameState, Paddle, BOUNDS, WALL_THICKNESS};
+
+/// How good the computer is at playing
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct AiSkill {
+    /// Seconds between looking at the ball and picking a new place to head to
+    pub reaction_delay: f32,
+    /// How far off (at most, either side) the computer's aim can be
+    pub aim_error: f32,
+    /// Fastest the computer will move the paddle
+    pub max_speed: f32,
+}
+
+impl AiSkill {
+    /// Never misses, as long as the ball isn't faster than the paddle
+    pub fn perfect() -> Self {
+        AiSkill {
+            reaction_delay: 0.0,
+            aim_error: 0.0,
+            max_speed: 800.0,
+        }
+    }
+}
+
+impl Default for AiSkill {
+    /// A decent but beatable player, used for the attract mode demo
+    fn default() -> Self {
+        AiSkill {
+            reaction_delay: 0.25,
+            aim_error: 40.0,
+            max_speed: 450.0,
+        }
+    }
+}
+
+/// Who moves a paddle
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum PaddleInput {
+    Keyboard,
+    Ai(AiSkill),
+}
+
+/// Component holding the computer's plan for a paddle. Every paddle gets one so the computer can take over during attract mode
+pub struct AiPaddle {
+    target_x: Option<f32>,
+    replan_timer: Timer,
+    rng: SeededRng,
+}
+
+impl AiPaddle {
+    pub fn new(seed: u64) -> Self {
+        AiPaddle {
+            target_x: None,
+            //starts finished so the first plan happens straight away
+            replan_timer: Timer::from_seconds(0.0, false),
+            rng: SeededRng::new(seed),
+        }
+    }
+}
+
+/// Where the ball will cross `target_y`, accounting for it bouncing off the side walls on the way.
+/// `left` and `right` are the furthest the ball's center can go before bouncing. None if the ball is heading away from `target_y`
+pub fn predict_landing_x(
+    position: Vec2,
+    velocity: Vec2,
+    target_y: f32,
+    left: f32,
+    right: f32,
+) -> Option<f32> {
+    let distance = target_y - position.y();
+    if velocity.y() == 0.0 || distance.signum() != velocity.y().signum() {
+        return None;
+    }
+
+    let time = distance / velocity.y();
+    let unfolded_x = position.x() + velocity.x() * time;
+
+    //Each bounce mirrors the path, so walking the straight line path over a span twice the width and folding it back gives where it ends up
+    let width = right - left;
+    if width <= 0.0 {
+        return Some(left);
+    }
+    let mut offset = (unfolded_x - left) % (2.0 * width);
+    if offset < 0.0 {
+        offset += 2.0 * width;
+    }
+    if offset > width {
+        offset = 2.0 * width - offset;
+    }
+    Some(left + offset)
+}
+
+/// Whether the computer is the one moving this paddle right now
+pub fn ai_skill(paddle: &Paddle, game_state: GameState) -> Option<AiSkill> {
+    match (paddle.input, game_state) {
+        (PaddleInput::Ai(skill), _) => Some(skill),
+        (PaddleInput::Keyboard, GameState::Attract) => Some(AiSkill::default()),
+        (PaddleInput::Keyboard, _) => None,
+    }
+}
+
+pub fn ai_paddle_system(
+    time: Res<Time>,
+    game_state: Res<GameState>,
+    mut paddle_query: Query<(&Paddle, &mut AiPaddle, &mut Transform, &Sprite)>,
+    mut ball_query: Query<(&Ball, &Transform, &Sprite)>,
+) {
+    if !game_state.is_simulating() {
+        return;
+    }
+
+    for (paddle, mut ai, mut transform, sprite) in &mut paddle_query.iter() {
+        let skill = match ai_skill(paddle, *game_state) {
+            Some(skill) => skill,
+            None => continue,
+        };
+        let paddle_position = transform.translation();
+
+        ai.replan_timer.tick(time.delta_seconds);
+        if ai.replan_timer.finished {
+            ai.replan_timer = Timer::from_seconds(skill.reaction_delay, false);
+
+            //go after whichever ball will get to us first, otherwise follow the closest one
+            let mut soonest: Option<(f32, f32)> = None;
+            let mut closest: Option<(f32, f32)> = None;
+            for (ball, ball_transform, ball_sprite) in &mut ball_query.iter() {
+                let ball_position = ball_transform.translation().truncate();
+                let ball_half_width = ball_sprite.size.x() / 2.0;
+                let left = -BOUNDS.0 + WALL_THICKNESS / 2.0 + ball_half_width;
+                let right = BOUNDS.0 - WALL_THICKNESS / 2.0 - ball_half_width;
+                //the ball touches the paddle once its center is half a ball out from the paddle's face (which faces the center line)
+                let facing = -paddle_position.y().signum();
+                let contact_y = paddle_position.y()
+                    + facing * (sprite.size.y() / 2.0 + ball_sprite.size.y() / 2.0);
+
+                let velocity = ball.velocity.truncate();
+                if let Some(landing_x) =
+                    predict_landing_x(ball_position, velocity, contact_y, left, right)
+                {
+                    let time_to_land = (contact_y - ball_position.y()) / velocity.y();
+                    if soonest.map_or(true, |(best_time, _)| time_to_land < best_time) {
+                        soonest = Some((time_to_land, landing_x));
+                    }
+                }
+
+                let distance = (ball_position.y() - paddle_position.y()).abs();
+                if closest.map_or(true, |(best_distance, _)| distance < best_distance) {
+                    closest = Some((distance, ball_position.x()));
+                }
+            }
+
+            let aim = soonest.or(closest).map(|(_, x)| x);
+            let error = (ai.rng.next_f32() * 2.0 - 1.0) * skill.aim_error;
+            ai.target_x = aim.map(|x| x + error);
+        }
+
+        if let Some(target_x) = ai.target_x {
+            let max_step = skill.max_speed * time.delta_seconds;
+            let step = (target_x - paddle_position.x())
+                .max(-max_step)
+                .min(max_step);
+            *transform.translation_mut().x_mut() += step;
+            clamp_movement_within_bounds(&sprite.size, &mut transform);
+        }
+    }
+}
+
+/// When nobody is at the keyboard there's nobody to press a key once the game is over, so just quit
+pub fn unattended_exit_system(
+    game_state: Res<GameState>,
+    mut app_exit_events: ResMut<Events<AppExit>>,
+    mut paddle_query: Query<&Paddle>,
+) {
+    if !matches!(*game_state, GameState::Cleared | GameState::GameOver) {
+        return;
+    }
+    let mut unattended = true;
+    for paddle in &mut paddle_query.iter() {
+        unattended &= matches!(paddle.input, PaddleInput::Ai(_));
+    }
+    if unattended {
+        app_exit_events.send(AppExit);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn straight_down_lands_right_below() {
+        let landing = predict_landing_x(
+            Vec2::new(10.0, 100.0),
+            Vec2::new(0.0, -50.0),
+            0.0,
+            -100.0,
+            100.0,
+        );
+        assert_eq!(landing, Some(10.0));
+    }
+
+    #[test]
+    fn heading_away_never_lands() {
+        let away = predict_landing_x(
+            Vec2::new(0.0, 100.0),
+            Vec2::new(10.0, 50.0),
+            0.0,
+            -100.0,
+            100.0,
+        );
+        assert_eq!(away, None);
+        let sideways = predict_landing_x(
+            Vec2::new(0.0, 100.0),
+            Vec2::new(10.0, 0.0),
+            0.0,
+            -100.0,
+            100.0,
+        );
+        assert_eq!(sideways, None);
+    }
+
+    #[test]
+    fn bounces_off_the_side_walls() {
+        //100 to the right wall then 50 back
+        let once = predict_landing_x(
+            Vec2::new(0.0, 150.0),
+            Vec2::new(1.0, -1.0),
+            0.0,
+            -100.0,
+            100.0,
+        );
+        assert_eq!(once, Some(50.0));
+        //100 to the right wall, 200 across to the left one and 50 back
+        let twice = predict_landing_x(
+            Vec2::new(0.0, 350.0),
+            Vec2::new(1.0, -1.0),
+            0.0,
+            -100.0,
+            100.0,
+        );
+        assert_eq!(twice, Some(-50.0));
+        let leftwards = predict_landing_x(
+            Vec2::new(0.0, 150.0),
+            Vec2::new(-1.0, -1.0),
+            0.0,
+            -100.0,
+            100.0,
+        );
+        assert_eq!(leftwards, Some(-50.0));
+    }
+
+    #[test]
+    fn always_lands_between_the_walls() {
+        let mut rng = SeededRng::new(7);
+        for _ in 0..1000 {
+            let position = Vec2::new(rng.next_f32() * 200.0 - 100.0, 300.0);
+            let velocity = Vec2::new(
+                rng.next_f32() * 2000.0 - 1000.0,
+                -(rng.next_f32() * 1000.0 + 1.0),
+            );
+            let landing = predict_landing_x(position, velocity, -250.0, -100.0, 100.0)
+                .expect("the ball is heading down towards the target");
+            assert!(
+                landing >= -100.0 && landing <= 100.0,
+                "{:?} going {:?} landed at {}",
+                position,
+                velocity,
+                landing
+            );
+        }
+    }
+}
@@ -0,0 +1,360 @@
+//A uniform grid over the arena so the ball only has to be checked against the bricks near it instead of every brick there is
+//Each brick is filed under every cell its bounds cover, and a ball looks up the cells covered by where it is plus where it's
+//about to be this frame. Walls and paddles are few enough that they're still checked directly
+
+use bevy::prelude::*;
+use std::{
+    collections::{HashMap, HashSet},
+    env, fs,
+    hash::Hash,
+    process,
+    time::{Duration, Instant},
+};
+
+use super::ai::{AiSkill, PaddleInput};
+use super::console::ConsoleState;
+use super::level::{Brick, BrickSpec, BrickType, LevelLayout, LevelSource};
+use super::progress::Progress;
+use super::stats::StatsExport;
+use super::{BreakoutPlugin, HeadlessPlugin, BALL_COLLISION_STAGE};
+use crate::geometry::Aabb;
+
+//A bit bigger than a brick, so most bricks only land in a cell or two
+const CELL_SIZE: f32 = 64.0;
+
+/// A grid of cells, each listing the items whose bounds cover it
+pub struct UniformGrid<T> {
+    cell_size: f32,
+    cells: HashMap<(i32, i32), Vec<T>>,
+    bounds: HashMap<T, Aabb>,
+}
+
+/// Resource with every brick filed by where it is
+pub type BrickGrid = UniformGrid<Entity>;
+
+impl<T> Default for UniformGrid<T>
+where
+    T: Copy + Eq + Hash,
+{
+    fn default() -> Self {
+        UniformGrid::new(CELL_SIZE)
+    }
+}
+
+impl<T> UniformGrid<T>
+where
+    T: Copy + Eq + Hash,
+{
+    pub fn new(cell_size: f32) -> Self {
+        UniformGrid {
+            cell_size,
+            cells: HashMap::new(),
+            bounds: HashMap::new(),
+        }
+    }
+
+    /// A grid with just the one cell, so every query goes through every item. Anything divided by an infinite cell size
+    /// comes out as 0 (or -0, which floors to the same cell)
+    pub fn single_cell() -> Self {
+        UniformGrid::new(f32::INFINITY)
+    }
+
+    /// Every cell the bounds cover, a row at a time
+    fn cells_covering(&self, bounds: &Aabb) -> impl Iterator<Item = (i32, i32)> {
+        let cell_size = self.cell_size;
+        let to_cell = move |value: f32| (value / cell_size).floor() as i32;
+        let (min_x, max_x) = (to_cell(bounds.min.x()), to_cell(bounds.max.x()));
+        let (min_y, max_y) = (to_cell(bounds.min.y()), to_cell(bounds.max.y()));
+        (min_y..=max_y).flat_map(move |y| (min_x..=max_x).map(move |x| (x, y)))
+    }
+
+    /// Files the item under its bounds, replacing wherever it was filed before
+    pub fn insert(&mut self, item: T, bounds: Aabb) {
+        if self.bounds.get(&item) == Some(&bounds) {
+            return;
+        }
+        self.remove(item);
+        for cell in self.cells_covering(&bounds).collect::<Vec<_>>() {
+            self.cells.entry(cell).or_insert_with(Vec::new).push(item);
+        }
+        self.bounds.insert(item, bounds);
+    }
+
+    pub fn remove(&mut self, item: T) {
+        let bounds = match self.bounds.remove(&item) {
+            Some(bounds) => bounds,
+            None => return,
+        };
+        for cell in self.cells_covering(&bounds).collect::<Vec<_>>() {
+            if let Some(items) = self.cells.get_mut(&cell) {
+                items.retain(|other| *other != item);
+                if items.is_empty() {
+                    self.cells.remove(&cell);
+                }
+            }
+        }
+    }
+
+    /// What the item was last filed under
+    pub fn bounds(&self, item: T) -> Option<&Aabb> {
+        self.bounds.get(&item)
+    }
+
+    /// Every item whose bounds overlap the area (or touch it), each listed once
+    pub fn query(&self, area: &Aabb) -> Vec<T> {
+        let mut seen = HashSet::new();
+        let mut found = Vec::new();
+        for cell in self.cells_covering(area) {
+            for item in self.cells.get(&cell).into_iter().flatten() {
+                if seen.insert(*item) && touches(&self.bounds[item], area) {
+                    found.push(*item);
+                }
+            }
+        }
+        found
+    }
+}
+
+fn touches(a: &Aabb, b: &Aabb) -> bool {
+    a.min.x() <= b.max.x()
+        && a.max.x() >= b.min.x()
+        && a.min.y() <= b.max.y()
+        && a.max.y() >= b.min.y()
+}
+
+/// The area a ball could touch this frame, covering it where it is now and where it's headed
+pub fn swept_bounds(center: Vec2, radius: f32, displacement: Vec2) -> Aabb {
+    let here = Aabb::from_center_size(center, Vec2::splat(radius * 2.0));
+    let there = Aabb::from_center_size(center + displacement, Vec2::splat(radius * 2.0));
+    Aabb::new(here.min.min(there.min), here.max.max(there.max))
+}
+
+/// Keeps the grid in step with bricks being spawned, moved and resized
+pub fn brick_grid_system(
+    mut grid: ResMut<BrickGrid>,
+    mut moved_query: Query<(Entity, &Brick, Changed<Transform>, &Sprite)>,
+    mut resized_query: Query<(Entity, &Brick, &Transform, Changed<Sprite>)>,
+) {
+    for (entity, _brick, transform, sprite) in &mut moved_query.iter() {
+        grid.insert(entity, Aabb::from_sprite(&transform, &sprite));
+    }
+    for (entity, _brick, transform, sprite) in &mut resized_query.iter() {
+        grid.insert(entity, Aabb::from_sprite(&transform, &sprite));
+    }
+}
+
+/// Takes despawned bricks back out of the grid. This has to run in a later stage than whatever despawned them, as the
+/// despawns only happen at the end of the stage and are forgotten about by the next frame
+pub fn brick_grid_removal_system(mut grid: ResMut<BrickGrid>, removed_query: Query<&Brick>) {
+    for entity in removed_query.removed::<Brick>() {
+        grid.remove(*entity);
+    }
+}
+
+//What the benchmark's timing of the ball collision stage has come to so far
+#[derive(Default)]
+struct CollisionTiming {
+    started: Option<Instant>,
+    total: Duration,
+    frames: u32,
+}
+
+fn collision_timing_start_system(mut timing: ResMut<CollisionTiming>) {
+    timing.started = Some(Instant::now());
+}
+
+fn collision_timing_end_system(mut timing: ResMut<CollisionTiming>) {
+    if let Some(started) = timing.started.take() {
+        timing.total += started.elapsed();
+        timing.frames += 1;
+    }
+}
+
+/// Plays a headless game on a level of 2000 bricks with 50 balls in it, timing the ball collision stage with and without
+/// the grid, and prints how the two compare. Without the grid every brick is in the one cell, so each ball gets checked
+/// against all of them
+pub fn benchmark() {
+    const BRICK_COLUMNS: usize = 50;
+    const BRICK_ROWS: usize = 40;
+    const BALLS: usize = 50;
+    const WARM_UP_FRAMES: u32 = 10;
+    const FRAMES: u32 = 200;
+
+    let brick_size = Vec2::new(16.0, 6.0);
+    let mut bricks = Vec::new();
+    for row in 0..BRICK_ROWS {
+        for column in 0..BRICK_COLUMNS {
+            bricks.push(BrickSpec {
+                position: Vec2::new(
+                    -425.0 + column as f32 * (brick_size.x() + 1.4),
+                    -20.0 + row as f32 * (brick_size.y() + 1.0),
+                ),
+                size: brick_size,
+                //tough, so the level stays dense for longer
+                brick_type: BrickType::Tough,
+                color: None,
+                path: None,
+            });
+        }
+    }
+    let layout = LevelLayout {
+        bricks,
+        hazards: Vec::new(),
+    };
+
+    //the progress and stats of a benchmark go somewhere they won't mix with anyone's real ones
+    let save_directory = env::temp_dir().join(format!("breakout-benchmark-{}", process::id()));
+    let run = |grid: BrickGrid| {
+        let mut builder = App::build();
+        builder
+            .add_plugin(HeadlessPlugin::default())
+            .add_resource(Progress::load(&save_directory.join("progress.ron")).unwrap())
+            .add_resource(StatsExport {
+                directory: save_directory.join("stats"),
+                ..Default::default()
+            })
+            .add_plugin(BreakoutPlugin {
+                level_source: LevelSource::Custom(layout.clone()),
+                players: vec![PaddleInput::Ai(AiSkill::perfect())],
+            })
+            .add_resource(grid)
+            .init_resource::<CollisionTiming>()
+            .add_stage_before(BALL_COLLISION_STAGE, "collision_timing_start")
+            .add_stage_after(BALL_COLLISION_STAGE, "collision_timing_end")
+            .add_system_to_stage(
+                "collision_timing_start",
+                collision_timing_start_system.system(),
+            )
+            .add_system_to_stage("collision_timing_end", collision_timing_end_system.system());
+
+        let mut app = builder.app;
+        app.startup_schedule
+            .initialize(&mut app.world, &mut app.resources);
+        app.startup_executor.run(
+            &mut app.startup_schedule,
+            &mut app.world,
+            &mut app.resources,
+        );
+        {
+            //no ball can be lost, so they all stay in play the whole time
+            let mut console = app.resources.get_mut::<ConsoleState>().unwrap();
+            console.queue("god");
+            for _ in 1..BALLS {
+                console.queue("spawn_ball");
+            }
+        }
+
+        for _ in 0..WARM_UP_FRAMES {
+            app.update();
+        }
+        *app.resources.get_mut::<CollisionTiming>().unwrap() = CollisionTiming::default();
+        for _ in 0..FRAMES {
+            app.update();
+        }
+
+        let timing = app.resources.get::<CollisionTiming>().unwrap();
+        let bricks_left = app.world.query::<&Brick>().iter().count();
+        (
+            timing.total.as_secs_f64() * 1_000_000.0 / timing.frames as f64,
+            bricks_left,
+        )
+    };
+
+    let every_brick = run(BrickGrid::single_cell());
+    let gridded = run(BrickGrid::default());
+    let _ = fs::remove_dir_all(&save_directory);
+
+    println!(
+        "{} bricks, {} balls, averaged over {} frames",
+        BRICK_COLUMNS * BRICK_ROWS,
+        BALLS,
+        FRAMES
+    );
+    println!(
+        "  every ball against every brick: {:>8.1}us per frame ({} bricks left)",
+        every_brick.0, every_brick.1
+    );
+    println!(
+        "  through the grid:               {:>8.1}us per frame ({} bricks left)",
+        gridded.0, gridded.1
+    );
+    println!("  {:.1}x faster", every_brick.0 / gridded.0);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::level_generator::SeededRng;
+    use super::*;
+
+    fn random_aabb(rng: &mut SeededRng, extent: f32, max_size: f32) -> Aabb {
+        let center = Vec2::new(
+            (rng.next_f32() * 2.0 - 1.0) * extent,
+            (rng.next_f32() * 2.0 - 1.0) * extent,
+        );
+        let size = Vec2::new(rng.next_f32() * max_size, rng.next_f32() * max_size);
+        Aabb::from_center_size(center, size)
+    }
+
+    #[test]
+    fn query_finds_the_same_as_checking_everything() {
+        let mut rng = SeededRng::new(34);
+        let mut grid = UniformGrid::new(CELL_SIZE);
+        let mut items: Vec<Option<Aabb>> = Vec::new();
+        for item in 0..500 {
+            let bounds = random_aabb(&mut rng, 600.0, 150.0);
+            grid.insert(item, bounds);
+            items.push(Some(bounds));
+        }
+        //move some and take some out again, which has to leave nothing behind in the cells they were in
+        for _ in 0..200 {
+            let item = rng.range(0, items.len() as u32) as usize;
+            if rng.chance(0.5) {
+                grid.remove(item);
+                items[item] = None;
+            } else {
+                let bounds = random_aabb(&mut rng, 600.0, 150.0);
+                grid.insert(item, bounds);
+                items[item] = Some(bounds);
+            }
+        }
+
+        for _ in 0..500 {
+            let area = random_aabb(&mut rng, 700.0, 300.0);
+            let mut found = grid.query(&area);
+            found.sort();
+            let expected: Vec<usize> = items
+                .iter()
+                .enumerate()
+                .filter(|(_item, bounds)| bounds.map_or(false, |bounds| touches(&bounds, &area)))
+                .map(|(item, _bounds)| item)
+                .collect();
+            assert_eq!(found, expected, "querying {:?}", area);
+        }
+    }
+
+    #[test]
+    fn removing_everything_empties_the_cells() {
+        let mut rng = SeededRng::new(35);
+        let mut grid = UniformGrid::new(CELL_SIZE);
+        for item in 0..100 {
+            grid.insert(item, random_aabb(&mut rng, 600.0, 150.0));
+        }
+        for item in 0..100 {
+            grid.remove(item);
+        }
+        assert!(grid.cells.is_empty());
+        assert!(grid.bounds.is_empty());
+    }
+
+    #[test]
+    fn a_single_cell_holds_everything() {
+        let mut rng = SeededRng::new(34);
+        let mut grid = UniformGrid::single_cell();
+        for item in 0..100 {
+            grid.insert(item, random_aabb(&mut rng, 600.0, 150.0));
+        }
+        assert_eq!(grid.cells.len(), 1);
+        let everywhere = Aabb::from_center_size(Vec2::zero(), Vec2::splat(2000.0));
+        assert_eq!(grid.query(&everywhere).len(), 100);
+    }
+}
@@ -0,0 +1,851 @@
+//A drop-down developer console, opened with the key under Escape, for poking at the game while it runs: spawning balls,
+//changing their speed, clearing or loading levels and so on. Tab completes a command's name and Up/Down go through what
+//was typed before.
+//
+//Every command is a ConsoleCommand, registered with add_console_command, so a plugin can add its own next to the built in ones.
+//Commands run with the whole World and every resource at hand (as a thread local system) since there's no telling what a
+//command will want to touch. The same commands can also be run from a script file, one per line, which is how an unattended
+//game gets set up without anyone at the keyboard
+
+use bevy::{ecs::IntoThreadLocalSystem, prelude::*, window::ReceivedCharacter};
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+
+use super::level::{Brick, LevelLayout, LevelProgress, LevelSource};
+use super::theme::ColorRole;
+use super::{serve, server, Ball, Collider, RestartGame, Scoreboard, BALL_SIZE, BALL_SPEED};
+
+const TOGGLE_KEY: KeyCode = KeyCode::Grave;
+//how many lines of output stay on screen above the input line
+const OUTPUT_LINES: usize = 8;
+const TEXT_SIZE: f32 = 20.0;
+const HISTORY_LIMIT: usize = 50;
+
+/// Something that can be typed into the console
+pub trait ConsoleCommand: Send + Sync + 'static {
+    /// What gets typed to run it
+    fn name(&self) -> &'static str;
+    /// How to use it, shown by `help`
+    fn usage(&self) -> &'static str;
+    /// Runs the command with whatever followed its name, returning what to print
+    fn run(
+        &self,
+        args: &[&str],
+        world: &mut World,
+        resources: &mut Resources,
+    ) -> Result<String, String>;
+}
+
+/// Lets plugins register console commands of their own
+pub trait AddConsoleCommand {
+    fn add_console_command(&mut self, command: impl ConsoleCommand) -> &mut Self;
+}
+
+impl AddConsoleCommand for AppBuilder {
+    fn add_console_command(&mut self, command: impl ConsoleCommand) -> &mut Self {
+        //whichever plugin registers first sets up the registry, so the console plugin doesn't have to be added beforehand
+        if self.resources().get::<ConsoleCommands>().is_none() {
+            self.init_resource::<ConsoleCommands>();
+        }
+        self.resources_mut()
+            .get_mut::<ConsoleCommands>()
+            .unwrap()
+            .0
+            .push(Arc::new(command));
+        self
+    }
+}
+
+/// Resource with every command the console knows
+#[derive(Default)]
+pub struct ConsoleCommands(Vec<Arc<dyn ConsoleCommand>>);
+
+impl ConsoleCommands {
+    fn find(&self, name: &str) -> Option<Arc<dyn ConsoleCommand>> {
+        self.0
+            .iter()
+            .find(|command| command.name() == name)
+            .cloned()
+    }
+}
+
+/// Resource with a script file for the console to run once the game has started
+pub struct ConsoleScript(pub PathBuf);
+
+pub struct DevConsolePlugin;
+
+impl Plugin for DevConsolePlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        app.init_resource::<ConsoleState>()
+            .add_startup_system(console_setup.system())
+            .add_system(console_input_system.system())
+            .add_system(console_text_system.system())
+            .add_system(console_execute_system.thread_local_system())
+            .add_console_command(SpawnBall)
+            .add_console_command(SetSpeed)
+            .add_console_command(ClearBricks)
+            .add_console_command(LoadLevel)
+            .add_console_command(Lives)
+            .add_console_command(God)
+            .add_console_command(Seed);
+    }
+}
+
+/// What's been typed and printed
+#[derive(Default)]
+pub struct ConsoleState {
+    pub open: bool,
+    input: String,
+    output: Vec<String>,
+    history: Vec<String>,
+    /// Where Up/Down has got to in the history, None while typing something new
+    history_index: Option<usize>,
+    /// Lines waiting to be run, which happens with the World to hand rather than in the input system
+    pending: Vec<String>,
+    script_loaded: bool,
+}
+
+impl ConsoleState {
+    /// Runs a line as if it had been typed in
+    pub fn queue(&mut self, line: &str) {
+        self.pending.push(line.to_string());
+    }
+
+    fn print(&mut self, line: String) {
+        self.output.push(line);
+        let overflow = self.output.len().saturating_sub(OUTPUT_LINES);
+        self.output.drain(..overflow);
+    }
+
+    /// Brings back the line typed before the one being shown (the latest one to start with)
+    fn history_back(&mut self) {
+        if self.history.is_empty() {
+            return;
+        }
+        let index = match self.history_index {
+            Some(index) => index.saturating_sub(1),
+            None => self.history.len() - 1,
+        };
+        self.history_index = Some(index);
+        self.input = self.history[index].clone();
+    }
+
+    /// Goes the other way through the history, back to an empty line after the latest
+    fn history_forward(&mut self) {
+        if let Some(index) = self.history_index {
+            if index + 1 < self.history.len() {
+                self.history_index = Some(index + 1);
+                self.input = self.history[index + 1].clone();
+            } else {
+                self.history_index = None;
+                self.input.clear();
+            }
+        }
+    }
+
+    /// Queues up what's been typed to be run, remembering it in the history
+    fn submit(&mut self) {
+        let line = std::mem::take(&mut self.input);
+        self.history_index = None;
+        if line.trim().is_empty() {
+            return;
+        }
+        if self.history.last() != Some(&line) {
+            self.history.push(line.clone());
+            let overflow = self.history.len().saturating_sub(HISTORY_LIMIT);
+            self.history.drain(..overflow);
+        }
+        self.print(format!("> {}", line));
+        self.pending.push(line);
+    }
+}
+
+//Marks the console's backdrop, and which line of the console each text is
+struct ConsolePanel;
+struct ConsoleText {
+    line: usize,
+}
+
+fn console_setup(
+    mut commands: Commands,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+    asset_server: Res<AssetServer>,
+) {
+    let font = asset_server.load("assets/fonts/FiraSans-Bold.ttf").unwrap();
+    let height = (OUTPUT_LINES + 1) as f32 * TEXT_SIZE + 10.0;
+    commands
+        .spawn(NodeComponents {
+            style: Style {
+                position_type: PositionType::Absolute,
+                position: Rect {
+                    top: Val::Px(0.0),
+                    left: Val::Px(0.0),
+                    ..Default::default()
+                },
+                size: Size::new(Val::Percent(100.0), Val::Px(height)),
+                ..Default::default()
+            },
+            material: materials.add(Color::rgba(0.0, 0.0, 0.0, 0.8).into()),
+            draw: Draw {
+                is_visible: false,
+                ..Default::default()
+            },
+            ..Default::default()
+        })
+        .with(ConsolePanel);
+
+    //the output lines and then the input line under them
+    for line in 0..=OUTPUT_LINES {
+        commands
+            .spawn(TextComponents {
+                text: Text {
+                    font,
+                    value: "".to_string(),
+                    style: TextStyle {
+                        color: Color::rgb(0.9, 0.9, 0.9),
+                        font_size: TEXT_SIZE,
+                    },
+                },
+                style: Style {
+                    position_type: PositionType::Absolute,
+                    position: Rect {
+                        top: Val::Px(5.0 + line as f32 * TEXT_SIZE),
+                        left: Val::Px(5.0),
+                        ..Default::default()
+                    },
+                    ..Default::default()
+                },
+                ..Default::default()
+            })
+            .with(ConsoleText { line });
+    }
+}
+
+fn console_input_system(
+    keyboard_input: Res<Input<KeyCode>>,
+    mut character_reader: Local<EventReader<ReceivedCharacter>>,
+    character_events: Res<Events<ReceivedCharacter>>,
+    console_commands: Res<ConsoleCommands>,
+    mut console: ResMut<ConsoleState>,
+) {
+    //the toggle key types a character as well, which shouldn't end up in the input
+    let characters: Vec<char> = character_reader
+        .iter(&character_events)
+        .map(|event| event.char)
+        .collect();
+    if keyboard_input.just_pressed(TOGGLE_KEY) {
+        console.open = !console.open;
+        return;
+    }
+    if !console.open {
+        return;
+    }
+
+    for character in characters {
+        if !character.is_control() {
+            console.input.push(character);
+            console.history_index = None;
+        }
+    }
+    if keyboard_input.just_pressed(KeyCode::Back) {
+        console.input.pop();
+    }
+
+    if keyboard_input.just_pressed(KeyCode::Tab) {
+        complete(&mut console, &console_commands);
+    }
+
+    if keyboard_input.just_pressed(KeyCode::Up) {
+        console.history_back();
+    }
+    if keyboard_input.just_pressed(KeyCode::Down) {
+        console.history_forward();
+    }
+
+    if keyboard_input.just_pressed(KeyCode::Return) {
+        console.submit();
+    }
+}
+
+/// Completes the command name being typed as far as every command it could be agrees, listing them if there's more than one
+fn complete(console: &mut ConsoleState, console_commands: &ConsoleCommands) {
+    //only the name gets completed, not its arguments
+    if console.input.contains(' ') {
+        return;
+    }
+    let mut matches: Vec<&str> = console_commands
+        .0
+        .iter()
+        .map(|command| command.name())
+        .chain(std::iter::once("help"))
+        .filter(|name| name.starts_with(console.input.as_str()))
+        .collect();
+    matches.sort();
+
+    match matches.as_slice() {
+        [] => {}
+        [name] => console.input = format!("{} ", name),
+        [first, rest @ ..] => {
+            let mut common = first.len();
+            for name in rest {
+                common = first
+                    .chars()
+                    .zip(name.chars())
+                    .take(common)
+                    .take_while(|(a, b)| a == b)
+                    .count();
+            }
+            console.input = first[..common].to_string();
+            let line = matches.join("  ");
+            console.print(line);
+        }
+    }
+}
+
+fn console_text_system(
+    console: Res<ConsoleState>,
+    mut panel_query: Query<(&ConsolePanel, &mut Draw)>,
+    mut text_query: Query<(&ConsoleText, &mut Text)>,
+) {
+    for (_panel, mut draw) in &mut panel_query.iter() {
+        draw.is_visible = console.open;
+    }
+    for (console_text, mut text) in &mut text_query.iter() {
+        text.value = if !console.open {
+            "".to_string()
+        } else if console_text.line < OUTPUT_LINES {
+            //the output sits at the bottom of its lines, right above the input
+            let blank = OUTPUT_LINES - console.output.len();
+            console_text
+                .line
+                .checked_sub(blank)
+                .and_then(|index| console.output.get(index))
+                .cloned()
+                .unwrap_or_default()
+        } else {
+            format!("> {}_", console.input)
+        };
+    }
+}
+
+/// Runs whatever lines are waiting, loading the script first if there is one
+fn console_execute_system(world: &mut World, resources: &mut Resources) {
+    let script = {
+        let mut console = resources.get_mut::<ConsoleState>().unwrap();
+        if console.script_loaded {
+            None
+        } else {
+            console.script_loaded = true;
+            resources
+                .get::<ConsoleScript>()
+                .map(|script| script.0.clone())
+        }
+    };
+    //script lines go first, and are logged as well since there may be nobody looking at the console
+    let mut echo = false;
+    if let Some(path) = script {
+        match load_script(&path) {
+            Ok(lines) => {
+                let mut console = resources.get_mut::<ConsoleState>().unwrap();
+                console.pending.splice(0..0, lines);
+                echo = true;
+            }
+            Err(error) => log::warn!("Couldn't load {}: {}", path.display(), error),
+        }
+    }
+
+    let pending = std::mem::take(&mut resources.get_mut::<ConsoleState>().unwrap().pending);
+    for line in pending {
+        let output = run_line(&line, world, resources);
+        if echo {
+            log::info!("> {}", line);
+        }
+        for output_line in output.lines() {
+            if echo {
+                log::info!("{}", output_line);
+            }
+            resources
+                .get_mut::<ConsoleState>()
+                .unwrap()
+                .print(output_line.to_string());
+        }
+    }
+}
+
+/// The lines of a script, skipping blank ones and # comments
+fn load_script(path: &Path) -> std::io::Result<Vec<String>> {
+    Ok(fs::read_to_string(path)?
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .map(str::to_string)
+        .collect())
+}
+
+fn run_line(line: &str, world: &mut World, resources: &mut Resources) -> String {
+    let mut words = line.split_whitespace();
+    let name = match words.next() {
+        Some(name) => name,
+        None => return "".to_string(),
+    };
+    let args: Vec<&str> = words.collect();
+
+    //the registry is borrowed from the resources, so the command is taken out of it before being handed the resources
+    let command = {
+        let console_commands = resources.get::<ConsoleCommands>().unwrap();
+        if name == "help" {
+            let mut usages: Vec<&str> = console_commands
+                .0
+                .iter()
+                .map(|command| command.usage())
+                .collect();
+            usages.sort();
+            return usages.join("\n");
+        }
+        console_commands.find(name)
+    };
+
+    match command {
+        Some(command) => match command.run(&args, world, resources) {
+            Ok(output) => output,
+            Err(error) => format!("{}: {}", name, error),
+        },
+        None => format!("Unknown command {}, try help", name),
+    }
+}
+
+/// The error a command hands back when a resource it needs isn't there, as it won't be when the plugin it comes from
+/// isn't part of the game. Used as `resources.get::<T>().ok_or_else(missing::<T>)?`
+pub fn missing<T>() -> String {
+    format!("there's no {} in this game", std::any::type_name::<T>())
+}
+
+pub fn parse_arg<T: std::str::FromStr>(args: &[&str], usage: &str) -> Result<T, String> {
+    match args {
+        [value] => value
+            .parse()
+            .map_err(|_| format!("{} isn't valid, usage: {}", value, usage)),
+        _ => Err(format!("usage: {}", usage)),
+    }
+}
+
+struct SpawnBall;
+
+impl ConsoleCommand for SpawnBall {
+    fn name(&self) -> &'static str {
+        "spawn_ball"
+    }
+
+    fn usage(&self) -> &'static str {
+        "spawn_ball - serves another ball"
+    }
+
+    fn run(
+        &self,
+        _args: &[&str],
+        world: &mut World,
+        resources: &mut Resources,
+    ) -> Result<String, String> {
+        let player_count = resources
+            .get::<Scoreboard>()
+            .ok_or_else(missing::<Scoreboard>)?
+            .players
+            .len();
+        //looks the same as the balls already in play, if there are any
+        let existing = world
+            .query::<(&Ball, &Handle<ColorMaterial>)>()
+            .iter()
+            .map(|(_ball, material)| *material)
+            .next();
+        let material = match existing {
+            Some(material) => material,
+            None => resources
+                .get_mut::<Assets<ColorMaterial>>()
+                .ok_or_else(missing::<Assets<ColorMaterial>>)?
+                .add(Color::rgb(0.8, 0.2, 0.2).into()),
+        };
+
+        let (position, velocity) = serve(player_count, 0);
+        let mut commands = Commands::default();
+        commands
+            .spawn(SpriteComponents {
+                material,
+                transform: Transform::from_translation(position.extend(1.0)),
+                sprite: Sprite::new(Vec2::splat(BALL_SIZE)),
+                ..Default::default()
+            })
+            .with(Ball {
+                velocity,
+                last_hit_by: server(player_count, 0),
+            })
+            .with(ColorRole::Ball);
+        commands.apply(world, resources);
+        Ok("Served another ball".to_string())
+    }
+}
+
+struct SetSpeed;
+
+impl ConsoleCommand for SetSpeed {
+    fn name(&self) -> &'static str {
+        "set_speed"
+    }
+
+    fn usage(&self) -> &'static str {
+        "set_speed <multiplier> - sets every ball's speed to a multiple of the serving speed"
+    }
+
+    fn run(
+        &self,
+        args: &[&str],
+        world: &mut World,
+        _resources: &mut Resources,
+    ) -> Result<String, String> {
+        let multiplier: f32 = parse_arg(args, self.usage())?;
+        if multiplier <= 0.0 {
+            return Err("the multiplier has to be above 0".to_string());
+        }
+        for mut ball in &mut world.query::<&mut Ball>().iter() {
+            if ball.velocity.length_squared() > 0.0 {
+                ball.velocity = ball.velocity.normalize() * BALL_SPEED * multiplier;
+            }
+        }
+        Ok(format!("Ball speed set to {}", BALL_SPEED * multiplier))
+    }
+}
+
+struct ClearBricks;
+
+impl ConsoleCommand for ClearBricks {
+    fn name(&self) -> &'static str {
+        "clear_bricks"
+    }
+
+    fn usage(&self) -> &'static str {
+        "clear_bricks - despawns every brick, clearing the level"
+    }
+
+    fn run(
+        &self,
+        _args: &[&str],
+        world: &mut World,
+        _resources: &mut Resources,
+    ) -> Result<String, String> {
+        let bricks: Vec<Entity> = world
+            .query::<(Entity, &Brick)>()
+            .iter()
+            .map(|(entity, _brick)| entity)
+            .collect();
+        for entity in bricks.iter() {
+            world
+                .despawn(*entity)
+                .map_err(|error| format!("couldn't despawn a brick: {:?}", error))?;
+        }
+        Ok(format!("Cleared {} bricks", bricks.len()))
+    }
+}
+
+struct LoadLevel;
+
+impl ConsoleCommand for LoadLevel {
+    fn name(&self) -> &'static str {
+        "load_level"
+    }
+
+    fn usage(&self) -> &'static str {
+        "load_level <file> - starts the game over on a level file"
+    }
+
+    fn run(
+        &self,
+        args: &[&str],
+        _world: &mut World,
+        resources: &mut Resources,
+    ) -> Result<String, String> {
+        let path: PathBuf = parse_arg(args, self.usage())?;
+        let layout = LevelLayout::load(&path).map_err(|error| error.to_string())?;
+        resources
+            .get_mut::<LevelProgress>()
+            .ok_or_else(missing::<LevelProgress>)?
+            .source = LevelSource::Custom(layout);
+        resources
+            .get_mut::<Events<RestartGame>>()
+            .ok_or_else(missing::<Events<RestartGame>>)?
+            .send(RestartGame);
+        Ok(format!("Loaded {}", path.display()))
+    }
+}
+
+struct Lives;
+
+impl ConsoleCommand for Lives {
+    fn name(&self) -> &'static str {
+        "lives"
+    }
+
+    fn usage(&self) -> &'static str {
+        "lives <count> - sets how many lives every player has left"
+    }
+
+    fn run(
+        &self,
+        args: &[&str],
+        _world: &mut World,
+        resources: &mut Resources,
+    ) -> Result<String, String> {
+        let lives = parse_arg(args, self.usage())?;
+        for player in resources
+            .get_mut::<Scoreboard>()
+            .ok_or_else(missing::<Scoreboard>)?
+            .players
+            .iter_mut()
+        {
+            player.lives = lives;
+        }
+        Ok(format!("Everyone has {} lives", lives))
+    }
+}
+
+//Marks a goal that god mode has turned into a solid wall, so it can be turned back
+struct GodWall {
+    player: usize,
+}
+
+struct God;
+
+impl ConsoleCommand for God {
+    fn name(&self) -> &'static str {
+        "god"
+    }
+
+    fn usage(&self) -> &'static str {
+        "god - toggles the bottom wall being solid, so the ball can't be lost"
+    }
+
+    fn run(
+        &self,
+        _args: &[&str],
+        world: &mut World,
+        _resources: &mut Resources,
+    ) -> Result<String, String> {
+        let walls: Vec<(Entity, usize)> = world
+            .query::<(Entity, &GodWall)>()
+            .iter()
+            .map(|(entity, wall)| (entity, wall.player))
+            .collect();
+        if !walls.is_empty() {
+            for (entity, player) in walls {
+                *world
+                    .get_mut::<Collider>(entity)
+                    .map_err(|error| format!("couldn't restore a goal: {:?}", error))? =
+                    Collider::Goal { player };
+                world
+                    .remove_one::<GodWall>(entity)
+                    .map_err(|error| format!("couldn't restore a goal: {:?}", error))?;
+            }
+            return Ok("God mode off".to_string());
+        }
+
+        //the bottom wall is the first player's goal
+        let goals: Vec<Entity> = world
+            .query::<(Entity, &Collider)>()
+            .iter()
+            .filter(|(_entity, collider)| matches!(collider, Collider::Goal { player: 0 }))
+            .map(|(entity, _collider)| entity)
+            .collect();
+        for entity in goals {
+            *world
+                .get_mut::<Collider>(entity)
+                .map_err(|error| format!("couldn't make a goal solid: {:?}", error))? =
+                Collider::Solid;
+            world
+                .insert_one(entity, GodWall { player: 0 })
+                .map_err(|error| format!("couldn't make a goal solid: {:?}", error))?;
+        }
+        Ok("God mode on".to_string())
+    }
+}
+
+struct Seed;
+
+impl ConsoleCommand for Seed {
+    fn name(&self) -> &'static str {
+        "seed"
+    }
+
+    fn usage(&self) -> &'static str {
+        "seed <number> - starts an endless game over from the given seed"
+    }
+
+    fn run(
+        &self,
+        args: &[&str],
+        _world: &mut World,
+        resources: &mut Resources,
+    ) -> Result<String, String> {
+        let seed = parse_arg(args, self.usage())?;
+        resources
+            .get_mut::<LevelProgress>()
+            .ok_or_else(missing::<LevelProgress>)?
+            .source = LevelSource::Endless { seed };
+        resources
+            .get_mut::<Events<RestartGame>>()
+            .ok_or_else(missing::<Events<RestartGame>>)?
+            .send(RestartGame);
+        Ok(format!("Endless game from seed {}", seed))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::level::BrickType;
+    use super::*;
+
+    fn commands() -> ConsoleCommands {
+        ConsoleCommands(vec![
+            Arc::new(SpawnBall),
+            Arc::new(SetSpeed),
+            Arc::new(ClearBricks),
+            Arc::new(LoadLevel),
+            Arc::new(Lives),
+            Arc::new(God),
+            Arc::new(Seed),
+        ])
+    }
+
+    fn typed(input: &str) -> ConsoleState {
+        ConsoleState {
+            input: input.to_string(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn completes_a_single_match_with_a_space_after() {
+        let mut console = typed("cl");
+        complete(&mut console, &commands());
+        assert_eq!(console.input, "clear_bricks ");
+        assert!(console.output.is_empty());
+
+        let mut console = typed("he");
+        complete(&mut console, &commands());
+        assert_eq!(console.input, "help ");
+    }
+
+    #[test]
+    fn completes_as_far_as_the_matches_agree_and_lists_them() {
+        let mut console = typed("s");
+        complete(&mut console, &commands());
+        assert_eq!(console.input, "s");
+        assert_eq!(console.output, vec!["seed  set_speed  spawn_ball"]);
+
+        let mut console = typed("se");
+        complete(&mut console, &commands());
+        assert_eq!(console.input, "se");
+        assert_eq!(console.output, vec!["seed  set_speed"]);
+
+        let mut console = typed("l");
+        complete(&mut console, &commands());
+        assert_eq!(console.input, "l");
+        assert_eq!(console.output, vec!["lives  load_level"]);
+    }
+
+    #[test]
+    fn leaves_unknown_names_and_arguments_alone() {
+        let mut console = typed("x");
+        complete(&mut console, &commands());
+        assert_eq!(console.input, "x");
+        assert!(console.output.is_empty());
+
+        let mut console = typed("lives 3");
+        complete(&mut console, &commands());
+        assert_eq!(console.input, "lives 3");
+    }
+
+    #[test]
+    fn history_goes_back_and_forward_through_what_was_run() {
+        let mut console = ConsoleState::default();
+        for line in &["god", "lives 5", "seed"] {
+            console.input = line.to_string();
+            console.submit();
+        }
+        assert_eq!(console.pending, vec!["god", "lives 5", "seed"]);
+        assert_eq!(console.input, "");
+
+        console.history_back();
+        assert_eq!(console.input, "seed");
+        console.history_back();
+        assert_eq!(console.input, "lives 5");
+        console.history_back();
+        console.history_back();
+        //it stops at the oldest line
+        assert_eq!(console.input, "god");
+
+        console.history_forward();
+        assert_eq!(console.input, "lives 5");
+        console.history_forward();
+        console.history_forward();
+        //and past the latest is back to an empty line
+        assert_eq!(console.input, "");
+        assert_eq!(console.history_index, None);
+        console.history_forward();
+        assert_eq!(console.input, "");
+    }
+
+    #[test]
+    fn history_skips_blank_lines_and_repeats() {
+        let mut console = ConsoleState::default();
+        for line in &["god", "  ", "god", "seed", "god"] {
+            console.input = line.to_string();
+            console.submit();
+        }
+        assert_eq!(console.history, vec!["god", "seed", "god"]);
+        assert_eq!(console.pending, vec!["god", "god", "seed", "god"]);
+
+        let mut console = ConsoleState::default();
+        console.history_back();
+        assert_eq!(console.input, "");
+        assert_eq!(console.history_index, None);
+    }
+
+    #[test]
+    fn history_keeps_only_the_latest_lines() {
+        let mut console = ConsoleState::default();
+        for number in 0..HISTORY_LIMIT + 10 {
+            console.input = format!("lives {}", number);
+            console.submit();
+        }
+        assert_eq!(console.history.len(), HISTORY_LIMIT);
+        assert_eq!(console.history[0], "lives 10");
+    }
+
+    #[test]
+    fn commands_without_what_they_need_fail_rather_than_panic() {
+        let mut world = World::default();
+        let mut resources = Resources::default();
+        assert!(SpawnBall.run(&[], &mut world, &mut resources).is_err());
+        assert!(Lives.run(&["3"], &mut world, &mut resources).is_err());
+        assert!(Seed.run(&["7"], &mut world, &mut resources).is_err());
+    }
+
+    #[test]
+    fn clearing_bricks_despawns_them_all() {
+        let mut world = World::default();
+        let mut resources = Resources::default();
+        for _ in 0..3 {
+            world.spawn((Brick {
+                brick_type: BrickType::Normal,
+                hits_remaining: 1,
+                color: None,
+            },));
+        }
+        assert_eq!(
+            ClearBricks.run(&[], &mut world, &mut resources),
+            Ok("Cleared 3 bricks".to_string())
+        );
+        assert_eq!(world.query::<&Brick>().iter().count(), 0);
+    }
+}
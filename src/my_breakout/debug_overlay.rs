@@ -0,0 +1,283 @@
+//An F3 overlay for seeing what the game thinks is going on: frame timings, how many balls and bricks there are, how fast the
+//ball is going and what state the game is in, plus the outline of every collider and an arrow along each ball's velocity.
+//Bevy has no line drawing, so the outlines and arrows are made of thin sprites kept in a pool. Each frame the pool is
+//laid out over whatever needs drawing, with any segments left over hidden rather than despawned
+
+use bevy::{
+    diagnostic::{Diagnostics, FrameTimeDiagnosticsPlugin},
+    prelude::*,
+};
+
+use super::{console::ConsoleState, level::Brick, Ball, Collider, GameState};
+use crate::geometry::Aabb;
+use crate::vec3_extension::*;
+
+const TOGGLE_KEY: KeyCode = KeyCode::F3;
+const LINE_THICKNESS: f32 = 2.0;
+//in front of everything else in the arena
+const OVERLAY_Z: f32 = 10.0;
+//how far ahead the velocity arrow reaches, so a faster ball gets a longer arrow
+const ARROW_SECONDS: f32 = 0.25;
+const ARROW_HEAD_LENGTH: f32 = 12.0;
+//text doesn't wrap onto new lines, so every line of stats is a text of its own
+const TEXT_LINES: usize = 4;
+const TEXT_SIZE: f32 = 20.0;
+
+pub struct DebugOverlayPlugin;
+
+impl Plugin for DebugOverlayPlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        app.add_plugin(FrameTimeDiagnosticsPlugin::default())
+            .init_resource::<DebugOverlay>()
+            .add_startup_system(debug_overlay_setup.system())
+            .add_system(debug_toggle_system.system())
+            .add_system(debug_text_system.system())
+            .add_system(debug_shapes_system.system());
+    }
+}
+
+/// Whether the overlay is showing, along with the materials its lines are drawn with
+#[derive(Default)]
+pub struct DebugOverlay {
+    pub visible: bool,
+    materials: Option<LineMaterials>,
+}
+
+#[derive(Clone)]
+struct LineMaterials {
+    solid: Handle<ColorMaterial>,
+    scorable: Handle<ColorMaterial>,
+    goal: Handle<ColorMaterial>,
+    velocity: Handle<ColorMaterial>,
+}
+
+impl LineMaterials {
+    fn for_collider(&self, collider: Collider) -> Handle<ColorMaterial> {
+        match collider {
+            Collider::Solid => self.solid,
+            Collider::Scorable => self.scorable,
+            Collider::Goal { .. } => self.goal,
+        }
+    }
+}
+
+//Marks a sprite belonging to the pool of line segments
+struct DebugLine;
+
+struct DebugText {
+    line: usize,
+}
+
+/// A line from `start` to `end` in one of the overlay's colors
+struct Segment {
+    start: Vec2,
+    end: Vec2,
+    material: Handle<ColorMaterial>,
+}
+
+fn debug_overlay_setup(
+    mut commands: Commands,
+    mut overlay: ResMut<DebugOverlay>,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+    asset_server: Res<AssetServer>,
+) {
+    overlay.materials = Some(LineMaterials {
+        solid: materials.add(Color::rgb(0.1, 0.9, 0.1).into()),
+        scorable: materials.add(Color::rgb(0.9, 0.9, 0.1).into()),
+        goal: materials.add(Color::rgb(0.9, 0.1, 0.1).into()),
+        velocity: materials.add(Color::rgb(0.1, 0.4, 1.0).into()),
+    });
+
+    let font = asset_server.load("assets/fonts/FiraSans-Bold.ttf").unwrap();
+    for line in 0..TEXT_LINES {
+        commands
+            .spawn(TextComponents {
+                text: Text {
+                    font,
+                    value: "".to_string(),
+                    style: TextStyle {
+                        color: Color::rgb(0.1, 0.1, 0.1),
+                        font_size: TEXT_SIZE,
+                    },
+                },
+                style: Style {
+                    position_type: PositionType::Absolute,
+                    position: Rect {
+                        //counting up from the bottom of the window, so the first line is the highest
+                        bottom: Val::Px(5.0 + (TEXT_LINES - 1 - line) as f32 * TEXT_SIZE),
+                        left: Val::Px(5.0),
+                        ..Default::default()
+                    },
+                    ..Default::default()
+                },
+                ..Default::default()
+            })
+            .with(DebugText { line });
+    }
+}
+
+fn debug_toggle_system(
+    keyboard_input: Res<Input<KeyCode>>,
+    console: Res<ConsoleState>,
+    mut overlay: ResMut<DebugOverlay>,
+) {
+    if !console.open && keyboard_input.just_pressed(TOGGLE_KEY) {
+        overlay.visible = !overlay.visible;
+    }
+}
+
+fn debug_text_system(
+    overlay: Res<DebugOverlay>,
+    diagnostics: Res<Diagnostics>,
+    game_state: Res<GameState>,
+    mut ball_query: Query<&Ball>,
+    mut brick_query: Query<&Brick>,
+    mut text_query: Query<(&DebugText, &mut Text)>,
+) {
+    let lines = if overlay.visible {
+        let average = |id| {
+            diagnostics
+                .get(id)
+                .and_then(|diagnostic| diagnostic.average())
+                .unwrap_or(0.0)
+        };
+        let fps = average(FrameTimeDiagnosticsPlugin::FPS);
+        let frame_time = average(FrameTimeDiagnosticsPlugin::FRAME_TIME);
+
+        let mut speeds = Vec::new();
+        for ball in &mut ball_query.iter() {
+            speeds.push(format!("{:.0}", ball.velocity.length()));
+        }
+        let mut bricks = 0;
+        for brick in &mut brick_query.iter() {
+            if brick.brick_type.is_breakable() {
+                bricks += 1;
+            }
+        }
+
+        vec![
+            format!("{:.0} fps  {:.2} ms", fps, frame_time * 1000.0),
+            format!("Balls: {}  Bricks left: {}", speeds.len(), bricks),
+            format!("Ball speed: {}", speeds.join(", ")),
+            format!("State: {:?}", *game_state),
+        ]
+    } else {
+        Vec::new()
+    };
+
+    for (debug_text, mut text) in &mut text_query.iter() {
+        text.value = lines.get(debug_text.line).cloned().unwrap_or_default();
+    }
+}
+
+/// Lays the pool of line sprites out over every collider's outline and every ball's velocity
+fn debug_shapes_system(
+    mut commands: Commands,
+    overlay: Res<DebugOverlay>,
+    mut collider_query: Query<(&Collider, &Transform, &Sprite)>,
+    mut ball_query: Query<(&Ball, &Transform)>,
+    mut line_query: Query<(
+        &DebugLine,
+        &mut Transform,
+        &mut Sprite,
+        &mut Draw,
+        &mut Handle<ColorMaterial>,
+    )>,
+) {
+    let line_materials = match &overlay.materials {
+        Some(line_materials) => line_materials.clone(),
+        None => return,
+    };
+
+    let mut segments = Vec::new();
+    if overlay.visible {
+        for (collider, transform, sprite) in &mut collider_query.iter() {
+            let bounds = Aabb::from_sprite(&transform, &sprite);
+            outline(
+                &mut segments,
+                &bounds,
+                line_materials.for_collider(*collider),
+            );
+        }
+        for (ball, transform) in &mut ball_query.iter() {
+            let start = transform.translation().truncate();
+            arrow(
+                &mut segments,
+                start,
+                start + ball.velocity.truncate() * ARROW_SECONDS,
+                line_materials.velocity,
+            );
+        }
+    }
+
+    let mut segments = segments.into_iter();
+    for (_line, mut transform, mut sprite, mut draw, mut material) in &mut line_query.iter() {
+        match segments.next() {
+            Some(segment) => {
+                let offset = segment.end - segment.start;
+                let midpoint = (segment.start + segment.end) / 2.0;
+                *transform = Transform::from_translation_rotation(
+                    midpoint.extend(OVERLAY_Z),
+                    Quat::from_rotation_z(offset.y().atan2(offset.x())),
+                );
+                sprite.size = Vec2::new(offset.length() + LINE_THICKNESS, LINE_THICKNESS);
+                *material = segment.material;
+                draw.is_visible = true;
+            }
+            None => draw.is_visible = false,
+        }
+    }
+
+    //the pool ran out, so grow it. The new lines get laid out from next frame on
+    for segment in segments {
+        commands
+            .spawn(SpriteComponents {
+                material: segment.material,
+                sprite: Sprite::new(Vec2::zero()),
+                draw: Draw {
+                    is_visible: false,
+                    ..Default::default()
+                },
+                ..Default::default()
+            })
+            .with(DebugLine);
+    }
+}
+
+fn outline(segments: &mut Vec<Segment>, bounds: &Aabb, material: Handle<ColorMaterial>) {
+    let corners = [
+        bounds.min,
+        Vec2::new(bounds.max.x(), bounds.min.y()),
+        bounds.max,
+        Vec2::new(bounds.min.x(), bounds.max.y()),
+    ];
+    for side in 0..corners.len() {
+        segments.push(Segment {
+            start: corners[side],
+            end: corners[(side + 1) % corners.len()],
+            material,
+        });
+    }
+}
+
+fn arrow(segments: &mut Vec<Segment>, start: Vec2, end: Vec2, material: Handle<ColorMaterial>) {
+    let offset = end - start;
+    if offset.length_squared() == 0.0 {
+        return;
+    }
+    segments.push(Segment {
+        start,
+        end,
+        material,
+    });
+
+    //the head's two barbs sweep back from the tip at 30 degrees either side of the shaft
+    let back = -offset.normalize() * ARROW_HEAD_LENGTH;
+    for angle in [30f32, -30.0].iter() {
+        segments.push(Segment {
+            start: end,
+            end: end + back.rotate(angle.to_radians()),
+            material,
+        });
+    }
+}
@@ -0,0 +1,849 @@
+//An in game level editor
+//Bricks are laid out on a snapping grid inside the arena, the level can be test played at any point and saved to / loaded from a level file.
+//Brick paths and hazards can only be written into the level file by hand. The editor keeps them as they are, but they only
+//move or show up while test playing
+//
+//Controls:
+//  Left click          select a brick (dragging moves it) or place a new one on empty space
+//  Right click         delete the brick under the cursor
+//  Delete / Backspace  delete the selected brick
+//  Arrow keys          resize the selected brick
+//  1 / 2 / 3           switch brick type (Normal / Tough / Unbreakable), also changing the selected brick
+//  C                   cycle the selected brick's color through the palette's brick colors
+//  Ctrl+Z / Ctrl+Y     undo / redo (Ctrl+Shift+Z also redoes)
+//  Ctrl+S / Ctrl+L     save / load the level file
+//  Enter               test play the level, pressing it again comes back to editing
+
+use bevy::{prelude::*, window::CursorMoved};
+use std::{io, path::PathBuf};
+
+use super::console::ConsoleState;
+use super::hazards::Hazard;
+use super::level::{
+    spawn_brick, spawn_level, Brick, BrickSpec, BrickType, LevelLayout, LevelProgress, LevelSource,
+};
+use super::locale::{Localization, Message};
+use super::scaling::ArenaScale;
+use super::theme::Accessibility;
+use super::{reset_ball, Ball, GameState, BOUNDS, WALL_THICKNESS};
+
+const GRID_SIZE: f32 = 10.0;
+const DEFAULT_BRICK_SIZE: (f32, f32) = (80.0, 30.0);
+const MIN_BRICK_SIZE: f32 = GRID_SIZE;
+
+pub struct EditorPlugin {
+    pub level_path: PathBuf,
+}
+
+impl Plugin for EditorPlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        //start from the saved level if there is one, otherwise from a blank level
+        let document = match LevelLayout::load(&self.level_path) {
+            Ok(layout) => layout,
+            Err(error) if error.kind() == io::ErrorKind::NotFound => LevelLayout::default(),
+            Err(error) => {
+                log::warn!(
+                    "Couldn't load level {}: {}",
+                    self.level_path.display(),
+                    error
+                );
+                LevelLayout::default()
+            }
+        };
+
+        //the level being played starts out empty, as the breakout setup would otherwise spawn the document's bricks without
+        //them being the editor's. The editor spawns its own, and the document is only handed over once it's test played
+        app.add_resource(GameState::Editing)
+            .add_resource(LevelProgress::new(LevelSource::Custom(
+                LevelLayout::default(),
+            )))
+            .add_resource(Editor::new(self.level_path.clone(), document))
+            .add_startup_system(editor_setup.system())
+            .add_system(editor_cursor_system.system())
+            .add_system(editor_mode_system.system())
+            .add_system(editor_mouse_system.system())
+            .add_system(editor_keyboard_system.system())
+            .add_system(editor_sync_system.system())
+            .add_system(editor_text_system.system());
+    }
+}
+
+/// A single reversible change to the level being edited
+#[derive(Clone, Debug)]
+enum EditOperation {
+    Add {
+        index: usize,
+        brick: BrickSpec,
+    },
+    Remove {
+        index: usize,
+        brick: BrickSpec,
+    },
+    Modify {
+        index: usize,
+        before: BrickSpec,
+        after: BrickSpec,
+    },
+    /// Swapping out the whole level, e.g. when loading from a file
+    Replace {
+        before: LevelLayout,
+        after: LevelLayout,
+    },
+}
+
+impl EditOperation {
+    fn apply(&self, layout: &mut LevelLayout) {
+        match self {
+            EditOperation::Add { index, brick } => layout.bricks.insert(*index, brick.clone()),
+            EditOperation::Remove { index, .. } => {
+                layout.bricks.remove(*index);
+            }
+            EditOperation::Modify { index, after, .. } => layout.bricks[*index] = after.clone(),
+            EditOperation::Replace { after, .. } => *layout = after.clone(),
+        }
+    }
+
+    fn inverse(&self) -> EditOperation {
+        match self.clone() {
+            EditOperation::Add { index, brick } => EditOperation::Remove { index, brick },
+            EditOperation::Remove { index, brick } => EditOperation::Add { index, brick },
+            EditOperation::Modify {
+                index,
+                before,
+                after,
+            } => EditOperation::Modify {
+                index,
+                before: after,
+                after: before,
+            },
+            EditOperation::Replace { before, after } => EditOperation::Replace {
+                before: after,
+                after: before,
+            },
+        }
+    }
+}
+
+/// Undo and redo stacks of operations that have already been applied to the level
+#[derive(Default)]
+struct EditHistory {
+    undo: Vec<EditOperation>,
+    redo: Vec<EditOperation>,
+}
+
+impl EditHistory {
+    /// Records an operation that has already been applied. Anything that was undone can no longer be redone
+    fn record(&mut self, operation: EditOperation) {
+        self.undo.push(operation);
+        self.redo.clear();
+    }
+
+    fn undo(&mut self, layout: &mut LevelLayout) -> bool {
+        match self.undo.pop() {
+            Some(operation) => {
+                operation.inverse().apply(layout);
+                self.redo.push(operation);
+                true
+            }
+            None => false,
+        }
+    }
+
+    fn redo(&mut self, layout: &mut LevelLayout) -> bool {
+        match self.redo.pop() {
+            Some(operation) => {
+                operation.apply(layout);
+                self.undo.push(operation);
+                true
+            }
+            None => false,
+        }
+    }
+}
+
+//A brick being dragged around, remembering where it started so the whole drag becomes a single undo step
+struct Drag {
+    before: BrickSpec,
+    grab_offset: Vec2,
+}
+
+/// Resource holding the level being edited and everything about the editing session
+struct Editor {
+    level_path: PathBuf,
+    document: LevelLayout,
+    history: EditHistory,
+    brick_type: BrickType,
+    selected: Option<usize>,
+    drag: Option<Drag>,
+    //where the mouse is in world coordinates
+    cursor: Vec2,
+    //set whenever the number of bricks changes so their entities get respawned
+    respawn: bool,
+    status: Option<Message>,
+}
+
+impl Editor {
+    fn new(level_path: PathBuf, document: LevelLayout) -> Self {
+        Editor {
+            level_path,
+            document,
+            history: EditHistory::default(),
+            brick_type: BrickType::Normal,
+            selected: None,
+            drag: None,
+            cursor: Vec2::zero(),
+            respawn: true,
+            status: None,
+        }
+    }
+
+    fn perform(&mut self, operation: EditOperation) {
+        operation.apply(&mut self.document);
+        self.history.record(operation);
+        self.respawn = true;
+    }
+
+    /// Changes the selected brick (if any) in a single undoable step
+    fn modify_selected(&mut self, change: impl FnOnce(&mut BrickSpec)) {
+        if let Some(index) = self.selected {
+            let before = self.document.bricks[index].clone();
+            let mut after = before.clone();
+            change(&mut after);
+            if after != before {
+                self.perform(EditOperation::Modify {
+                    index,
+                    before,
+                    after,
+                });
+            }
+        }
+    }
+
+    /// Gives the selected brick the next of the palette's colors. Cycling past the end goes back to the brick type's own color
+    fn cycle_color(&mut self, colors: &[Color]) {
+        self.modify_selected(|brick| {
+            let current = colors.iter().position(|&color| brick.color == Some(color));
+            brick.color = match current {
+                None => colors.first().copied(),
+                Some(index) => colors.get(index + 1).copied(),
+            };
+        });
+    }
+
+    fn delete(&mut self, index: usize) {
+        let brick = self.document.bricks[index].clone();
+        self.perform(EditOperation::Remove { index, brick });
+        self.selected = None;
+    }
+
+    /// The top most brick under the given point
+    fn brick_at(&self, point: Vec2) -> Option<usize> {
+        self.document.bricks.iter().rposition(|brick| {
+            let offset = point - brick.position;
+            offset.x().abs() <= brick.size.x() / 2.0 && offset.y().abs() <= brick.size.y() / 2.0
+        })
+    }
+
+    fn press(&mut self) {
+        let cursor = self.cursor;
+        match self.brick_at(cursor) {
+            Some(index) => {
+                let brick = self.document.bricks[index].clone();
+                self.selected = Some(index);
+                self.drag = Some(Drag {
+                    grab_offset: brick.position - cursor,
+                    before: brick,
+                });
+            }
+            None => {
+                let size = Vec2::new(DEFAULT_BRICK_SIZE.0, DEFAULT_BRICK_SIZE.1);
+                let brick = BrickSpec {
+                    position: clamp_to_arena(snap(cursor), size),
+                    size,
+                    brick_type: self.brick_type,
+                    color: None,
+                    path: None,
+                };
+                let index = self.document.bricks.len();
+                self.perform(EditOperation::Add { index, brick });
+                self.selected = Some(index);
+            }
+        }
+    }
+
+    fn drag_to_cursor(&mut self) {
+        if let (Some(index), Some(drag)) = (self.selected, &self.drag) {
+            let brick = &mut self.document.bricks[index];
+            brick.position = clamp_to_arena(snap(self.cursor + drag.grab_offset), brick.size);
+        }
+    }
+
+    fn release(&mut self) {
+        if let (Some(index), Some(drag)) = (self.selected, self.drag.take()) {
+            let after = self.document.bricks[index].clone();
+            //the brick already moved while dragging so this only needs recording
+            if after != drag.before {
+                self.history.record(EditOperation::Modify {
+                    index,
+                    before: drag.before,
+                    after,
+                });
+            }
+        }
+    }
+
+    fn undo(&mut self) {
+        //a drag still going on becomes its own step first, so undoing takes back the drag rather than losing it
+        self.release();
+        if self.history.undo(&mut self.document) {
+            //indices may have shifted so the selection can't be trusted anymore
+            self.selected = None;
+            self.respawn = true;
+        }
+    }
+
+    fn redo(&mut self) {
+        self.release();
+        if self.history.redo(&mut self.document) {
+            self.selected = None;
+            self.respawn = true;
+        }
+    }
+
+    fn save(&mut self) {
+        let path = self.level_path.display();
+        self.status = Some(match self.document.save(&self.level_path) {
+            Ok(()) => Message::new("editor.saved").arg("path", path),
+            Err(error) => Message::new("editor.save_failed")
+                .arg("path", path)
+                .arg("error", error),
+        });
+    }
+
+    fn load(&mut self) {
+        match LevelLayout::load(&self.level_path) {
+            Ok(layout) => {
+                self.perform(EditOperation::Replace {
+                    before: self.document.clone(),
+                    after: layout,
+                });
+                self.selected = None;
+                self.status =
+                    Some(Message::new("editor.loaded").arg("path", self.level_path.display()));
+            }
+            Err(error) => {
+                self.status = Some(
+                    Message::new("editor.load_failed")
+                        .arg("path", self.level_path.display())
+                        .arg("error", error),
+                );
+            }
+        }
+    }
+}
+
+fn snap(position: Vec2) -> Vec2 {
+    Vec2::new(
+        (position.x() / GRID_SIZE).round() * GRID_SIZE,
+        (position.y() / GRID_SIZE).round() * GRID_SIZE,
+    )
+}
+
+/// Keeps a brick of the given size entirely inside the walls
+fn clamp_to_arena(position: Vec2, size: Vec2) -> Vec2 {
+    let max_x = (BOUNDS.0 - WALL_THICKNESS / 2.0 - size.x() / 2.0).max(0.0);
+    let max_y = (BOUNDS.1 - WALL_THICKNESS / 2.0 - size.y() / 2.0).max(0.0);
+    Vec2::new(
+        position.x().max(-max_x).min(max_x),
+        position.y().max(-max_y).min(max_y),
+    )
+}
+
+//Marks a brick entity as showing the brick at the given index of the editor's level
+struct EditorBrick {
+    index: usize,
+}
+
+struct SelectionHighlight;
+struct EditorText;
+
+fn editor_setup(
+    mut commands: Commands,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+    asset_server: Res<AssetServer>,
+) {
+    commands
+        //drawn slightly bigger and behind the selected brick so it shows up as an outline
+        .spawn(SpriteComponents {
+            material: materials.add(Color::rgb(1.0, 1.0, 1.0).into()),
+            transform: Transform::from_translation(Vec3::new(0.0, 0.0, -0.5)),
+            sprite: Sprite::new(Vec2::zero()),
+            ..Default::default()
+        })
+        .with(SelectionHighlight)
+        .spawn(TextComponents {
+            text: Text {
+                font: asset_server.load("assets/fonts/FiraSans-Bold.ttf").unwrap(),
+                value: "".to_string(),
+                style: TextStyle {
+                    color: Color::rgb(0.1, 0.1, 0.1),
+                    font_size: 20.0,
+                },
+            },
+            style: Style {
+                position_type: PositionType::Absolute,
+                position: Rect {
+                    bottom: Val::Px(5.0),
+                    left: Val::Px(5.0),
+                    ..Default::default()
+                },
+                ..Default::default()
+            },
+            ..Default::default()
+        })
+        .with(EditorText);
+}
+
+fn editor_cursor_system(
+    mut editor: ResMut<Editor>,
+    windows: Res<Windows>,
+    arena_scale: Res<ArenaScale>,
+    mut cursor_reader: Local<EventReader<CursorMoved>>,
+    cursor_events: Res<Events<CursorMoved>>,
+) {
+    for event in cursor_reader.iter(&cursor_events) {
+        if let Some(window) = windows.get(event.id) {
+            //cursor positions are relative to the bottom left of the window while our camera has 0,0 at the center of it,
+            //and is zoomed to fit the arena in the window
+            editor.cursor = arena_scale.window_to_arena(
+                event.position,
+                Vec2::new(window.width as f32, window.height as f32),
+            );
+        }
+    }
+}
+
+/// Switches between editing and test playing the level
+fn editor_mode_system(
+    mut commands: Commands,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+    keyboard_input: Res<Input<KeyCode>>,
+    console: Res<ConsoleState>,
+    mut game_state: ResMut<GameState>,
+    mut editor: ResMut<Editor>,
+    mut level_progress: ResMut<LevelProgress>,
+    mut ball_query: Query<(&mut Ball, &mut Transform)>,
+    mut brick_query: Query<(Entity, &Brick)>,
+    mut hazard_query: Query<With<Hazard, Entity>>,
+) {
+    //Return in the console runs a command rather than switching modes
+    if console.open || !keyboard_input.just_pressed(KeyCode::Return) {
+        return;
+    }
+
+    //whichever way we're going the bricks on screen get replaced. Hazards aren't edited, so they're only there while test playing
+    for (entity, _brick) in &mut brick_query.iter() {
+        commands.despawn(entity);
+    }
+    for entity in &mut hazard_query.iter() {
+        commands.despawn(entity);
+    }
+    for (mut ball, mut transform) in &mut ball_query.iter() {
+        reset_ball(&mut ball, &mut transform);
+    }
+
+    match *game_state {
+        GameState::Editing => {
+            editor.selected = None;
+            editor.drag = None;
+            level_progress.source = LevelSource::Custom(editor.document.clone());
+            spawn_level(
+                &mut commands,
+                &mut materials,
+                &level_progress.current_layout(),
+            );
+            *game_state = GameState::Playing;
+        }
+        _ => {
+            //bring back whatever got broken while test playing
+            editor.respawn = true;
+            *game_state = GameState::Editing;
+        }
+    }
+}
+
+fn editor_mouse_system(
+    mouse_input: Res<Input<MouseButton>>,
+    game_state: Res<GameState>,
+    mut editor: ResMut<Editor>,
+) {
+    if *game_state != GameState::Editing {
+        return;
+    }
+
+    if mouse_input.just_pressed(MouseButton::Left) {
+        editor.press();
+    } else if mouse_input.pressed(MouseButton::Left) {
+        editor.drag_to_cursor();
+    } else if mouse_input.just_released(MouseButton::Left) {
+        editor.release();
+    }
+
+    if mouse_input.just_pressed(MouseButton::Right) {
+        let cursor = editor.cursor;
+        if let Some(index) = editor.brick_at(cursor) {
+            editor.delete(index);
+        }
+    }
+}
+
+fn editor_keyboard_system(
+    keyboard_input: Res<Input<KeyCode>>,
+    console: Res<ConsoleState>,
+    game_state: Res<GameState>,
+    accessibility: Res<Accessibility>,
+    mut editor: ResMut<Editor>,
+) {
+    if *game_state != GameState::Editing || console.open {
+        return;
+    }
+
+    let control =
+        keyboard_input.pressed(KeyCode::LControl) || keyboard_input.pressed(KeyCode::RControl);
+    let shift = keyboard_input.pressed(KeyCode::LShift) || keyboard_input.pressed(KeyCode::RShift);
+
+    if control {
+        if keyboard_input.just_pressed(KeyCode::Z) {
+            if shift {
+                editor.redo();
+            } else {
+                editor.undo();
+            }
+        }
+        if keyboard_input.just_pressed(KeyCode::Y) {
+            editor.redo();
+        }
+        if keyboard_input.just_pressed(KeyCode::S) {
+            editor.save();
+        }
+        if keyboard_input.just_pressed(KeyCode::L) {
+            editor.load();
+        }
+        return;
+    }
+
+    if keyboard_input.just_pressed(KeyCode::Delete) || keyboard_input.just_pressed(KeyCode::Back) {
+        if let Some(index) = editor.selected {
+            editor.delete(index);
+        }
+    }
+
+    for (key, brick_type) in [
+        (KeyCode::Key1, BrickType::Normal),
+        (KeyCode::Key2, BrickType::Tough),
+        (KeyCode::Key3, BrickType::Unbreakable),
+    ]
+    .iter()
+    {
+        if keyboard_input.just_pressed(*key) {
+            editor.brick_type = *brick_type;
+            editor.modify_selected(|brick| brick.brick_type = *brick_type);
+        }
+    }
+
+    if keyboard_input.just_pressed(KeyCode::C) {
+        editor.cycle_color(&accessibility.palette().brick_colors);
+    }
+
+    let mut resize = Vec2::zero();
+    if keyboard_input.just_pressed(KeyCode::Left) {
+        *resize.x_mut() -= GRID_SIZE;
+    }
+    if keyboard_input.just_pressed(KeyCode::Right) {
+        *resize.x_mut() += GRID_SIZE;
+    }
+    if keyboard_input.just_pressed(KeyCode::Down) {
+        *resize.y_mut() -= GRID_SIZE;
+    }
+    if keyboard_input.just_pressed(KeyCode::Up) {
+        *resize.y_mut() += GRID_SIZE;
+    }
+    if resize != Vec2::zero() {
+        editor.modify_selected(|brick| {
+            let max_size = Vec2::new(BOUNDS.0 * 2.0, BOUNDS.1 * 2.0) - Vec2::splat(WALL_THICKNESS);
+            brick.size = (brick.size + resize)
+                .max(Vec2::splat(MIN_BRICK_SIZE))
+                .min(max_size);
+            brick.position = clamp_to_arena(brick.position, brick.size);
+        });
+    }
+}
+
+/// Keeps the brick entities in line with the level being edited
+fn editor_sync_system(
+    mut commands: Commands,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+    game_state: Res<GameState>,
+    mut editor: ResMut<Editor>,
+    mut brick_query: Query<(
+        Entity,
+        &EditorBrick,
+        &mut Brick,
+        &mut Transform,
+        &mut Sprite,
+    )>,
+    mut highlight_query: Query<(&SelectionHighlight, &mut Transform, &mut Sprite)>,
+) {
+    if *game_state != GameState::Editing {
+        return;
+    }
+
+    if editor.respawn {
+        for (entity, ..) in &mut brick_query.iter() {
+            commands.despawn(entity);
+        }
+        for (index, brick) in editor.document.bricks.iter().enumerate() {
+            spawn_brick(&mut commands, &mut materials, brick);
+            commands.with(EditorBrick { index });
+        }
+        editor.respawn = false;
+    } else {
+        for (_entity, editor_brick, mut brick_component, mut transform, mut sprite) in
+            &mut brick_query.iter()
+        {
+            if let Some(brick) = editor.document.bricks.get(editor_brick.index) {
+                let translation = transform.translation_mut();
+                *translation.x_mut() = brick.position.x();
+                *translation.y_mut() = brick.position.y();
+                sprite.size = brick.size;
+                //the theme colors it (and its skin draws it) from these
+                if brick_component.brick_type != brick.brick_type {
+                    brick_component.brick_type = brick.brick_type;
+                    brick_component.hits_remaining = brick.brick_type.hits();
+                }
+                brick_component.color = brick.color;
+            }
+        }
+    }
+
+    let selected = editor
+        .selected
+        .and_then(|index| editor.document.bricks.get(index));
+    for (_highlight, mut transform, mut sprite) in &mut highlight_query.iter() {
+        match selected {
+            Some(brick) => {
+                let translation = transform.translation_mut();
+                *translation.x_mut() = brick.position.x();
+                *translation.y_mut() = brick.position.y();
+                sprite.size = brick.size + Vec2::splat(6.0);
+            }
+            None => sprite.size = Vec2::zero(),
+        }
+    }
+}
+
+fn editor_text_system(
+    localization: Res<Localization>,
+    game_state: Res<GameState>,
+    editor: Res<Editor>,
+    mut query: Query<(&EditorText, &mut Text)>,
+) {
+    for (_editor_text, mut text) in &mut query.iter() {
+        text.value = match *game_state {
+            GameState::Editing => localization.format(
+                "editor.editing",
+                &[
+                    ("path", editor.level_path.display().to_string()),
+                    (
+                        "bricks",
+                        localization.plural("bricks", editor.document.bricks.len() as u64),
+                    ),
+                    (
+                        "brick_type",
+                        localization.text(&format!("brick_type.{:?}", editor.brick_type)),
+                    ),
+                    (
+                        "status",
+                        editor
+                            .status
+                            .as_ref()
+                            .map_or("".to_string(), |status| localization.message(status)),
+                    ),
+                ],
+            ),
+            _ => localization.text("editor.test_playing"),
+        };
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::theme::Palette;
+    use super::*;
+
+    fn brick(x: f32, y: f32) -> BrickSpec {
+        BrickSpec {
+            position: Vec2::new(x, y),
+            size: Vec2::new(DEFAULT_BRICK_SIZE.0, DEFAULT_BRICK_SIZE.1),
+            brick_type: BrickType::Normal,
+            color: None,
+            path: None,
+        }
+    }
+
+    fn editor(bricks: Vec<BrickSpec>) -> Editor {
+        Editor::new(
+            PathBuf::from("editor_test.ron"),
+            LevelLayout {
+                bricks,
+                ..LevelLayout::default()
+            },
+        )
+    }
+
+    //applies the operation through the history, then checks undo takes the level back and redo brings the change again
+    fn assert_round_trip(start: LevelLayout, operation: EditOperation) {
+        let mut layout = start.clone();
+        let mut history = EditHistory::default();
+        operation.apply(&mut layout);
+        history.record(operation);
+        let changed = layout.clone();
+        assert_ne!(changed, start);
+
+        assert!(history.undo(&mut layout));
+        assert_eq!(layout, start);
+        assert!(history.redo(&mut layout));
+        assert_eq!(layout, changed);
+    }
+
+    #[test]
+    fn add_round_trips() {
+        let start = LevelLayout {
+            bricks: vec![brick(0.0, 0.0), brick(100.0, 0.0)],
+            ..LevelLayout::default()
+        };
+        assert_round_trip(
+            start,
+            EditOperation::Add {
+                index: 1,
+                brick: brick(0.0, 100.0),
+            },
+        );
+    }
+
+    #[test]
+    fn remove_round_trips() {
+        let start = LevelLayout {
+            bricks: vec![brick(0.0, 0.0), brick(100.0, 0.0), brick(200.0, 0.0)],
+            ..LevelLayout::default()
+        };
+        let removed = start.bricks[1].clone();
+        assert_round_trip(
+            start,
+            EditOperation::Remove {
+                index: 1,
+                brick: removed,
+            },
+        );
+    }
+
+    #[test]
+    fn move_round_trips() {
+        let start = LevelLayout {
+            bricks: vec![brick(0.0, 0.0)],
+            ..LevelLayout::default()
+        };
+        assert_round_trip(
+            start,
+            EditOperation::Modify {
+                index: 0,
+                before: brick(0.0, 0.0),
+                after: brick(50.0, -20.0),
+            },
+        );
+    }
+
+    #[test]
+    fn type_change_round_trips() {
+        let mut editor = editor(vec![brick(0.0, 0.0)]);
+        editor.selected = Some(0);
+        editor.modify_selected(|brick| brick.brick_type = BrickType::Tough);
+        assert_eq!(editor.document.bricks[0].brick_type, BrickType::Tough);
+
+        editor.undo();
+        assert_eq!(editor.document.bricks[0].brick_type, BrickType::Normal);
+        editor.redo();
+        assert_eq!(editor.document.bricks[0].brick_type, BrickType::Tough);
+    }
+
+    #[test]
+    fn colors_come_from_the_palette() {
+        let colors = Palette::standard().brick_colors;
+        let mut editor = editor(vec![brick(0.0, 0.0)]);
+        editor.selected = Some(0);
+        for &color in colors.iter() {
+            editor.cycle_color(&colors);
+            assert_eq!(editor.document.bricks[0].color, Some(color));
+        }
+        //and back to the type's own
+        editor.cycle_color(&colors);
+        assert_eq!(editor.document.bricks[0].color, None);
+    }
+
+    #[test]
+    fn new_edit_clears_redo() {
+        let mut layout = LevelLayout::default();
+        let mut history = EditHistory::default();
+        for operation in vec![
+            EditOperation::Add {
+                index: 0,
+                brick: brick(0.0, 0.0),
+            },
+            EditOperation::Add {
+                index: 1,
+                brick: brick(100.0, 0.0),
+            },
+        ] {
+            operation.apply(&mut layout);
+            history.record(operation);
+        }
+        assert!(history.undo(&mut layout));
+
+        let operation = EditOperation::Remove {
+            index: 0,
+            brick: brick(0.0, 0.0),
+        };
+        operation.apply(&mut layout);
+        history.record(operation);
+        assert!(!history.redo(&mut layout));
+        assert!(layout.bricks.is_empty());
+    }
+
+    #[test]
+    fn empty_history_does_nothing() {
+        let mut layout = LevelLayout {
+            bricks: vec![brick(0.0, 0.0)],
+            ..LevelLayout::default()
+        };
+        let mut history = EditHistory::default();
+        assert!(!history.undo(&mut layout));
+        assert!(!history.redo(&mut layout));
+        assert_eq!(layout.bricks, vec![brick(0.0, 0.0)]);
+    }
+
+    #[test]
+    fn undo_mid_drag_takes_back_the_drag() {
+        let mut editor = editor(vec![brick(0.0, 0.0)]);
+        editor.press();
+        editor.cursor = Vec2::new(100.0, 50.0);
+        editor.drag_to_cursor();
+        let dragged = editor.document.bricks[0].position;
+        assert_ne!(dragged, Vec2::zero());
+
+        editor.undo();
+        assert!(editor.drag.is_none());
+        assert_eq!(editor.document.bricks[0].position, Vec2::zero());
+        editor.redo();
+        assert_eq!(editor.document.bricks[0].position, dragged);
+    }
+}
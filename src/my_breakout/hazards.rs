@@ -0,0 +1,493 @@
+//Things a level can put in the arena besides bricks: gravity wells bending the ball's path towards them, pairs of portals,
+//zones the ball speeds up or slows down in, and barriers it can only get through going one way. None of them are colliders,
+//the ball goes into (or through) all of them, and each kind has a system of its own working on the ball's velocity and
+//where it is. They work the same whichever collision code is in use, as both pick the ball up from where these leave it.
+//
+//A well only bends the ball's path and a speed zone only moves it along quicker, so the ball always comes out going as fast
+//as it went in. Its speed is how hard the game's getting, which is up to the bricks.
+//A portal only sends the ball somewhere it fits: it's pushed out of any wall or paddle at the other end, and a brick there
+//blocks the portal altogether rather than having the ball break it from the inside
+
+use bevy::prelude::*;
+use std::{collections::HashSet, hash::Hash};
+
+use super::broadphase::{BrickGrid, UniformGrid};
+use super::level::{Brick, HazardSpec};
+use super::theme::{Accessibility, ColorRole};
+use super::{within_bounds, Ball, Collider, GameState};
+use crate::geometry::{circle_aabb, Aabb};
+use crate::vec3_extension::Vec3Ext;
+
+//over the bricks so they can't hide them, but under the ball
+const HAZARD_Z: f32 = 0.2;
+
+pub struct HazardsPlugin;
+
+impl Plugin for HazardsPlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        app.add_system(gravity_well_system.system())
+            .add_system(portal_system.system())
+            .add_system(speed_zone_system.system())
+            .add_system(one_way_barrier_system.system());
+    }
+}
+
+/// Marks anything spawned from a level's hazards, so it gets cleared away with the level's bricks
+pub struct Hazard;
+
+/// Component pulling the ball towards the entity while it's within `radius`
+pub struct GravityWell {
+    pub radius: f32,
+    pub strength: f32,
+}
+
+/// Component on one of a pair of portals, which sends the ball to the other one
+pub struct Portal {
+    pub exit: Vec2,
+    pub radius: f32,
+}
+
+/// Component on an area (the size of its sprite) the ball moves `factor` times as fast in
+pub struct SpeedZone {
+    pub factor: f32,
+}
+
+/// Component on a barrier (the size of its sprite) the ball can only pass through heading `direction`'s way
+pub struct OneWayBarrier {
+    pub direction: Vec2,
+}
+
+fn spawn_area(
+    commands: &mut Commands,
+    materials: &mut Assets<ColorMaterial>,
+    position: Vec2,
+    size: Vec2,
+    role: ColorRole,
+) {
+    commands
+        .spawn(SpriteComponents {
+            //the theme sets the actual color
+            material: materials.add(Color::rgb(1.0, 1.0, 1.0).into()),
+            sprite: Sprite::new(size),
+            transform: Transform::from_translation(position.extend(HAZARD_Z)),
+            ..Default::default()
+        })
+        .with(role)
+        .with(Hazard);
+}
+
+pub fn spawn_hazard(
+    commands: &mut Commands,
+    materials: &mut Assets<ColorMaterial>,
+    hazard: &HazardSpec,
+) {
+    let vec2 = |(x, y): (f32, f32)| Vec2::new(x, y);
+    match hazard {
+        HazardSpec::GravityWell {
+            position,
+            radius,
+            strength,
+        } => {
+            spawn_area(
+                commands,
+                materials,
+                vec2(*position),
+                Vec2::splat(radius * 2.0),
+                ColorRole::Field,
+            );
+            commands.with(GravityWell {
+                radius: *radius,
+                strength: *strength,
+            });
+        }
+        HazardSpec::Portals {
+            entrance,
+            exit,
+            radius,
+        } => {
+            for (from, to) in [(entrance, exit), (exit, entrance)].iter() {
+                spawn_area(
+                    commands,
+                    materials,
+                    vec2(**from),
+                    Vec2::splat(radius * 2.0),
+                    ColorRole::Portal,
+                );
+                commands.with(Portal {
+                    exit: vec2(**to),
+                    radius: *radius,
+                });
+            }
+        }
+        HazardSpec::SpeedZone {
+            position,
+            size,
+            factor,
+        } => {
+            spawn_area(
+                commands,
+                materials,
+                vec2(*position),
+                vec2(*size),
+                ColorRole::Field,
+            );
+            commands.with(SpeedZone { factor: *factor });
+        }
+        HazardSpec::OneWayBarrier {
+            position,
+            size,
+            direction,
+        } => {
+            spawn_area(
+                commands,
+                materials,
+                vec2(*position),
+                vec2(*size),
+                ColorRole::Wall,
+            );
+            let direction = vec2(*direction);
+            commands.with(OneWayBarrier {
+                direction: if direction.length_squared() > 0.0 {
+                    direction.normalize()
+                } else {
+                    direction
+                },
+            });
+        }
+    }
+}
+
+//the same clamped and slowed down timestep the ball moves by
+fn game_delta_seconds(time: &Time, accessibility: &Accessibility) -> f32 {
+    f32::min(0.2, time.delta_seconds) * accessibility.game_speed
+}
+
+/// Turns each ball towards any well it's in, keeping its speed
+fn gravity_well_system(
+    time: Res<Time>,
+    game_state: Res<GameState>,
+    accessibility: Res<Accessibility>,
+    mut well_query: Query<(&GravityWell, &Transform)>,
+    mut ball_query: Query<(&mut Ball, &Transform)>,
+) {
+    if !game_state.is_simulating() {
+        return;
+    }
+    let delta_seconds = game_delta_seconds(&time, &accessibility);
+
+    let mut wells = Vec::new();
+    for (well, transform) in &mut well_query.iter() {
+        wells.push((
+            transform.translation().truncate(),
+            well.radius,
+            well.strength,
+        ));
+    }
+
+    for (mut ball, transform) in &mut ball_query.iter() {
+        let position = transform.translation().truncate();
+        for (center, radius, strength) in wells.iter() {
+            let pull = well_pull(position - *center, *radius, *strength);
+            let speed = ball.velocity.length();
+            let bent = ball.velocity + pull.extend(0.0) * delta_seconds;
+            if bent.length_squared() > 0.0 {
+                ball.velocity = bent.normalize() * speed;
+            }
+        }
+    }
+}
+
+/// How hard a well pulls on a ball `offset` from its center: at full strength in the middle, fading away to nothing at the edge
+fn well_pull(offset: Vec2, radius: f32, strength: f32) -> Vec2 {
+    let distance = offset.length();
+    if distance >= radius || distance <= 0.0 {
+        return Vec2::zero();
+    }
+    -offset / distance * strength * (1.0 - distance / radius)
+}
+
+/// Moves any ball that's just gone into a portal to the other one, leaving its velocity be.
+/// It then has to leave that one before it can go back through, otherwise it'd be sent straight back
+fn portal_system(
+    game_state: Res<GameState>,
+    brick_grid: Res<BrickGrid>,
+    //balls in a portal they've already been through (or come out of)
+    mut inside: Local<HashSet<Entity>>,
+    mut portal_query: Query<(&Portal, &Transform)>,
+    mut solid_query: Query<Without<Brick, (&Collider, &Transform, &Sprite)>>,
+    mut ball_query: Query<With<Ball, (Entity, &mut Transform, &Sprite)>>,
+) {
+    if !game_state.is_simulating() {
+        return;
+    }
+
+    //the walls and paddles
+    let mut solids = Vec::new();
+    for (_collider, transform, sprite) in &mut solid_query.iter() {
+        solids.push(Aabb::from_sprite(&transform, &sprite));
+    }
+
+    let mut portals = Vec::new();
+    for (portal, transform) in &mut portal_query.iter() {
+        portals.push((
+            transform.translation().truncate(),
+            portal.exit,
+            portal.radius,
+        ));
+    }
+
+    let mut balls_seen = Vec::new();
+    for (entity, mut transform, sprite) in &mut ball_query.iter() {
+        balls_seen.push(entity);
+        let position = transform.translation().truncate();
+        let entered = portals
+            .iter()
+            .find(|(center, _exit, radius)| (position - *center).length() < *radius);
+        match entered {
+            Some((_center, exit, _radius)) => {
+                if !inside.insert(entity) {
+                    continue;
+                }
+                //a blocked portal lets the ball carry on through, it's still inside it as far as going back through goes
+                if let Some(exit) = portal_exit(*exit, sprite.size, &brick_grid, &solids) {
+                    let translation = transform.translation_mut();
+                    *translation.x_mut() = exit.x();
+                    *translation.y_mut() = exit.y();
+                }
+            }
+            None => {
+                inside.remove(&entity);
+            }
+        }
+    }
+    inside.retain(|entity| balls_seen.contains(entity));
+}
+
+/// Where a ball of the given size comes out of a portal at `exit`, after being pushed out of any of the `solids` it'd
+/// overlap there. None if there's a brick in the way
+fn portal_exit<T>(
+    exit: Vec2,
+    ball_size: Vec2,
+    bricks: &UniformGrid<T>,
+    solids: &[Aabb],
+) -> Option<Vec2>
+where
+    T: Copy + Eq + Hash,
+{
+    let mut ball = Aabb::from_center_size(within_bounds(exit, ball_size), ball_size);
+    for solid in solids {
+        if let Some(push) = ball.penetration(solid) {
+            ball = Aabb::new(ball.min + push, ball.max + push);
+        }
+    }
+
+    let blocked = bricks.query(&ball).into_iter().any(|brick| {
+        bricks
+            .bounds(brick)
+            .map_or(false, |bounds| bounds.overlaps(&ball))
+    });
+    if blocked {
+        None
+    } else {
+        Some(ball.center())
+    }
+}
+
+/// Moves each ball in a speed zone on by however much further (or less far) than its velocity already took it the zone
+/// has it go
+fn speed_zone_system(
+    time: Res<Time>,
+    game_state: Res<GameState>,
+    accessibility: Res<Accessibility>,
+    mut zone_query: Query<(&SpeedZone, &Transform, &Sprite)>,
+    mut ball_query: Query<(&Ball, &mut Transform, &Sprite)>,
+) {
+    if !game_state.is_simulating() {
+        return;
+    }
+    let delta_seconds = game_delta_seconds(&time, &accessibility);
+
+    let mut zones = Vec::new();
+    for (zone, transform, sprite) in &mut zone_query.iter() {
+        zones.push((Aabb::from_sprite(&transform, &sprite), zone.factor));
+    }
+
+    for (ball, mut transform, sprite) in &mut ball_query.iter() {
+        let position = transform.translation().truncate();
+        //zones don't stack, the ball goes by whichever one it's in
+        if let Some((_bounds, factor)) = zones
+            .iter()
+            .find(|(bounds, _factor)| bounds.contains(position))
+        {
+            let moved = zone_move(
+                position,
+                sprite.size,
+                ball.velocity.truncate(),
+                *factor,
+                delta_seconds,
+            );
+            let translation = transform.translation_mut();
+            *translation.x_mut() = moved.x();
+            *translation.y_mut() = moved.y();
+        }
+    }
+}
+
+/// Where a zone moves a ball on to, on top of where its velocity has already taken it, without it going past the walls
+fn zone_move(
+    position: Vec2,
+    ball_size: Vec2,
+    velocity: Vec2,
+    factor: f32,
+    delta_seconds: f32,
+) -> Vec2 {
+    within_bounds(
+        position + velocity * (factor - 1.0) * delta_seconds,
+        ball_size,
+    )
+}
+
+/// Turns back any ball touching a barrier while heading against the way it can be passed through. It goes back out the
+/// way it came, so there's no need to push it out like a collider
+fn one_way_barrier_system(
+    game_state: Res<GameState>,
+    mut barrier_query: Query<(&OneWayBarrier, &Transform, &Sprite)>,
+    mut ball_query: Query<(&mut Ball, &Transform, &Sprite)>,
+) {
+    if !game_state.is_simulating() {
+        return;
+    }
+
+    let mut barriers = Vec::new();
+    for (barrier, transform, sprite) in &mut barrier_query.iter() {
+        barriers.push((Aabb::from_sprite(&transform, &sprite), barrier.direction));
+    }
+
+    for (mut ball, transform, sprite) in &mut ball_query.iter() {
+        let center = transform.translation().truncate();
+        let radius = sprite.size.x() / 2.0;
+        for (bounds, direction) in barriers.iter() {
+            if turned_back(center, radius, ball.velocity, bounds, *direction) {
+                ball.velocity = ball.velocity.reflect(direction.extend(0.0));
+            }
+        }
+    }
+}
+
+/// Whether a ball touching the barrier is heading against the way it can be passed through
+fn turned_back(center: Vec2, radius: f32, velocity: Vec3, bounds: &Aabb, direction: Vec2) -> bool {
+    circle_aabb(center, radius, bounds).is_some() && velocity.truncate().dot(direction) < 0.0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::my_breakout::BOUNDS;
+
+    #[test]
+    fn wells_pull_towards_their_center_fading_out_to_the_edge() {
+        let pull = well_pull(Vec2::new(50.0, 0.0), 100.0, 200.0);
+        assert_eq!(pull, Vec2::new(-100.0, 0.0));
+        let weaker = well_pull(Vec2::new(0.0, -75.0), 100.0, 200.0);
+        assert_eq!(weaker, Vec2::new(0.0, 50.0));
+
+        assert_eq!(well_pull(Vec2::new(100.0, 0.0), 100.0, 200.0), Vec2::zero());
+        //nowhere to pull a ball already in the middle
+        assert_eq!(well_pull(Vec2::zero(), 100.0, 200.0), Vec2::zero());
+    }
+
+    #[test]
+    fn barriers_only_turn_back_balls_heading_the_wrong_way() {
+        let barrier = Aabb::from_center_size(Vec2::zero(), Vec2::new(200.0, 10.0));
+        let up = Vec2::new(0.0, 1.0);
+        //passing through upwards is fine
+        assert!(!turned_back(
+            Vec2::new(0.0, -15.0),
+            15.0,
+            Vec3::new(0.0, 400.0, 0.0),
+            &barrier,
+            up
+        ));
+        //coming back down isn't, even at an angle
+        assert!(turned_back(
+            Vec2::new(0.0, 15.0),
+            15.0,
+            Vec3::new(300.0, -10.0, 0.0),
+            &barrier,
+            up
+        ));
+        //running along it doesn't count either way
+        assert!(!turned_back(
+            Vec2::new(0.0, 15.0),
+            15.0,
+            Vec3::new(400.0, 0.0, 0.0),
+            &barrier,
+            up
+        ));
+        //and it has to actually be touching
+        assert!(!turned_back(
+            Vec2::new(0.0, 50.0),
+            15.0,
+            Vec3::new(0.0, -400.0, 0.0),
+            &barrier,
+            up
+        ));
+    }
+
+    #[test]
+    fn zones_move_the_ball_on_but_not_past_the_walls() {
+        let ball_size = Vec2::splat(30.0);
+        let velocity = Vec2::new(400.0, 0.0);
+        //twice as fast means as far again as its velocity took it
+        assert_eq!(
+            zone_move(Vec2::zero(), ball_size, velocity, 2.0, 0.5),
+            Vec2::new(200.0, 0.0)
+        );
+        //and half as fast takes half of that back
+        assert_eq!(
+            zone_move(Vec2::zero(), ball_size, velocity, 0.5, 0.5),
+            Vec2::new(-100.0, 0.0)
+        );
+
+        let near_wall = Vec2::new(BOUNDS.0 - 20.0, 0.0);
+        assert_eq!(
+            zone_move(near_wall, ball_size, velocity, 3.0, 0.5),
+            Vec2::new(BOUNDS.0 - 15.0, 0.0)
+        );
+    }
+
+    #[test]
+    fn portals_push_the_ball_out_of_walls_at_the_exit() {
+        let ball_size = Vec2::splat(30.0);
+        let wall = Aabb::from_center_size(Vec2::new(100.0, 0.0), Vec2::new(10.0, 200.0));
+        let bricks = UniformGrid::<u32>::default();
+        assert_eq!(
+            portal_exit(Vec2::new(0.0, 0.0), ball_size, &bricks, &[wall]),
+            Some(Vec2::zero())
+        );
+        //overlapping the wall's left side by 10, so it's moved back left
+        assert_eq!(
+            portal_exit(Vec2::new(90.0, 0.0), ball_size, &bricks, &[wall]),
+            Some(Vec2::new(80.0, 0.0))
+        );
+    }
+
+    #[test]
+    fn bricks_block_a_portal() {
+        let ball_size = Vec2::splat(30.0);
+        let mut bricks = UniformGrid::default();
+        bricks.insert(
+            1,
+            Aabb::from_center_size(Vec2::new(0.0, 100.0), Vec2::new(60.0, 20.0)),
+        );
+        assert_eq!(
+            portal_exit(Vec2::new(10.0, 80.0), ball_size, &bricks, &[]),
+            None
+        );
+        //just touching it is fine
+        assert_eq!(
+            portal_exit(Vec2::new(0.0, 75.0), ball_size, &bricks, &[]),
+            Some(Vec2::new(0.0, 75.0))
+        );
+    }
+}
@@ -0,0 +1,88 @@
+//Everything the game needs to run without a window, a GPU or a sound card, for letting the computer play by itself from the
+//command line or in a test. It's the default plugins minus winit, wgpu and audio, with a stand in for the GPU so the render
+//systems still have something to hand their buffers and textures to. Plugins that need a window or sound look for the
+//Headless resource and leave those parts out.
+//
+//The clock doesn't follow the wall clock either. Every update moves it on by the same fixed step, so a game plays out the
+//same however fast the machine is, and as quickly as it can be simulated
+
+use bevy::{
+    app::stage,
+    asset::AssetPlugin,
+    core::CorePlugin,
+    diagnostic::DiagnosticsPlugin,
+    input::InputPlugin,
+    prelude::*,
+    render::{
+        renderer::{HeadlessRenderResourceContext, RenderResourceContext},
+        RenderPlugin,
+    },
+    sprite::SpritePlugin,
+    text::TextPlugin,
+    transform::TransformPlugin,
+    type_registry::TypeRegistryPlugin,
+    ui::UiPlugin,
+    window::WindowPlugin,
+};
+use std::time::Duration;
+
+const FIXED_CLOCK_STAGE: &str = "fixed_clock";
+
+/// The plugins to build a headless app on, in place of the default ones
+pub struct HeadlessPlugin {
+    /// How many seconds pass with each update
+    pub step_seconds: f32,
+}
+
+impl Default for HeadlessPlugin {
+    fn default() -> Self {
+        HeadlessPlugin {
+            step_seconds: 1.0 / 60.0,
+        }
+    }
+}
+
+/// Resource marking an app as running headless
+pub struct Headless {
+    step_seconds: f32,
+}
+
+impl Plugin for HeadlessPlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        app.add_resource(Headless {
+            step_seconds: self.step_seconds,
+        })
+        //the core plugin registers its types, so it needs the registry in place first
+        .add_plugin(TypeRegistryPlugin::default())
+        .add_plugin(CorePlugin)
+        .add_plugin(TransformPlugin)
+        .add_plugin(DiagnosticsPlugin)
+        .add_plugin(InputPlugin)
+        //there's no winit to open it, so the primary window never turns up and everything waiting on one just waits
+        .add_plugin(WindowPlugin::default())
+        .add_plugin(AssetPlugin)
+        .add_plugin(RenderPlugin::default())
+        .add_plugin(SpritePlugin)
+        .add_plugin(UiPlugin)
+        .add_plugin(TextPlugin)
+        .add_resource::<Box<dyn RenderResourceContext>>(Box::new(
+            HeadlessRenderResourceContext::default(),
+        ))
+        //straight after the core plugin's time system has set the time from the wall clock
+        .add_stage_after(stage::FIRST, FIXED_CLOCK_STAGE)
+        .add_system_to_stage(FIXED_CLOCK_STAGE, fixed_clock_system.system());
+    }
+}
+
+fn fixed_clock_system(
+    headless: Res<Headless>,
+    mut time: ResMut<Time>,
+    mut seconds_since_startup: Local<f64>,
+) {
+    let step = headless.step_seconds;
+    *seconds_since_startup += step as f64;
+    time.delta = Duration::from_secs_f32(step);
+    time.delta_seconds = step;
+    time.delta_seconds_f64 = step as f64;
+    time.seconds_since_startup = *seconds_since_startup;
+}
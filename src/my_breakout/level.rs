@@ -0,0 +1,472 @@
+//Levels are described as plain data (a list of bricks) so that where a level comes from (the classic grid, the generator, a file)
+//is kept separate from how it gets turned into entities
+
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::{fs, io, path::Path};
+
+use super::hazards::spawn_hazard;
+use super::level_generator::{level_difficulty, level_seed, LevelGenerator};
+use super::players::player_color;
+use super::Collider;
+
+/// The kinds of bricks a level can be built from
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum BrickType {
+    /// Breaks on the first hit
+    Normal,
+    /// Takes a few hits to break but is worth more points
+    Tough,
+    /// Can never be broken, the ball just bounces off of it
+    Unbreakable,
+}
+
+impl BrickType {
+    /// How many hits it takes to break this kind of brick (0 meaning it can't be broken)
+    pub fn hits(self) -> u32 {
+        match self {
+            BrickType::Normal => 1,
+            BrickType::Tough => 3,
+            BrickType::Unbreakable => 0,
+        }
+    }
+
+    /// Points awarded when this kind of brick is broken
+    pub fn points(self) -> usize {
+        match self {
+            BrickType::Normal => 1,
+            BrickType::Tough => 3,
+            BrickType::Unbreakable => 0,
+        }
+    }
+
+    pub fn is_breakable(self) -> bool {
+        self.hits() > 0
+    }
+}
+
+/// Everything needed to spawn a single brick
+#[derive(Clone, Debug, PartialEq)]
+pub struct BrickSpec {
+    pub position: Vec2,
+    pub size: Vec2,
+    pub brick_type: BrickType,
+    /// Overrides the palette's color for the brick type when set
+    pub color: Option<Color>,
+    /// How the brick moves about its position while the game's being played, if it moves at all
+    pub path: Option<BrickPath>,
+}
+
+/// A path a brick can follow, relative to where it's placed. Each takes it back to where it started, so it goes round forever
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub enum BrickPath {
+    /// Slides side to side, up to `distance` either way, taking `period` seconds to go there and back
+    Oscillate { distance: f32, period: f32 },
+    /// Circles `radius` away from where it's placed, once every `period` seconds
+    Orbit { radius: f32, period: f32 },
+    /// Heads to each point in turn at `speed` units a second, then back to where it started
+    Waypoints { points: Vec<(f32, f32)>, speed: f32 },
+}
+
+impl BrickPath {
+    /// How far from where it was placed a brick is after following the path for the given number of seconds
+    pub fn offset(&self, time: f32) -> Vec2 {
+        match self {
+            BrickPath::Oscillate { distance, period } => {
+                Vec2::new(distance * angle(time, *period).sin(), 0.0)
+            }
+            BrickPath::Orbit { radius, period } => {
+                let angle = angle(time, *period);
+                Vec2::new(angle.cos(), angle.sin()) * *radius
+            }
+            BrickPath::Waypoints { points, speed } => {
+                let stops: Vec<Vec2> = std::iter::once(Vec2::zero())
+                    .chain(points.iter().map(|(x, y)| Vec2::new(*x, *y)))
+                    .collect();
+                //each stop to the next, with the last one leading back to the start
+                let legs: Vec<(Vec2, Vec2)> = stops
+                    .iter()
+                    .zip(stops.iter().cycle().skip(1))
+                    .map(|(from, to)| (*from, *to))
+                    .collect();
+                let length: f32 = legs.iter().map(|(from, to)| (*to - *from).length()).sum();
+                if length <= 0.0 || *speed <= 0.0 {
+                    return Vec2::zero();
+                }
+
+                let mut distance = (time * speed) % length;
+                for (from, to) in legs {
+                    let leg = (to - from).length();
+                    if distance <= leg && leg > 0.0 {
+                        return from + (to - from) * (distance / leg);
+                    }
+                    distance -= leg;
+                }
+                Vec2::zero()
+            }
+        }
+    }
+}
+
+//How far round a repeating path something is after the given time, in radians
+fn angle(time: f32, period: f32) -> f32 {
+    if period > 0.0 {
+        time / period * 2.0 * std::f32::consts::PI
+    } else {
+        0.0
+    }
+}
+
+/// Something in the arena besides the bricks, which the ball isn't stopped by but gets pushed about by.
+/// Positions and sizes are in the same units as the bricks'
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub enum HazardSpec {
+    /// Pulls the ball towards it while it's within `radius`, harder the nearer it gets
+    GravityWell {
+        position: (f32, f32),
+        radius: f32,
+        strength: f32,
+    },
+    /// A pair of portals, each sending the ball out of the other one going just as it went in
+    Portals {
+        entrance: (f32, f32),
+        exit: (f32, f32),
+        radius: f32,
+    },
+    /// Speeds the ball up while it's inside, or slows it down with a `factor` under 1
+    SpeedZone {
+        position: (f32, f32),
+        size: (f32, f32),
+        factor: f32,
+    },
+    /// Lets the ball through going `direction`'s way, and bounces it back going the other
+    OneWayBarrier {
+        position: (f32, f32),
+        size: (f32, f32),
+        direction: (f32, f32),
+    },
+}
+
+/// A full set of bricks (and any hazards) making up a level
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct LevelLayout {
+    pub bricks: Vec<BrickSpec>,
+    pub hazards: Vec<HazardSpec>,
+}
+
+//The on disk form of a level. Kept separate from LevelLayout so the file format doesn't depend on how bevy's math types serialize
+#[derive(Serialize, Deserialize)]
+struct LevelFile {
+    bricks: Vec<BrickRecord>,
+    #[serde(default)]
+    hazards: Vec<HazardSpec>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct BrickRecord {
+    x: f32,
+    y: f32,
+    width: f32,
+    height: f32,
+    brick_type: BrickType,
+    #[serde(default)]
+    color: Option<[f32; 3]>,
+    #[serde(default)]
+    path: Option<BrickPath>,
+}
+
+impl LevelLayout {
+    /// Reads a level saved with `save`
+    pub fn load(path: &Path) -> io::Result<LevelLayout> {
+        let contents = fs::read_to_string(path)?;
+        let file: LevelFile = ron::de::from_str(&contents)
+            .map_err(|error| io::Error::new(io::ErrorKind::InvalidData, error.to_string()))?;
+
+        let bricks = file
+            .bricks
+            .into_iter()
+            .map(|record| BrickSpec {
+                position: Vec2::new(record.x, record.y),
+                size: Vec2::new(record.width, record.height),
+                brick_type: record.brick_type,
+                color: record.color.map(|[r, g, b]| Color::rgb(r, g, b)),
+                path: record.path,
+            })
+            .collect();
+        Ok(LevelLayout {
+            bricks,
+            hazards: file.hazards,
+        })
+    }
+
+    /// Writes the level out as a (human readable) ron file, creating its directory if needed
+    pub fn save(&self, path: &Path) -> io::Result<()> {
+        let file = LevelFile {
+            bricks: self
+                .bricks
+                .iter()
+                .map(|brick| BrickRecord {
+                    x: brick.position.x(),
+                    y: brick.position.y(),
+                    width: brick.size.x(),
+                    height: brick.size.y(),
+                    brick_type: brick.brick_type,
+                    color: brick.color.map(|color| [color.r, color.g, color.b]),
+                    path: brick.path.clone(),
+                })
+                .collect(),
+            hazards: self.hazards.clone(),
+        };
+        let contents = ron::ser::to_string_pretty(&file, ron::ser::PrettyConfig::default())
+            .map_err(|error| io::Error::new(io::ErrorKind::InvalidData, error.to_string()))?;
+
+        if let Some(directory) = path.parent() {
+            fs::create_dir_all(directory)?;
+        }
+        fs::write(path, contents)
+    }
+}
+
+/// Component tracking what kind of brick an entity is and how many more hits it can take
+pub struct Brick {
+    pub brick_type: BrickType,
+    pub hits_remaining: u32,
+    /// Overrides the palette's color for the brick type when set
+    pub color: Option<Color>,
+}
+
+/// Component on a brick following a path, which moves it about where it was placed
+pub struct BrickMovement {
+    pub path: BrickPath,
+    pub origin: Vec2,
+    /// How long it's been following the path, in game time so it slows down along with the ball
+    pub elapsed: f32,
+    /// How fast it moved over the last frame, in the same units as the ball's velocity
+    pub velocity: Vec2,
+}
+
+/// The original hard coded 4x5 grid of bricks
+pub fn classic_layout() -> LevelLayout {
+    let brick_rows = 4;
+    let brick_columns = 5;
+    let brick_spacing = 20.0;
+    let brick_size = Vec2::new(150.0, 30.0);
+    let bricks_width = brick_columns as f32 * (brick_size.x() + brick_spacing) - brick_spacing;
+    // center the bricks and move them up a bit
+    let bricks_offset = Vec2::new(-(bricks_width - brick_size.x()) / 2.0, 100.0);
+
+    let mut bricks = Vec::new();
+    for row in 0..brick_rows {
+        let y_position = row as f32 * (brick_size.y() + brick_spacing);
+        for column in 0..brick_columns {
+            let position = Vec2::new(column as f32 * (brick_size.x() + brick_spacing), y_position)
+                + bricks_offset;
+            bricks.push(BrickSpec {
+                position,
+                size: brick_size,
+                brick_type: BrickType::Normal,
+                color: None,
+                path: None,
+            });
+        }
+    }
+    LevelLayout {
+        bricks,
+        hazards: Vec::new(),
+    }
+}
+
+/// Two walls of bricks in the players' colors, each one guarding that player's half of the arena
+pub fn versus_layout() -> LevelLayout {
+    let brick_rows = 2;
+    let brick_columns = 8;
+    let brick_spacing = 10.0;
+    let brick_size = Vec2::new(95.0, 20.0);
+    let bricks_width = brick_columns as f32 * (brick_size.x() + brick_spacing) - brick_spacing;
+    let left = -(bricks_width - brick_size.x()) / 2.0;
+
+    let mut bricks = Vec::new();
+    for (player, side) in [(0, -1.0), (1, 1.0)].iter() {
+        for row in 0..brick_rows {
+            //the walls start a little way out from the center line so there's room to serve between them
+            let y_position = side * (80.0 + row as f32 * (brick_size.y() + brick_spacing));
+            for column in 0..brick_columns {
+                bricks.push(BrickSpec {
+                    position: Vec2::new(
+                        left + column as f32 * (brick_size.x() + brick_spacing),
+                        y_position,
+                    ),
+                    size: brick_size,
+                    brick_type: BrickType::Normal,
+                    color: Some(player_color(*player)),
+                    path: None,
+                });
+            }
+        }
+    }
+    LevelLayout {
+        bricks,
+        hazards: Vec::new(),
+    }
+}
+
+/// Spawns every brick and hazard of the given layout. Breakable bricks are `Collider::Scorable` while unbreakable ones are `Collider::Solid`
+pub fn spawn_level(
+    commands: &mut Commands,
+    materials: &mut Assets<ColorMaterial>,
+    layout: &LevelLayout,
+) {
+    for brick in layout.bricks.iter() {
+        spawn_brick(commands, materials, brick);
+    }
+    for hazard in layout.hazards.iter() {
+        spawn_hazard(commands, materials, hazard);
+    }
+}
+
+pub fn spawn_brick(
+    commands: &mut Commands,
+    materials: &mut Assets<ColorMaterial>,
+    brick: &BrickSpec,
+) {
+    let collider = if brick.brick_type.is_breakable() {
+        Collider::Scorable
+    } else {
+        Collider::Solid
+    };
+
+    commands
+        .spawn(SpriteComponents {
+            //the theme sets the actual color, depending on the palette
+            material: materials.add(brick.color.unwrap_or(Color::rgb(1.0, 1.0, 1.0)).into()),
+            sprite: Sprite::new(brick.size),
+            transform: Transform::from_translation(brick.position.extend(0.0)),
+            ..Default::default()
+        })
+        .with(collider)
+        .with(Brick {
+            brick_type: brick.brick_type,
+            hits_remaining: brick.brick_type.hits(),
+            color: brick.color,
+        });
+    if let Some(path) = &brick.path {
+        commands.with(BrickMovement {
+            path: path.clone(),
+            origin: brick.position,
+            elapsed: 0.0,
+            velocity: Vec2::zero(),
+        });
+    }
+}
+
+/// Where the levels being played come from
+#[derive(Clone, Debug, PartialEq)]
+pub enum LevelSource {
+    /// The original grid of bricks
+    Classic,
+    /// An endless chain of generated levels, each derived from the starting seed
+    Endless { seed: u64 },
+    /// The two walls of a two player game
+    Versus,
+    /// A single hand made level, e.g. one loaded from a file or being test played from the editor
+    Custom(LevelLayout),
+}
+
+/// Resource tracking which level is being played
+pub struct LevelProgress {
+    pub source: LevelSource,
+    /// Zero based index of the current level
+    pub level: u32,
+}
+
+impl LevelProgress {
+    pub fn new(source: LevelSource) -> Self {
+        LevelProgress { source, level: 0 }
+    }
+
+    /// The layout of the level currently being played
+    pub fn current_layout(&self) -> LevelLayout {
+        match &self.source {
+            LevelSource::Classic => classic_layout(),
+            LevelSource::Versus => versus_layout(),
+            LevelSource::Custom(layout) => layout.clone(),
+            LevelSource::Endless { seed } => {
+                LevelGenerator::new(level_seed(*seed, self.level), level_difficulty(self.level))
+                    .generate()
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_near(actual: Vec2, expected: Vec2) {
+        assert!(
+            (actual - expected).length() < 0.001,
+            "{:?} isn't {:?}",
+            actual,
+            expected
+        );
+    }
+
+    #[test]
+    fn oscillating_goes_side_to_side() {
+        let path = BrickPath::Oscillate {
+            distance: 50.0,
+            period: 4.0,
+        };
+        assert_near(path.offset(0.0), Vec2::zero());
+        assert_near(path.offset(1.0), Vec2::new(50.0, 0.0));
+        assert_near(path.offset(2.0), Vec2::zero());
+        assert_near(path.offset(3.0), Vec2::new(-50.0, 0.0));
+        assert_near(path.offset(4.0), Vec2::zero());
+    }
+
+    #[test]
+    fn orbiting_goes_round() {
+        let path = BrickPath::Orbit {
+            radius: 20.0,
+            period: 4.0,
+        };
+        assert_near(path.offset(0.0), Vec2::new(20.0, 0.0));
+        assert_near(path.offset(1.0), Vec2::new(0.0, 20.0));
+        assert_near(path.offset(2.0), Vec2::new(-20.0, 0.0));
+        for step in 0..20 {
+            assert!((path.offset(step as f32 * 0.3).length() - 20.0).abs() < 0.001);
+        }
+    }
+
+    #[test]
+    fn waypoints_are_visited_in_turn_then_back_to_the_start() {
+        let path = BrickPath::Waypoints {
+            points: vec![(100.0, 0.0), (100.0, 100.0)],
+            speed: 50.0,
+        };
+        assert_near(path.offset(0.0), Vec2::zero());
+        assert_near(path.offset(1.0), Vec2::new(50.0, 0.0));
+        assert_near(path.offset(2.0), Vec2::new(100.0, 0.0));
+        assert_near(path.offset(3.0), Vec2::new(100.0, 50.0));
+        //the diagonal back to the start is 141 long, so it's halfway back at 4 + 1.41 seconds
+        let back = 4.0 + 100.0 * 2f32.sqrt() / 2.0 / 50.0;
+        assert_near(path.offset(back), Vec2::new(50.0, 50.0));
+        //and round again once the whole loop's been done
+        let lap = (200.0 + 100.0 * 2f32.sqrt()) / 50.0;
+        assert_near(path.offset(lap + 1.0), Vec2::new(50.0, 0.0));
+    }
+
+    #[test]
+    fn paths_that_cant_move_stay_put() {
+        let still = BrickPath::Waypoints {
+            points: vec![],
+            speed: 50.0,
+        };
+        assert_near(still.offset(3.0), Vec2::zero());
+        let stopped = BrickPath::Orbit {
+            radius: 20.0,
+            period: 0.0,
+        };
+        assert_near(stopped.offset(3.0), Vec2::new(20.0, 0.0));
+    }
+}
@@ -0,0 +1,379 @@
+//Procedurally generated brick layouts
+//Every layout is derived from nothing but a seed and a difficulty, so sharing the seed is enough for someone else to get the exact same levels
+
+use bevy::prelude::*;
+
+use super::level::{BrickSpec, BrickType, LevelLayout};
+use super::{BALL_SIZE, BOUNDS, WALL_THICKNESS};
+
+//The area bricks are allowed to occupy. Between the outer bricks and the walls there's room for the ball plus a margin,
+//so it can always slip around the sides of the layout rather than having to squeeze through a gap exactly its own size
+const SIDE_MARGIN: f32 = 10.0;
+const LAYOUT_WIDTH: f32 = 2.0 * (BOUNDS.0 - WALL_THICKNESS / 2.0 - BALL_SIZE - SIDE_MARGIN);
+const LOWEST_ROW_Y: f32 = 40.0;
+const BRICK_HEIGHT: f32 = 20.0;
+const BRICK_SPACING: f32 = 8.0;
+
+/// A small seedable pseudo random number generator (SplitMix64)
+/// We use our own rather than pulling in a crate so that the same seed always produces the same layouts regardless of dependency updates
+pub struct SeededRng {
+    state: u64,
+}
+
+impl SeededRng {
+    pub fn new(seed: u64) -> Self {
+        SeededRng { state: seed }
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        self.state = self.state.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        let mut z = self.state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^ (z >> 31)
+    }
+
+    /// A value in [0, 1)
+    pub fn next_f32(&mut self) -> f32 {
+        (self.next_u64() >> 40) as f32 / (1u64 << 24) as f32
+    }
+
+    /// A value in [low, high)
+    pub fn range(&mut self, low: u32, high: u32) -> u32 {
+        if high <= low {
+            return low;
+        }
+        low + (self.next_u64() % (high - low) as u64) as u32
+    }
+
+    /// True with the given probability
+    pub fn chance(&mut self, probability: f32) -> bool {
+        self.next_f32() < probability
+    }
+}
+
+/// How a generated layout mirrors itself
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Symmetry {
+    None,
+    /// Left half mirrored onto the right half
+    Horizontal,
+    /// Mirrored left to right and top to bottom
+    Quad,
+}
+
+/// Derives the seed of a given level in a chain from the seed that started the chain
+pub fn level_seed(seed: u64, level: u32) -> u64 {
+    SeededRng::new(seed ^ (level as u64).wrapping_mul(0xD6E8_FEB8_6659_FD93)).next_u64()
+}
+
+/// How hard the nth level of an endless run should be, ramping up to the maximum over ten levels
+pub fn level_difficulty(level: u32) -> f32 {
+    (level as f32 * 0.1).min(1.0)
+}
+
+pub struct LevelGenerator {
+    pub seed: u64,
+    /// From 0.0 (sparse and soft) to 1.0 (dense, tough and with unbreakable obstacles)
+    pub difficulty: f32,
+}
+
+type Grid = Vec<Vec<Option<BrickType>>>;
+
+impl LevelGenerator {
+    pub fn new(seed: u64, difficulty: f32) -> Self {
+        LevelGenerator {
+            seed,
+            difficulty: difficulty.max(0.0).min(1.0),
+        }
+    }
+
+    pub fn generate(&self) -> LevelLayout {
+        to_layout(&self.generate_grid().0)
+    }
+
+    fn generate_grid(&self) -> (Grid, Symmetry) {
+        let mut rng = SeededRng::new(self.seed);
+        let difficulty = self.difficulty;
+
+        let columns = rng.range(5, 7 + (difficulty * 4.0) as u32) as usize;
+        let rows = rng.range(4, 5 + (difficulty * 4.0) as u32) as usize;
+        let symmetry = match rng.range(0, 3) {
+            0 => Symmetry::None,
+            1 => Symmetry::Horizontal,
+            _ => Symmetry::Quad,
+        };
+
+        let density = 0.45 + 0.5 * difficulty;
+        let tough_chance = 0.1 + 0.4 * difficulty;
+        let unbreakable_chance = 0.15 * difficulty;
+
+        let mut grid: Grid = vec![vec![None; columns]; rows];
+        for row in 0..rows {
+            for column in 0..columns {
+                //only roll for the cells that aren't a mirror of one we've already rolled
+                let mirrored_column = column >= (columns + 1) / 2;
+                let mirrored_row = row >= (rows + 1) / 2;
+                let is_mirror = match symmetry {
+                    Symmetry::None => false,
+                    Symmetry::Horizontal => mirrored_column,
+                    Symmetry::Quad => mirrored_column || mirrored_row,
+                };
+                if is_mirror {
+                    continue;
+                }
+
+                let cell = if rng.chance(density) {
+                    let roll = rng.next_f32();
+                    Some(if roll < unbreakable_chance {
+                        BrickType::Unbreakable
+                    } else if roll < unbreakable_chance + tough_chance {
+                        BrickType::Tough
+                    } else {
+                        BrickType::Normal
+                    })
+                } else {
+                    None
+                };
+                for (mirror_row, mirror_column) in mirrors(symmetry, rows, columns, row, column) {
+                    grid[mirror_row][mirror_column] = cell;
+                }
+            }
+        }
+
+        ensure_reachable(&mut grid, symmetry);
+        ensure_breakable(&mut grid);
+
+        (grid, symmetry)
+    }
+}
+
+/// Every cell that should share the contents of the given cell (including itself)
+fn mirrors(
+    symmetry: Symmetry,
+    rows: usize,
+    columns: usize,
+    row: usize,
+    column: usize,
+) -> Vec<(usize, usize)> {
+    let flipped_row = rows - 1 - row;
+    let flipped_column = columns - 1 - column;
+    let mut cells = vec![(row, column)];
+    match symmetry {
+        Symmetry::None => {}
+        Symmetry::Horizontal => cells.push((row, flipped_column)),
+        Symmetry::Quad => {
+            cells.push((row, flipped_column));
+            cells.push((flipped_row, column));
+            cells.push((flipped_row, flipped_column));
+        }
+    }
+    cells
+}
+
+/// Which cells the ball can get to. The ball can travel around the outside of the layout, so every cell on the border
+/// that isn't unbreakable is a way in, and from there it can travel through empty or breakable cells
+fn reachable_cells(grid: &Grid) -> Vec<Vec<bool>> {
+    let rows = grid.len();
+    let columns = grid[0].len();
+    let passable = |row: usize, column: usize| grid[row][column] != Some(BrickType::Unbreakable);
+
+    let mut reached = vec![vec![false; columns]; rows];
+    let mut to_visit = Vec::new();
+    for row in 0..rows {
+        for column in 0..columns {
+            let on_border = row == 0 || column == 0 || row == rows - 1 || column == columns - 1;
+            if on_border && passable(row, column) {
+                reached[row][column] = true;
+                to_visit.push((row, column));
+            }
+        }
+    }
+
+    while let Some((row, column)) = to_visit.pop() {
+        let mut neighbors = Vec::with_capacity(4);
+        if row > 0 {
+            neighbors.push((row - 1, column));
+        }
+        if row + 1 < rows {
+            neighbors.push((row + 1, column));
+        }
+        if column > 0 {
+            neighbors.push((row, column - 1));
+        }
+        if column + 1 < columns {
+            neighbors.push((row, column + 1));
+        }
+        for (neighbor_row, neighbor_column) in neighbors {
+            if !reached[neighbor_row][neighbor_column] && passable(neighbor_row, neighbor_column) {
+                reached[neighbor_row][neighbor_column] = true;
+                to_visit.push((neighbor_row, neighbor_column));
+            }
+        }
+    }
+    reached
+}
+
+/// Breakable bricks walled in by unbreakable ones would make a level impossible to clear.
+/// For each one found we turn the unbreakable bricks beneath it into normal ones, opening a path down to the bottom edge
+fn ensure_reachable(grid: &mut Grid, symmetry: Symmetry) {
+    let rows = grid.len();
+    let columns = grid[0].len();
+    loop {
+        let reached = reachable_cells(grid);
+        let stranded = (0..rows)
+            .flat_map(|row| (0..columns).map(move |column| (row, column)))
+            .find(|&(row, column)| {
+                let breakable = grid[row][column].map_or(false, |brick| brick.is_breakable());
+                breakable && !reached[row][column]
+            });
+
+        let (stranded_row, stranded_column) = match stranded {
+            Some(cell) => cell,
+            None => return,
+        };
+
+        for row in 0..stranded_row {
+            if grid[row][stranded_column] == Some(BrickType::Unbreakable) {
+                //fix up the mirrored cells too so the layout stays symmetric
+                for (mirror_row, mirror_column) in
+                    mirrors(symmetry, rows, columns, row, stranded_column)
+                {
+                    grid[mirror_row][mirror_column] = Some(BrickType::Normal);
+                }
+            }
+        }
+    }
+}
+
+/// A level with nothing to break would be cleared immediately, so always leave at least one brick in the middle
+fn ensure_breakable(grid: &mut Grid) {
+    let has_breakable = grid
+        .iter()
+        .flatten()
+        .any(|cell| cell.map_or(false, |brick| brick.is_breakable()));
+    if !has_breakable {
+        let middle = grid[0].len() / 2;
+        grid[0][middle] = Some(BrickType::Normal);
+    }
+}
+
+fn to_layout(grid: &Grid) -> LevelLayout {
+    let columns = grid[0].len();
+    let brick_width = (LAYOUT_WIDTH - BRICK_SPACING * (columns - 1) as f32) / columns as f32;
+    let brick_size = Vec2::new(brick_width, BRICK_HEIGHT);
+    let left = -LAYOUT_WIDTH / 2.0 + brick_width / 2.0;
+
+    let mut bricks = Vec::new();
+    for (row, cells) in grid.iter().enumerate() {
+        for (column, cell) in cells.iter().enumerate() {
+            if let Some(brick_type) = cell {
+                bricks.push(BrickSpec {
+                    position: Vec2::new(
+                        left + column as f32 * (brick_width + BRICK_SPACING),
+                        LOWEST_ROW_Y + row as f32 * (BRICK_HEIGHT + BRICK_SPACING),
+                    ),
+                    size: brick_size,
+                    brick_type: *brick_type,
+                    color: None,
+                    path: None,
+                });
+            }
+        }
+    }
+    LevelLayout {
+        bricks,
+        hazards: Vec::new(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn same_seed_same_layout() {
+        for &difficulty in &[0.0, 0.5, 1.0] {
+            let layout = LevelGenerator::new(1234, difficulty).generate();
+            assert_eq!(LevelGenerator::new(1234, difficulty).generate(), layout);
+            assert_ne!(LevelGenerator::new(1235, difficulty).generate(), layout);
+        }
+    }
+
+    #[test]
+    fn breakable_bricks_are_reachable() {
+        for seed in 0..200 {
+            let (grid, _) = LevelGenerator::new(seed, 1.0).generate_grid();
+            let reached = reachable_cells(&grid);
+            for (row, cells) in grid.iter().enumerate() {
+                for (column, cell) in cells.iter().enumerate() {
+                    if cell.map_or(false, |brick| brick.is_breakable()) {
+                        assert!(reached[row][column], "seed {} strands a brick", seed);
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn walled_in_brick_is_opened_up() {
+        let unbreakable = Some(BrickType::Unbreakable);
+        let mut grid = vec![
+            vec![unbreakable, unbreakable, unbreakable],
+            vec![unbreakable, Some(BrickType::Normal), unbreakable],
+            vec![unbreakable, unbreakable, unbreakable],
+        ];
+        ensure_reachable(&mut grid, Symmetry::None);
+        assert!(reachable_cells(&grid)[1][1]);
+    }
+
+    #[test]
+    fn layouts_are_symmetric() {
+        for seed in 0..200 {
+            let (grid, symmetry) = LevelGenerator::new(seed, 1.0).generate_grid();
+            let rows = grid.len();
+            let columns = grid[0].len();
+            for row in 0..rows {
+                for column in 0..columns {
+                    for (mirror_row, mirror_column) in mirrors(symmetry, rows, columns, row, column)
+                    {
+                        assert_eq!(
+                            grid[mirror_row][mirror_column], grid[row][column],
+                            "seed {} isn't {:?} symmetric",
+                            seed, symmetry
+                        );
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn always_something_to_break() {
+        let mut grid = vec![vec![Some(BrickType::Unbreakable), None]; 2];
+        ensure_breakable(&mut grid);
+        assert!(grid
+            .iter()
+            .flatten()
+            .any(|cell| cell.map_or(false, |brick| brick.is_breakable())));
+
+        for seed in 0..200 {
+            let layout = LevelGenerator::new(seed, 1.0).generate();
+            assert!(layout
+                .bricks
+                .iter()
+                .any(|brick| brick.brick_type.is_breakable()));
+        }
+    }
+
+    #[test]
+    fn ball_fits_around_the_layout() {
+        let wall_inside = BOUNDS.0 - WALL_THICKNESS / 2.0;
+        for seed in 0..50 {
+            for brick in LevelGenerator::new(seed, 1.0).generate().bricks {
+                let outer_edge = brick.position.x().abs() + brick.size.x() / 2.0;
+                assert!(wall_inside - outer_edge > BALL_SIZE);
+            }
+        }
+    }
+}
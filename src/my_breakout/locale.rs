@@ -0,0 +1,348 @@
+//Every string the player sees (menus, the HUD, the results) is looked up by key in a string table for the language set in the
+//settings, one ron file per language in assets/locales. Strings can have {name} placeholders filled in when they're looked up,
+//and anything counted has a form per plural category (the key with .one or .other on the end) since languages disagree on
+//what takes a plural. Numbers are formatted with the language's separators.
+//
+//English is built into the game so there's always something to fall back on: a key missing from another language is shown
+//in English, with a warning the first time so the gap gets noticed. The console and debug overlay are for developers and stay
+//in English
+
+use bevy::prelude::*;
+use serde::Deserialize;
+use std::{
+    collections::{HashMap, HashSet},
+    env, fs, io,
+    path::PathBuf,
+    sync::Mutex,
+};
+
+use super::console::{missing, parse_arg, AddConsoleCommand, ConsoleCommand};
+use super::theme::Accessibility;
+
+const LOCALES_DIRECTORY: &str = "assets/locales";
+const FALLBACK_LANGUAGE: &str = "en";
+const FALLBACK_TABLE: &str = include_str!("../../assets/locales/en.ron");
+
+/// Which plural category a count falls into, as languages split them differently
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize)]
+pub enum PluralRule {
+    /// Only exactly one is singular, like English and German
+    OneIsSingular,
+    /// Zero is singular too, like French
+    ZeroAndOneAreSingular,
+    /// Nothing changes with the count, like Japanese
+    NoPlural,
+}
+
+impl PluralRule {
+    fn category(self, count: u64) -> &'static str {
+        let singular = match self {
+            PluralRule::OneIsSingular => count == 1,
+            PluralRule::ZeroAndOneAreSingular => count <= 1,
+            PluralRule::NoPlural => false,
+        };
+        if singular {
+            "one"
+        } else {
+            "other"
+        }
+    }
+}
+
+/// One language's strings, as read from its file
+#[derive(Debug, Deserialize)]
+struct StringTable {
+    plural: PluralRule,
+    decimal_separator: String,
+    /// Put between every three digits of a big number
+    grouping_separator: String,
+    strings: HashMap<String, String>,
+}
+
+impl StringTable {
+    fn parse(contents: &str) -> io::Result<StringTable> {
+        ron::de::from_str(contents)
+            .map_err(|error| io::Error::new(io::ErrorKind::InvalidData, error.to_string()))
+    }
+
+    fn plural_key(&self, key: &str, count: u64) -> String {
+        format!("{}.{}", key, self.plural.category(count))
+    }
+
+    fn number(&self, value: u64) -> String {
+        group_digits(&value.to_string(), &self.grouping_separator)
+    }
+
+    fn decimal(&self, value: f32, places: usize) -> String {
+        let formatted = format!("{:.*}", places, value.abs());
+        let mut parts = formatted.splitn(2, '.');
+        let whole = group_digits(parts.next().unwrap_or("0"), &self.grouping_separator);
+        //no -0 for something that only rounds to zero
+        let sign = if value < 0.0 && formatted.chars().any(|c| c.is_ascii_digit() && c != '0') {
+            "-"
+        } else {
+            ""
+        };
+        match parts.next() {
+            Some(fraction) => format!("{}{}{}{}", sign, whole, self.decimal_separator, fraction),
+            None => format!("{}{}", sign, whole),
+        }
+    }
+}
+
+fn group_digits(digits: &str, separator: &str) -> String {
+    let mut grouped = String::new();
+    for (index, digit) in digits.chars().enumerate() {
+        if index > 0 && (digits.len() - index) % 3 == 0 {
+            grouped.push_str(separator);
+        }
+        grouped.push(digit);
+    }
+    grouped
+}
+
+fn fill(template: &str, args: &[(&str, String)]) -> String {
+    args.iter()
+        .fold(template.to_string(), |text, (name, value)| {
+            text.replace(&format!("{{{}}}", name), value)
+        })
+}
+
+/// A string to show, kept as its key and arguments so it's looked up in whatever language is set when it's shown
+#[derive(Clone, Debug, PartialEq)]
+pub struct Message {
+    pub key: &'static str,
+    pub args: Vec<(&'static str, String)>,
+}
+
+impl Message {
+    pub fn new(key: &'static str) -> Self {
+        Message {
+            key,
+            args: Vec::new(),
+        }
+    }
+
+    pub fn arg(mut self, name: &'static str, value: impl ToString) -> Self {
+        self.args.push((name, value.to_string()));
+        self
+    }
+}
+
+/// Resource with the strings for the language in use
+pub struct Localization {
+    pub language: String,
+    table: Option<StringTable>,
+    fallback: StringTable,
+    //so each missing key is only warned about once, rather than every frame it's on screen
+    warned: Mutex<HashSet<String>>,
+}
+
+impl Localization {
+    /// Loads the given language's strings, with English to fall back on
+    pub fn load(language: &str) -> io::Result<Localization> {
+        let table = if language == FALLBACK_LANGUAGE {
+            None
+        } else {
+            let path = PathBuf::from(LOCALES_DIRECTORY).join(format!("{}.ron", language));
+            Some(StringTable::parse(&fs::read_to_string(path)?)?)
+        };
+        Ok(Localization {
+            language: language.to_string(),
+            table,
+            fallback: StringTable::parse(FALLBACK_TABLE).expect("the built in English strings"),
+            warned: Mutex::new(HashSet::new()),
+        })
+    }
+
+    fn table(&self) -> &StringTable {
+        self.table.as_ref().unwrap_or(&self.fallback)
+    }
+
+    fn lookup(&self, key: &str, fallback_key: &str) -> String {
+        if let Some(text) = self.table().strings.get(key) {
+            return text.clone();
+        }
+        if self.warned.lock().unwrap().insert(key.to_string()) {
+            log::warn!(
+                "No {} string for {}, falling back to English",
+                self.language,
+                key
+            );
+        }
+        //the key itself is the last resort, at least it shows what's missing
+        self.fallback
+            .strings
+            .get(fallback_key)
+            .cloned()
+            .unwrap_or_else(|| fallback_key.to_string())
+    }
+
+    /// The string for the key, with its placeholders filled in from `args`
+    pub fn format(&self, key: &str, args: &[(&str, String)]) -> String {
+        fill(&self.lookup(key, key), args)
+    }
+
+    pub fn text(&self, key: &str) -> String {
+        self.format(key, &[])
+    }
+
+    pub fn message(&self, message: &Message) -> String {
+        self.format(message.key, &message.args)
+    }
+
+    /// The form of the string for the key that goes with the count, which fills its {count} placeholder
+    pub fn plural(&self, key: &str, count: u64) -> String {
+        let template = self.lookup(
+            &self.table().plural_key(key, count),
+            &self.fallback.plural_key(key, count),
+        );
+        fill(&template, &[("count", self.number(count))])
+    }
+
+    /// A whole number with the language's grouping separators
+    pub fn number(&self, value: u64) -> String {
+        self.table().number(value)
+    }
+
+    /// A number rounded to the given number of decimal places, with the language's separators
+    pub fn decimal(&self, value: f32, places: usize) -> String {
+        self.table().decimal(value, places)
+    }
+}
+
+/// The language the system's set to (from LANG, like "de_DE.UTF-8"), which is what's used until the settings say otherwise
+pub fn system_language() -> String {
+    env::var("LANG")
+        .ok()
+        .and_then(|lang| {
+            lang.split(|c| c == '_' || c == '.')
+                .next()
+                .map(str::to_string)
+        })
+        .filter(|language| !language.is_empty() && language != "C")
+        .unwrap_or_else(|| FALLBACK_LANGUAGE.to_string())
+}
+
+fn load_or_fallback(language: &str) -> Localization {
+    Localization::load(language).unwrap_or_else(|error| {
+        log::warn!(
+            "Couldn't load the {} strings, using English: {}",
+            language,
+            error
+        );
+        Localization::load(FALLBACK_LANGUAGE).unwrap()
+    })
+}
+
+pub struct LocalePlugin;
+
+impl Plugin for LocalePlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        //the language comes from the settings, so the theme plugin has to have loaded them already
+        let language = app
+            .resources()
+            .get::<Accessibility>()
+            .map_or_else(system_language, |settings| settings.language.clone());
+        app.add_resource(load_or_fallback(&language))
+            .add_system(language_system.system())
+            .add_console_command(LanguageCommand);
+    }
+}
+
+/// Switches the strings over when the language setting changes
+fn language_system(settings: Res<Accessibility>, mut localization: ResMut<Localization>) {
+    //a language that didn't load stays asked for, so only try it the once
+    if settings.language != localization.language {
+        *localization = load_or_fallback(&settings.language);
+        localization.language = settings.language.clone();
+    }
+}
+
+struct LanguageCommand;
+
+impl ConsoleCommand for LanguageCommand {
+    fn name(&self) -> &'static str {
+        "language"
+    }
+
+    fn usage(&self) -> &'static str {
+        "language <code> - shows the game's text in the language from assets/locales/<code>.ron"
+    }
+
+    fn run(
+        &self,
+        args: &[&str],
+        _world: &mut World,
+        resources: &mut Resources,
+    ) -> Result<String, String> {
+        let language: String = parse_arg(args, self.usage())?;
+        Localization::load(&language).map_err(|error| error.to_string())?;
+        let mut settings = resources
+            .get_mut::<Accessibility>()
+            .ok_or_else(missing::<Accessibility>)?;
+        settings.language = language.clone();
+        settings.save_or_warn();
+        Ok(format!("Switched to {}", language))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn table(contents: &str) -> StringTable {
+        StringTable::parse(contents).unwrap()
+    }
+
+    #[test]
+    fn digits_are_grouped_in_threes_from_the_right() {
+        assert_eq!(group_digits("0", ","), "0");
+        assert_eq!(group_digits("999", ","), "999");
+        assert_eq!(group_digits("1000", ","), "1,000");
+        assert_eq!(group_digits("1234567", "."), "1.234.567");
+        assert_eq!(group_digits("12345678", " "), "12 345 678");
+        assert_eq!(group_digits("", ","), "");
+    }
+
+    #[test]
+    fn numbers_use_the_languages_separators() {
+        let english = table(FALLBACK_TABLE);
+        let german = table(include_str!("../../assets/locales/de.ron"));
+        let french = table(include_str!("../../assets/locales/fr.ron"));
+        assert_eq!(english.number(1234567), "1,234,567");
+        assert_eq!(german.number(1234567), "1.234.567");
+        assert_eq!(french.number(1234567), "1 234 567");
+
+        assert_eq!(english.decimal(1234.5, 2), "1,234.50");
+        assert_eq!(german.decimal(1234.5, 2), "1.234,50");
+        assert_eq!(german.decimal(0.3, 1), "0,3");
+        assert_eq!(english.decimal(7.0, 0), "7");
+    }
+
+    #[test]
+    fn negative_decimals_only_keep_their_sign_if_they_dont_round_to_zero() {
+        let english = table(FALLBACK_TABLE);
+        assert_eq!(english.decimal(-1234.5, 1), "-1,234.5");
+        assert_eq!(english.decimal(-0.04, 1), "0.0");
+        assert_eq!(english.decimal(-0.4, 0), "0");
+        assert_eq!(english.decimal(-0.06, 1), "-0.1");
+    }
+
+    #[test]
+    fn plural_categories_follow_the_language() {
+        assert_eq!(PluralRule::OneIsSingular.category(0), "other");
+        assert_eq!(PluralRule::OneIsSingular.category(1), "one");
+        assert_eq!(PluralRule::OneIsSingular.category(2), "other");
+        assert_eq!(PluralRule::ZeroAndOneAreSingular.category(0), "one");
+        assert_eq!(PluralRule::ZeroAndOneAreSingular.category(1), "one");
+        assert_eq!(PluralRule::ZeroAndOneAreSingular.category(2), "other");
+        assert_eq!(PluralRule::NoPlural.category(1), "other");
+
+        let french = table(include_str!("../../assets/locales/fr.ron"));
+        assert_eq!(
+            french.plural_key("net.ticks_ahead", 0),
+            "net.ticks_ahead.one"
+        );
+    }
+}
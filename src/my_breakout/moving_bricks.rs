@@ -0,0 +1,52 @@
+//Bricks that follow a path from the level file: sliding side to side, circling, or going round a set of waypoints.
+//Each one's moved to wherever its path has it at, and how fast that moved it is kept on the brick so the collision code can
+//treat it as something that can run into the ball, rather than only something the ball runs into.
+//They keep to game time, so they slow down along with the ball and stop when the game does
+
+use bevy::prelude::*;
+
+use super::level::BrickMovement;
+use super::theme::Accessibility;
+use super::{GameState, BRICK_MOVEMENT_STAGE};
+
+pub struct MovingBricksPlugin;
+
+impl Plugin for MovingBricksPlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        //in a stage of its own so the grid and the ball's collision checks both see where the bricks are this frame
+        app.add_system_to_stage(BRICK_MOVEMENT_STAGE, brick_movement_system.system());
+    }
+}
+
+fn brick_movement_system(
+    time: Res<Time>,
+    game_state: Res<GameState>,
+    accessibility: Res<Accessibility>,
+    mut query: Query<(&mut BrickMovement, &mut Transform)>,
+) {
+    if !game_state.is_simulating() {
+        //a brick held still shouldn't hit the ball as if it were still moving once the game carries on
+        for (mut movement, _transform) in &mut query.iter() {
+            movement.velocity = Vec2::zero();
+        }
+        return;
+    }
+
+    // the same clamped and slowed down timestep the ball moves by
+    let delta_seconds = f32::min(0.2, time.delta_seconds) * accessibility.game_speed;
+    if delta_seconds <= 0.0 {
+        return;
+    }
+
+    for (mut movement, mut transform) in &mut query.iter() {
+        let before = movement.path.offset(movement.elapsed);
+        movement.elapsed += delta_seconds;
+        let after = movement.path.offset(movement.elapsed);
+        movement.velocity = (after - before) / delta_seconds;
+
+        let position = movement.origin + after;
+        let translation = transform.translation_mut();
+        *translation.x_mut() = position.x();
+        *translation.y_mut() = position.y();
+    }
+}
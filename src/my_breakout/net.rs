@@ -0,0 +1,982 @@
+//Online versus play between two instances of the game over UDP, on the same machine or across a LAN
+//
+//Both peers run the deterministic simulation and only ever send each other their inputs. We don't wait on the other side's
+//input before simulating a tick, instead we predict it (assuming they're still holding whatever they last pressed) and when their
+//real input shows up and turns out different we roll back to the tick it was for and re-simulate forward from there.
+//Every so often both sides hash the state of a tick whose inputs are fully known and compare, so any desync gets noticed.
+//
+//To try it locally run the game twice: press H in one to host, then press Enter in the other to join 127.0.0.1.
+//H only takes players from the same machine, L is for hosting someone else on the LAN.
+//
+//Only inputs the peer could still have to be told about, or that could still be rolled back over, are kept. The rest are
+//dropped as the match goes on, unless the match is being recorded to a replay
+
+use bevy::{prelude::*, window::ReceivedCharacter};
+use std::{
+    collections::HashMap,
+    io,
+    net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr, UdpSocket},
+    path::PathBuf,
+};
+
+use super::console::ConsoleState;
+use super::level::{spawn_brick, versus_layout, LevelLayout};
+use super::locale::{Localization, Message};
+use super::players::{PlayerBindings, STARTING_LIVES};
+use super::simulation::{step, Replay, SimulationState, TickInputs, PLAYER_COUNT, TICK_SECONDS};
+use super::{Ball, GameState, Paddle, Scoreboard};
+
+const DEFAULT_PORT: u16 = 7777;
+//How many of our latest inputs go out in every packet, so a few lost packets don't lose any inputs
+const INPUT_WINDOW: usize = 32;
+//How many ticks we'll run ahead of the last input we've heard from the peer before waiting for them to catch up
+const MAX_PREDICTION: u32 = 30;
+//How many ticks back we keep snapshots for, which has to cover the furthest we could ever need to roll back
+const SNAPSHOT_HISTORY: u32 = 4 * MAX_PREDICTION;
+const CHECKSUM_INTERVAL: u32 = 60;
+const HELLO_SECONDS: f32 = 0.5;
+
+/// Everything the peers say to each other
+#[derive(Clone, Debug, PartialEq)]
+enum Message {
+    /// Sent by the joining peer until the host answers
+    Hello,
+    /// The host's answer, after which both sides start at tick 0
+    Welcome,
+    /// The sender's inputs for the ticks ending at `last_tick`
+    Inputs { last_tick: u32, inputs: Vec<i8> },
+    /// The hash of the sender's state at the start of `tick`
+    Checksum { tick: u32, checksum: u64 },
+}
+
+impl Message {
+    fn encode(&self) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        match self {
+            Message::Hello => bytes.push(1),
+            Message::Welcome => bytes.push(2),
+            Message::Inputs { last_tick, inputs } => {
+                bytes.push(3);
+                bytes.extend_from_slice(&last_tick.to_le_bytes());
+                bytes.push(inputs.len() as u8);
+                bytes.extend(inputs.iter().map(|input| *input as u8));
+            }
+            Message::Checksum { tick, checksum } => {
+                bytes.push(4);
+                bytes.extend_from_slice(&tick.to_le_bytes());
+                bytes.extend_from_slice(&checksum.to_le_bytes());
+            }
+        }
+        bytes
+    }
+
+    /// None for anything malformed, which just gets dropped like a lost packet would
+    fn decode(bytes: &[u8]) -> Option<Message> {
+        let read_u32 = |at: usize| -> Option<u32> {
+            let mut buffer = [0; 4];
+            buffer.copy_from_slice(bytes.get(at..at + 4)?);
+            Some(u32::from_le_bytes(buffer))
+        };
+
+        match bytes.first()? {
+            1 => Some(Message::Hello),
+            2 => Some(Message::Welcome),
+            3 => {
+                let last_tick = read_u32(1)?;
+                let count = *bytes.get(5)? as usize;
+                let inputs = bytes.get(6..6 + count)?;
+                if count == 0 || count as u32 > last_tick.saturating_add(1) {
+                    return None;
+                }
+                Some(Message::Inputs {
+                    last_tick,
+                    inputs: inputs.iter().map(|input| *input as i8).collect(),
+                })
+            }
+            4 => {
+                let tick = read_u32(1)?;
+                let mut buffer = [0; 8];
+                buffer.copy_from_slice(bytes.get(5..13)?);
+                Some(Message::Checksum {
+                    tick,
+                    checksum: u64::from_le_bytes(buffer),
+                })
+            }
+            _ => None,
+        }
+    }
+}
+
+/// A non blocking UDP socket talking to a single peer
+struct Transport {
+    socket: UdpSocket,
+    peer: Option<SocketAddr>,
+}
+
+impl Transport {
+    fn bind(address: SocketAddr) -> io::Result<Self> {
+        let socket = UdpSocket::bind(address)?;
+        socket.set_nonblocking(true)?;
+        Ok(Transport { socket, peer: None })
+    }
+
+    /// Where to listen to be reachable from `peer`. That's only the loopback interface for a peer on this machine, any
+    /// other one needs every interface as there's no telling which the packets will come in on
+    fn address_reaching(peer: IpAddr, port: u16) -> SocketAddr {
+        let ip = match (peer, peer.is_loopback()) {
+            (IpAddr::V4(_), true) => IpAddr::V4(Ipv4Addr::LOCALHOST),
+            (IpAddr::V4(_), false) => IpAddr::V4(Ipv4Addr::UNSPECIFIED),
+            (IpAddr::V6(_), true) => IpAddr::V6(Ipv6Addr::LOCALHOST),
+            (IpAddr::V6(_), false) => IpAddr::V6(Ipv6Addr::UNSPECIFIED),
+        };
+        SocketAddr::new(ip, port)
+    }
+
+    fn send(&self, message: &Message) {
+        if let Some(peer) = self.peer {
+            //UDP gives no guarantees anyway, so a failed send is treated the same as a lost packet
+            let _ = self.socket.send_to(&message.encode(), peer);
+        }
+    }
+
+    /// Everything that has arrived since we last looked, only from the peer once there is one
+    fn receive(&self) -> Vec<(SocketAddr, Message)> {
+        let mut messages = Vec::new();
+        let mut buffer = [0; 512];
+        loop {
+            match self.socket.recv_from(&mut buffer) {
+                Ok((_length, from)) if self.peer.map_or(false, |peer| peer != from) => {}
+                Ok((length, from)) => {
+                    if let Some(message) = Message::decode(&buffer[..length]) {
+                        messages.push((from, message));
+                    }
+                }
+                //WouldBlock means there's nothing left, anything else (e.g. the peer's port not being open yet) we ride out
+                Err(error) if error.kind() == io::ErrorKind::WouldBlock => break,
+                Err(_) => break,
+            }
+        }
+        messages
+    }
+}
+
+/// The rollback bookkeeping for a running online game
+struct Rollback {
+    local_player: usize,
+    layout: LevelLayout,
+    /// The next tick to simulate
+    tick: u32,
+    state: SimulationState,
+    /// The state at the start of each recent tick
+    snapshots: HashMap<u32, SimulationState>,
+    /// The tick the inputs below start from, every tick before it having been confirmed and dropped
+    base_tick: u32,
+    /// Our own inputs, indexed by tick from `base_tick`
+    local_inputs: Vec<i8>,
+    /// The peer's inputs as far as we've heard them, indexed by tick from `base_tick`
+    remote_inputs: Vec<Option<i8>>,
+    /// What we assumed the peer's input was for each tick we've simulated, indexed by tick from `base_tick`
+    remote_used: Vec<i8>,
+    /// The peer's input for the tick before `base_tick`, for predicting from when we've heard nothing newer
+    remote_before_base: i8,
+    /// Every tick's inputs before `base_tick`, if the match is being recorded
+    recorded: Option<Vec<TickInputs>>,
+    next_checksum_tick: u32,
+    local_checksums: HashMap<u32, u64>,
+    remote_checksums: HashMap<u32, u64>,
+    /// The first tick the two sides were found to disagree on
+    desync: Option<u32>,
+}
+
+impl Rollback {
+    /// `record` keeps every input of the match for its replay
+    fn new(local_player: usize, record: bool) -> Self {
+        let layout = versus_layout();
+        Rollback {
+            local_player,
+            tick: 0,
+            state: SimulationState::new(&layout, STARTING_LIVES),
+            layout,
+            snapshots: HashMap::new(),
+            base_tick: 0,
+            local_inputs: Vec::new(),
+            remote_inputs: Vec::new(),
+            remote_used: Vec::new(),
+            remote_before_base: 0,
+            recorded: if record { Some(Vec::new()) } else { None },
+            next_checksum_tick: CHECKSUM_INTERVAL,
+            local_checksums: HashMap::new(),
+            remote_checksums: HashMap::new(),
+            desync: None,
+        }
+    }
+
+    fn remote_player(&self) -> usize {
+        (self.local_player + 1) % PLAYER_COUNT
+    }
+
+    /// Where a tick's inputs are in the input buffers
+    fn index(&self, tick: u32) -> usize {
+        (tick - self.base_tick) as usize
+    }
+
+    /// The last tick up to which we know every one of the peer's inputs
+    fn confirmed_tick(&self) -> Option<u32> {
+        let known = self
+            .remote_inputs
+            .iter()
+            .position(|input| input.is_none())
+            .unwrap_or_else(|| self.remote_inputs.len());
+        (self.base_tick + known as u32).checked_sub(1)
+    }
+
+    /// The peer's input for a tick, or our best guess of it: whatever they were last known to be pressing
+    fn remote_input(&self, tick: u32) -> i8 {
+        let latest = self
+            .index(tick)
+            .min(self.remote_inputs.len().saturating_sub(1));
+        self.remote_inputs
+            .get(..=latest)
+            .and_then(|inputs| inputs.iter().rev().find_map(|input| *input))
+            .unwrap_or(self.remote_before_base)
+    }
+
+    fn inputs_for(&self, tick: u32) -> TickInputs {
+        let mut inputs = [0; PLAYER_COUNT];
+        inputs[self.local_player] = self.local_inputs[self.index(tick)];
+        inputs[self.remote_player()] = self.remote_input(tick);
+        inputs
+    }
+
+    /// Simulates the current tick, recording what it was simulated with
+    fn simulate_tick(&mut self) {
+        let tick = self.tick;
+        let index = self.index(tick);
+        let inputs = self.inputs_for(tick);
+        self.snapshots.insert(tick, self.state.clone());
+        if self.remote_used.len() <= index {
+            self.remote_used.push(inputs[self.remote_player()]);
+        } else {
+            self.remote_used[index] = inputs[self.remote_player()];
+        }
+        step(&mut self.state, &self.layout, inputs);
+        self.tick += 1;
+    }
+
+    /// Runs the next tick with our input. Returns false without doing anything if we're too far ahead of the peer
+    fn advance(&mut self, local_input: i8) -> bool {
+        let confirmed_until = self.confirmed_tick().map_or(0, |tick| tick + 1);
+        if self.tick >= confirmed_until + MAX_PREDICTION {
+            return false;
+        }
+
+        self.local_inputs.push(local_input);
+        self.simulate_tick();
+
+        let oldest = self.tick.saturating_sub(SNAPSHOT_HISTORY);
+        self.snapshots.retain(|tick, _| *tick >= oldest);
+        self.trim_inputs();
+        true
+    }
+
+    /// Drops the inputs that are done with: the peer's are all known, so they can't cause a rollback, and ours have dropped
+    /// out of what gets resent
+    fn trim_inputs(&mut self) {
+        let confirmed_until = self.confirmed_tick().map_or(0, |tick| tick + 1);
+        let resent_from = self.tick.saturating_sub(INPUT_WINDOW as u32);
+        let keep_from = confirmed_until.min(resent_from);
+        if keep_from <= self.base_tick {
+            return;
+        }
+
+        if self.recorded.is_some() {
+            let inputs: Vec<TickInputs> = (self.base_tick..keep_from)
+                .map(|tick| self.inputs_for(tick))
+                .collect();
+            self.recorded.as_mut().unwrap().extend(inputs);
+        }
+        self.remote_before_base = self.remote_input(keep_from - 1);
+        let count = self.index(keep_from);
+        self.local_inputs.drain(..count);
+        self.remote_inputs.drain(..count);
+        self.remote_used.drain(..count);
+        self.base_tick = keep_from;
+    }
+
+    /// Takes in the peer's inputs, rolling back and re-simulating if any of them differ from what we predicted
+    fn receive_inputs(&mut self, last_tick: u32, inputs: &[i8]) {
+        //the peer can't legitimately get further ahead of us than it's allowed to predict
+        let local_until = self.base_tick + self.local_inputs.len() as u32;
+        if last_tick >= local_until + MAX_PREDICTION {
+            return;
+        }
+        let first_tick = last_tick + 1 - inputs.len() as u32;
+        let mut mispredicted: Option<u32> = None;
+        for (offset, input) in inputs.iter().enumerate() {
+            let tick = first_tick + offset as u32;
+            //anything from before what we keep is already known
+            if tick < self.base_tick {
+                continue;
+            }
+            let index = self.index(tick);
+            if self.remote_inputs.len() <= index {
+                self.remote_inputs.resize(index + 1, None);
+            }
+            if self.remote_inputs[index].is_some() {
+                continue;
+            }
+            self.remote_inputs[index] = Some(*input);
+
+            if tick < self.tick && self.remote_used[index] != *input {
+                mispredicted = Some(mispredicted.map_or(tick, |earliest| earliest.min(tick)));
+            }
+        }
+
+        if let Some(from_tick) = mispredicted {
+            self.rollback_to(from_tick);
+        }
+        self.trim_inputs();
+    }
+
+    fn rollback_to(&mut self, from_tick: u32) {
+        let present = self.tick;
+        let snapshot = match self.snapshots.get(&from_tick) {
+            Some(snapshot) => snapshot.clone(),
+            //it's further back than we keep, which MAX_PREDICTION should never let happen
+            None => return,
+        };
+        self.state = snapshot;
+        self.tick = from_tick;
+        while self.tick < present {
+            self.simulate_tick();
+        }
+    }
+
+    /// Hashes every checkpoint tick whose inputs are now all known, returning the ones to send to the peer
+    fn take_checksums(&mut self) -> Vec<(u32, u64)> {
+        let mut checksums = Vec::new();
+        let confirmed_until = self.confirmed_tick().map_or(0, |tick| tick + 1);
+        while self.next_checksum_tick <= confirmed_until && self.next_checksum_tick < self.tick {
+            let tick = self.next_checksum_tick;
+            if let Some(snapshot) = self.snapshots.get(&tick) {
+                let checksum = snapshot.checksum();
+                self.local_checksums.insert(tick, checksum);
+                self.compare_checksums(tick);
+                checksums.push((tick, checksum));
+            }
+            self.next_checksum_tick += CHECKSUM_INTERVAL;
+        }
+        checksums
+    }
+
+    fn receive_checksum(&mut self, tick: u32, checksum: u64) {
+        self.remote_checksums.insert(tick, checksum);
+        self.compare_checksums(tick);
+    }
+
+    fn compare_checksums(&mut self, tick: u32) {
+        if let (Some(local), Some(remote)) = (
+            self.local_checksums.get(&tick),
+            self.remote_checksums.get(&tick),
+        ) {
+            if local != remote && self.desync.map_or(true, |first| tick < first) {
+                log::warn!(
+                    "Desync detected at tick {}: {:016x} vs {:016x}",
+                    tick,
+                    local,
+                    remote
+                );
+                self.desync = Some(tick);
+            }
+        }
+    }
+
+    /// Every input of the match so far, once all of them are known. Only a match being recorded has them
+    fn replay(&self) -> Option<Replay> {
+        let recorded = self.recorded.as_ref()?;
+        let confirmed_until = self.confirmed_tick().map_or(0, |tick| tick + 1);
+        if confirmed_until < self.tick {
+            return None;
+        }
+        let mut inputs = recorded.clone();
+        inputs.extend((self.base_tick..self.tick).map(|tick| self.inputs_for(tick)));
+        Some(Replay { inputs })
+    }
+
+    /// Our latest inputs, as sent in every packet
+    fn input_message(&self) -> Option<Message> {
+        let last_tick = (self.base_tick + self.local_inputs.len() as u32).checked_sub(1)?;
+        let start = self.local_inputs.len().saturating_sub(INPUT_WINDOW);
+        Some(Message::Inputs {
+            last_tick,
+            inputs: self.local_inputs[start..].to_vec(),
+        })
+    }
+}
+
+enum NetStage {
+    /// Picking whether to host or join (and typing in who to join)
+    Lobby,
+    Hosting,
+    Joining {
+        hello_timer: Timer,
+    },
+    Running(Box<Rollback>),
+}
+
+/// Resource for the online session
+pub struct NetSession {
+    stage: NetStage,
+    transport: Option<Transport>,
+    address_input: String,
+    accumulator: f32,
+    status: Option<Message>,
+    /// Where to save the match once it's decided, which is cleared once it has been
+    replay_path: Option<PathBuf>,
+}
+
+impl NetSession {
+    fn new(replay_path: Option<PathBuf>) -> Self {
+        NetSession {
+            stage: NetStage::Lobby,
+            transport: None,
+            address_input: format!("127.0.0.1:{}", DEFAULT_PORT),
+            accumulator: 0.0,
+            status: None,
+            replay_path,
+        }
+    }
+}
+
+impl NetSession {
+    fn start(&mut self, local_player: usize) {
+        self.accumulator = 0.0;
+        let record = self.replay_path.is_some();
+        self.stage = NetStage::Running(Box::new(Rollback::new(local_player, record)));
+    }
+}
+
+/// Online versus play. It goes on top of the BreakoutPlugin set up for two players and an empty level
+pub struct OnlinePlugin {
+    /// Where to save the match's inputs once it's decided
+    pub replay_path: Option<PathBuf>,
+}
+
+impl Plugin for OnlinePlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        app.add_resource(GameState::Online)
+            .add_resource(NetSession::new(self.replay_path.clone()))
+            .add_startup_system(online_setup.system())
+            .add_system(net_lobby_system.system())
+            .add_system(net_update_system.system())
+            .add_system(net_render_system.system())
+            .add_system(net_text_system.system());
+    }
+}
+
+//Marks a brick entity as showing the brick at the given index of the simulation's layout
+struct NetBrick {
+    index: usize,
+}
+
+struct NetText;
+
+fn online_setup(
+    mut commands: Commands,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+    asset_server: Res<AssetServer>,
+) {
+    for (index, brick) in versus_layout().bricks.iter().enumerate() {
+        spawn_brick(&mut commands, &mut materials, brick);
+        commands.with(NetBrick { index });
+    }
+
+    commands
+        .spawn(TextComponents {
+            text: Text {
+                font: asset_server.load("assets/fonts/FiraSans-Bold.ttf").unwrap(),
+                value: "".to_string(),
+                style: TextStyle {
+                    color: Color::rgb(0.1, 0.1, 0.1),
+                    font_size: 25.0,
+                },
+            },
+            style: Style {
+                position_type: PositionType::Absolute,
+                position: Rect {
+                    bottom: Val::Px(5.0),
+                    left: Val::Px(5.0),
+                    ..Default::default()
+                },
+                ..Default::default()
+            },
+            ..Default::default()
+        })
+        .with(NetText);
+}
+
+/// Hosting, typing in an address and connecting
+fn net_lobby_system(
+    time: Res<Time>,
+    keyboard_input: Res<Input<KeyCode>>,
+    console: Res<ConsoleState>,
+    mut character_reader: Local<EventReader<ReceivedCharacter>>,
+    character_events: Res<Events<ReceivedCharacter>>,
+    mut session: ResMut<NetSession>,
+) {
+    let session = &mut *session;
+    match &mut session.stage {
+        //what's typed into the console is still read past, so it doesn't turn up in the address once the console closes
+        NetStage::Lobby if console.open => {
+            for _event in character_reader.iter(&character_events) {}
+        }
+        NetStage::Lobby => {
+            for event in character_reader.iter(&character_events) {
+                //only what an ip:port can be made of
+                if event.char.is_ascii_digit() || event.char == '.' || event.char == ':' {
+                    session.address_input.push(event.char);
+                }
+            }
+            if keyboard_input.just_pressed(KeyCode::Back) {
+                session.address_input.pop();
+            }
+
+            //H hosts for someone on this machine, L for someone elsewhere on the network
+            let host_for = if keyboard_input.just_pressed(KeyCode::H) {
+                Some(IpAddr::V4(Ipv4Addr::LOCALHOST))
+            } else if keyboard_input.just_pressed(KeyCode::L) {
+                Some(IpAddr::V4(Ipv4Addr::UNSPECIFIED))
+            } else {
+                None
+            };
+            if let Some(peer) = host_for {
+                match Transport::bind(Transport::address_reaching(peer, DEFAULT_PORT)) {
+                    Ok(transport) => {
+                        session.transport = Some(transport);
+                        session.stage = NetStage::Hosting;
+                        session.status = None;
+                    }
+                    Err(error) => {
+                        session.status = Some(
+                            Message::new("net.host_failed")
+                                .arg("port", DEFAULT_PORT)
+                                .arg("error", error),
+                        )
+                    }
+                }
+            } else if keyboard_input.just_pressed(KeyCode::Return) {
+                let peer = match session.address_input.parse::<SocketAddr>() {
+                    Ok(peer) => peer,
+                    Err(_) => {
+                        session.status = Some(
+                            Message::new("net.bad_address").arg("address", &session.address_input),
+                        );
+                        return;
+                    }
+                };
+                //any free port will do for the joining side, the host learns it from our hello
+                match Transport::bind(Transport::address_reaching(peer.ip(), 0)) {
+                    Ok(mut transport) => {
+                        transport.peer = Some(peer);
+                        session.transport = Some(transport);
+                        session.stage = NetStage::Joining {
+                            hello_timer: Timer::from_seconds(HELLO_SECONDS, true),
+                        };
+                        session.status = None;
+                    }
+                    Err(error) => {
+                        session.status = Some(Message::new("net.socket_failed").arg("error", error))
+                    }
+                }
+            }
+        }
+        NetStage::Hosting => {
+            let transport = session.transport.as_mut().unwrap();
+            let hello_from = transport
+                .receive()
+                .into_iter()
+                .find(|(_, message)| *message == Message::Hello)
+                .map(|(from, _)| from);
+            if let Some(peer) = hello_from {
+                transport.peer = Some(peer);
+                transport.send(&Message::Welcome);
+                //the host always defends the bottom
+                session.start(0);
+            }
+        }
+        NetStage::Joining { hello_timer } => {
+            let transport = session.transport.as_ref().unwrap();
+            hello_timer.tick(time.delta_seconds);
+            if hello_timer.finished {
+                transport.send(&Message::Hello);
+            }
+            let welcomed = transport
+                .receive()
+                .iter()
+                .any(|(_, message)| *message == Message::Welcome);
+            if welcomed {
+                session.start(1);
+            }
+        }
+        NetStage::Running(_) => {}
+    }
+}
+
+/// Exchanges inputs and steps the simulation at its fixed rate
+fn net_update_system(
+    time: Res<Time>,
+    keyboard_input: Res<Input<KeyCode>>,
+    console: Res<ConsoleState>,
+    bindings: Res<PlayerBindings>,
+    mut session: ResMut<NetSession>,
+) {
+    let session = &mut *session;
+    let (rollback, transport) = match (&mut session.stage, &session.transport) {
+        (NetStage::Running(rollback), Some(transport)) => (rollback, transport),
+        _ => return,
+    };
+
+    for (_from, message) in transport.receive() {
+        match message {
+            Message::Inputs { last_tick, inputs } => rollback.receive_inputs(last_tick, &inputs),
+            Message::Checksum { tick, checksum } => rollback.receive_checksum(tick, checksum),
+            //the peer hasn't heard our welcome yet
+            Message::Hello => transport.send(&Message::Welcome),
+            Message::Welcome => {}
+        }
+    }
+
+    //whoever is at this keyboard plays with the first player's keys, whichever side they're on
+    //(and nothing's pressed while typing into the console)
+    let mut local_input = 0;
+    if let (Some(keys), false) = (bindings.0.first(), console.open) {
+        if keyboard_input.pressed(keys.left) {
+            local_input -= 1;
+        }
+        if keyboard_input.pressed(keys.right) {
+            local_input += 1;
+        }
+    }
+
+    //don't try to catch up on more than a few ticks after a hitch
+    session.accumulator = (session.accumulator + time.delta_seconds).min(TICK_SECONDS * 8.0);
+    while session.accumulator >= TICK_SECONDS {
+        if !rollback.advance(local_input) {
+            break;
+        }
+        session.accumulator -= TICK_SECONDS;
+    }
+
+    if let Some(message) = rollback.input_message() {
+        transport.send(&message);
+    }
+    for (tick, checksum) in rollback.take_checksums() {
+        transport.send(&Message::Checksum { tick, checksum });
+    }
+
+    //the match is only really over once we've heard every one of the peer's inputs up to the end of it
+    if rollback.state.winner().is_some() && session.replay_path.is_some() {
+        if let Some(replay) = rollback.replay() {
+            let path = session.replay_path.take().unwrap();
+            match replay.save(&path) {
+                Ok(()) => println!("Saved the match to {}", path.display()),
+                Err(error) => {
+                    log::warn!("Couldn't save the match to {}: {}", path.display(), error)
+                }
+            }
+        }
+    }
+}
+
+/// Shows the simulation's latest state through the usual entities
+fn net_render_system(
+    session: Res<NetSession>,
+    mut scoreboard: ResMut<Scoreboard>,
+    mut paddle_query: Query<(&Paddle, &mut Transform)>,
+    mut ball_query: Query<(&Ball, &mut Transform)>,
+    mut brick_query: Query<(&NetBrick, &mut Draw)>,
+) {
+    let rollback = match &session.stage {
+        NetStage::Running(rollback) => rollback,
+        _ => return,
+    };
+    let state = &rollback.state;
+
+    for (paddle, mut transform) in &mut paddle_query.iter() {
+        if let Some(x) = state.paddle_x.get(paddle.player) {
+            *transform.translation_mut().x_mut() = *x;
+        }
+    }
+    for (_ball, mut transform) in &mut ball_query.iter() {
+        let translation = transform.translation_mut();
+        *translation.x_mut() = state.ball_position.x();
+        *translation.y_mut() = state.ball_position.y();
+    }
+    //bricks are only hidden rather than despawned, since a rollback can turn out a brick wasn't broken after all
+    for (net_brick, mut draw) in &mut brick_query.iter() {
+        draw.is_visible = state.brick_alive(&rollback.layout, net_brick.index);
+    }
+
+    for (player, player_score) in scoreboard.players.iter_mut().enumerate() {
+        player_score.score = state.scores[player];
+        player_score.lives = state.lives[player];
+    }
+}
+
+fn net_text_system(
+    localization: Res<Localization>,
+    session: Res<NetSession>,
+    mut query: Query<(&NetText, &mut Text)>,
+) {
+    //ports and ticks are left without separators, they're identifiers more than amounts
+    let value = match &session.stage {
+        NetStage::Lobby => localization.format(
+            "net.lobby",
+            &[
+                ("port", DEFAULT_PORT.to_string()),
+                ("address", session.address_input.clone()),
+                (
+                    "status",
+                    session
+                        .status
+                        .as_ref()
+                        .map_or("".to_string(), |status| localization.message(status)),
+                ),
+            ],
+        ),
+        NetStage::Hosting => {
+            localization.format("net.hosting", &[("port", DEFAULT_PORT.to_string())])
+        }
+        NetStage::Joining { .. } => {
+            localization.format("net.joining", &[("address", session.address_input.clone())])
+        }
+        NetStage::Running(rollback) => match (rollback.desync, rollback.state.winner()) {
+            (Some(tick), _) => localization.format("net.desync", &[("tick", tick.to_string())]),
+            (None, Some(winner)) => {
+                localization.format("net.winner", &[("player", (winner + 1).to_string())])
+            }
+            (None, None) => {
+                //we can be behind as well as ahead, once the peer's inputs run past the tick we're on
+                let confirmed_until = rollback.confirmed_tick().map_or(0, |tick| tick + 1);
+                let ahead = rollback.tick as i64 - confirmed_until as i64;
+                let gap = if ahead >= 0 {
+                    localization.plural("net.ticks_ahead", ahead as u64)
+                } else {
+                    localization.plural("net.ticks_behind", -ahead as u64)
+                };
+                localization.format(
+                    "net.running",
+                    &[("tick", rollback.tick.to_string()), ("gap", gap)],
+                )
+            }
+        },
+    };
+
+    for (_net_text, mut text) in &mut query.iter() {
+        text.value = value.clone();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::level_generator::SeededRng;
+    use super::*;
+
+    const TICKS: u32 = 600;
+
+    /// One direction of a made up network, which holds each packet back for a random while so they arrive late and out of order
+    struct Link {
+        in_flight: Vec<(u32, Vec<u8>)>,
+    }
+
+    impl Link {
+        fn send(&mut self, rng: &mut SeededRng, now: u32, message: &Message) {
+            let arrival = now + rng.range(1, 12);
+            self.in_flight.push((arrival, message.encode()));
+        }
+
+        fn receive(&mut self, now: u32, rollback: &mut Rollback) {
+            let (arrived, in_flight): (Vec<_>, Vec<_>) = self
+                .in_flight
+                .drain(..)
+                .partition(|(arrival, _)| *arrival <= now);
+            self.in_flight = in_flight;
+            for (_arrival, bytes) in arrived {
+                match Message::decode(&bytes) {
+                    Some(Message::Inputs { last_tick, inputs }) => {
+                        rollback.receive_inputs(last_tick, &inputs)
+                    }
+                    Some(Message::Checksum { tick, checksum }) => {
+                        rollback.receive_checksum(tick, checksum)
+                    }
+                    _ => {}
+                }
+            }
+        }
+    }
+
+    fn exchange(rng: &mut SeededRng, now: u32, from: &mut Rollback, link: &mut Link) {
+        if let Some(message) = from.input_message() {
+            link.send(rng, now, &message);
+        }
+        for (tick, checksum) in from.take_checksums() {
+            link.send(rng, now, &Message::Checksum { tick, checksum });
+        }
+    }
+
+    #[test]
+    fn peers_agree_despite_late_and_reordered_inputs() {
+        let mut rng = SeededRng::new(30);
+        let mut peers = [Rollback::new(0, true), Rollback::new(1, true)];
+        let mut links = [
+            Link {
+                in_flight: Vec::new(),
+            },
+            Link {
+                in_flight: Vec::new(),
+            },
+        ];
+        let mut held = [0i8; PLAYER_COUNT];
+
+        //keep going until both have played every tick, then a while longer for the last packets to land
+        let mut now = 0;
+        let mut settling = 0;
+        while settling < 40 {
+            for player in 0..PLAYER_COUNT {
+                let other = (player + 1) % PLAYER_COUNT;
+                links[other].receive(now, &mut peers[player]);
+                if peers[player].tick < TICKS {
+                    if rng.chance(0.1) {
+                        held[player] = rng.range(0, 3) as i8 - 1;
+                    }
+                    peers[player].advance(held[player]);
+                }
+                exchange(&mut rng, now, &mut peers[player], &mut links[player]);
+            }
+            if peers.iter().all(|peer| peer.tick == TICKS) {
+                settling += 1;
+            }
+            now += 1;
+            assert!(now < TICKS * 4, "the peers stopped making progress");
+        }
+
+        let [first, second] = &peers;
+        assert_eq!(first.tick, TICKS);
+        assert_eq!(second.tick, TICKS);
+        assert_eq!(first.state, second.state);
+        assert_eq!(first.state.checksum(), second.state.checksum());
+        assert!(!first.local_checksums.is_empty());
+        assert_eq!(first.local_checksums, second.local_checksums);
+        assert_eq!(first.desync, None);
+        assert_eq!(second.desync, None);
+
+        //what's left of the inputs is only what could still need resending, and the recordings have all of them
+        assert!(first.local_inputs.len() <= INPUT_WINDOW);
+        assert!(second.remote_inputs.len() <= INPUT_WINDOW);
+        let replay = first.replay().unwrap();
+        assert_eq!(replay.inputs.len(), TICKS as usize);
+        assert_eq!(Some(replay), second.replay());
+    }
+
+    #[test]
+    fn messages_survive_encoding() {
+        let messages = [
+            Message::Hello,
+            Message::Welcome,
+            Message::Inputs {
+                last_tick: 40,
+                inputs: vec![-1, 0, 1, 1],
+            },
+            Message::Checksum {
+                tick: 120,
+                checksum: 0x0123_4567_89ab_cdef,
+            },
+        ];
+        for message in messages.iter() {
+            assert_eq!(Message::decode(&message.encode()).as_ref(), Some(message));
+        }
+
+        let inputs = Message::Inputs {
+            last_tick: 40,
+            inputs: vec![1; 4],
+        }
+        .encode();
+        //cut short, or claiming inputs from before the first tick
+        assert_eq!(Message::decode(&inputs[..inputs.len() - 1]), None);
+        let too_many = Message::Inputs {
+            last_tick: 2,
+            inputs: vec![1; 4],
+        };
+        assert_eq!(Message::decode(&too_many.encode()), None);
+        assert_eq!(Message::decode(&[]), None);
+        assert_eq!(Message::decode(&[9]), None);
+    }
+
+    #[test]
+    fn peers_play_over_loopback() {
+        let loopback = Transport::address_reaching(IpAddr::V4(Ipv4Addr::LOCALHOST), 0);
+        let mut host = Transport::bind(loopback).unwrap();
+        let mut joiner = Transport::bind(loopback).unwrap();
+        //only the loopback interface is listened on
+        assert!(host.socket.local_addr().unwrap().ip().is_loopback());
+
+        //keeps at it until the other side has heard, as the real thing does, in case a packet goes missing
+        let wait_for = |transport: &Transport, resend: &dyn Fn()| {
+            for _attempt in 0..200 {
+                resend();
+                let messages = transport.receive();
+                if !messages.is_empty() {
+                    return messages;
+                }
+                std::thread::sleep(std::time::Duration::from_millis(5));
+            }
+            panic!("nothing arrived over loopback");
+        };
+
+        joiner.peer = Some(host.socket.local_addr().unwrap());
+        let hello = wait_for(&host, &|| joiner.send(&Message::Hello));
+        let (from, message) = &hello[0];
+        assert_eq!(*message, Message::Hello);
+        assert_eq!(*from, joiner.socket.local_addr().unwrap());
+        host.peer = Some(*from);
+        let welcome = wait_for(&joiner, &|| host.send(&Message::Welcome));
+        assert!(welcome
+            .iter()
+            .any(|(_from, message)| *message == Message::Welcome));
+
+        //each side holds its own direction the whole time
+        let mut peers = [
+            (Rollback::new(0, false), host, -1),
+            (Rollback::new(1, false), joiner, 1),
+        ];
+        for _attempt in 0..2000 {
+            for (rollback, transport, input) in peers.iter_mut() {
+                for (_from, message) in transport.receive() {
+                    if let Message::Inputs { last_tick, inputs } = message {
+                        rollback.receive_inputs(last_tick, &inputs);
+                    }
+                }
+                if rollback.tick < 120 {
+                    rollback.advance(*input);
+                }
+                if let Some(message) = rollback.input_message() {
+                    transport.send(&message);
+                }
+            }
+            let done = peers.iter().all(|(rollback, _, _)| {
+                rollback.tick == 120 && rollback.confirmed_tick() == Some(119)
+            });
+            if done {
+                break;
+            }
+            std::thread::sleep(std::time::Duration::from_millis(1));
+        }
+
+        let [(first, _, _), (second, _, _)] = &peers;
+        assert_eq!(first.confirmed_tick(), Some(119));
+        assert_eq!(second.confirmed_tick(), Some(119));
+        assert_eq!(first.state, second.state);
+        assert_eq!(first.remote_input(119), 1);
+        assert_eq!(second.remote_input(119), -1);
+    }
+}
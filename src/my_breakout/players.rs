@@ -0,0 +1,76 @@
+//Everything that differs from one player to the next: who (or what) is moving their paddle, which keys they use,
+//which side of the arena they defend and how they're doing
+
+use bevy::prelude::*;
+
+use super::ai::PaddleInput;
+
+pub const STARTING_LIVES: u32 = 3;
+
+/// The keys a player moves their paddle with
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct PaddleBindings {
+    pub left: KeyCode,
+    pub right: KeyCode,
+}
+
+/// Resource with each player's bindings, indexed by player id
+pub struct PlayerBindings(pub Vec<PaddleBindings>);
+
+impl Default for PlayerBindings {
+    fn default() -> Self {
+        PlayerBindings(vec![
+            PaddleBindings {
+                left: KeyCode::Left,
+                right: KeyCode::Right,
+            },
+            PaddleBindings {
+                left: KeyCode::A,
+                right: KeyCode::D,
+            },
+        ])
+    }
+}
+
+/// Resource with how each player's paddle is moved, indexed by player id. Its length is how many players there are
+pub struct Players(pub Vec<PaddleInput>);
+
+impl Players {
+    pub fn count(&self) -> usize {
+        self.0.len()
+    }
+
+    pub fn has_keyboard_player(&self) -> bool {
+        self.0.iter().any(|input| *input == PaddleInput::Keyboard)
+    }
+}
+
+/// How a single player is doing
+pub struct PlayerScore {
+    pub score: usize,
+    pub lives: u32,
+}
+
+impl Default for PlayerScore {
+    fn default() -> Self {
+        PlayerScore {
+            score: 0,
+            lives: STARTING_LIVES,
+        }
+    }
+}
+
+pub fn player_color(player: usize) -> Color {
+    match player {
+        0 => Color::rgb(0.2, 0.2, 0.8),
+        _ => Color::rgb(0.2, 0.6, 0.2),
+    }
+}
+
+/// Player 0 defends the bottom of the arena and player 1 the top
+pub fn paddle_y(player: usize) -> f32 {
+    match player {
+        0 => -215.0,
+        _ => 215.0,
+    }
+}
@@ -0,0 +1,128 @@
+//What's kept between runs of the game: the best score for each kind of game and which achievements have been unlocked.
+//It all lives in the one save file, written out whenever something in it changes
+
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    fs, io,
+    path::{Path, PathBuf},
+};
+
+use super::level::{LevelProgress, LevelSource};
+use super::{GameEvent, Scoreboard};
+
+const SAVE_PATH: &str = "save/progress.ron";
+
+/// Resource with everything saved between runs
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct Progress {
+    /// The best single player score for each kind of game, keyed by `high_score_key`
+    #[serde(default)]
+    pub high_scores: BTreeMap<String, usize>,
+    /// The ids of every achievement unlocked so far
+    #[serde(default)]
+    pub achievements: BTreeSet<String>,
+    #[serde(skip)]
+    path: PathBuf,
+}
+
+impl Progress {
+    /// Reads the save file, starting afresh if there isn't one yet
+    pub fn load(path: &Path) -> io::Result<Progress> {
+        let mut progress = if path.exists() {
+            let contents = fs::read_to_string(path)?;
+            ron::de::from_str(&contents)
+                .map_err(|error| io::Error::new(io::ErrorKind::InvalidData, error.to_string()))?
+        } else {
+            Progress::default()
+        };
+        progress.path = path.to_path_buf();
+        Ok(progress)
+    }
+
+    /// Writes everything back to the file it was loaded from, creating its directory if needed
+    pub fn save(&self) -> io::Result<()> {
+        let contents = ron::ser::to_string_pretty(self, ron::ser::PrettyConfig::default())
+            .map_err(|error| io::Error::new(io::ErrorKind::InvalidData, error.to_string()))?;
+        if let Some(directory) = self.path.parent() {
+            fs::create_dir_all(directory)?;
+        }
+        fs::write(&self.path, contents)
+    }
+
+    /// Saves, only complaining if it doesn't work since losing the save isn't worth stopping the game over
+    pub fn save_or_warn(&self) {
+        if let Err(error) = self.save() {
+            log::warn!(
+                "Couldn't save progress to {}: {}",
+                self.path.display(),
+                error
+            );
+        }
+    }
+
+    /// Records the score if it's the best yet for this kind of game, returning whether it was
+    pub fn record_score(&mut self, key: &str, score: usize) -> bool {
+        let best = self.high_scores.entry(key.to_string()).or_insert(0);
+        if score > *best {
+            *best = score;
+            true
+        } else {
+            false
+        }
+    }
+}
+
+/// Which high score table a kind of game goes on. Versus has no single player score to keep
+pub fn high_score_key(source: &LevelSource) -> Option<&'static str> {
+    match source {
+        LevelSource::Classic => Some("classic"),
+        LevelSource::Endless { .. } => Some("endless"),
+        LevelSource::Custom(_) => Some("custom"),
+        LevelSource::Versus => None,
+    }
+}
+
+pub struct ProgressPlugin;
+
+impl Plugin for ProgressPlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        //progress already put in place (say by a test, saving somewhere out of the way) is kept
+        if app.resources().get::<Progress>().is_none() {
+            let path = PathBuf::from(SAVE_PATH);
+            let progress = Progress::load(&path).unwrap_or_else(|error| {
+                //keep the broken file around rather than overwriting it with a blank one
+                log::warn!("Couldn't load {}: {}", path.display(), error);
+                Progress {
+                    path: path.with_extension("ron.new"),
+                    ..Default::default()
+                }
+            });
+            app.add_resource(progress);
+        }
+        app.add_system(high_score_system.system());
+    }
+}
+
+/// Puts the score on the high score table once a single player game someone was playing ends
+fn high_score_system(
+    mut game_event_reader: Local<EventReader<GameEvent>>,
+    game_events: Res<Events<GameEvent>>,
+    scoreboard: Res<Scoreboard>,
+    level_progress: Res<LevelProgress>,
+    mut progress: ResMut<Progress>,
+) {
+    let ended = game_event_reader
+        .iter(&game_events)
+        .any(|event| matches!(event, GameEvent::GameEnded { .. }));
+    if !ended || scoreboard.players.len() != 1 {
+        return;
+    }
+
+    if let Some(key) = high_score_key(&level_progress.source) {
+        if progress.record_score(key, scoreboard.players[0].score) {
+            progress.save_or_warn();
+        }
+    }
+}
@@ -0,0 +1,283 @@
+//The ball handed over to rapier (through bevy_rapier) instead of being moved and collided by our own code
+//Only built with the "rapier" feature. Walls and bricks become static bodies, paddles kinematic ones that follow wherever the
+//keyboard or the computer moved them to (as do bricks following a path), and the ball a dynamic body that bounces without losing any speed.
+//Rapier's contact events are then turned back into the same lost lives, brick hits and speed ups the regular collision system does.
+//Rapier steps the world in the update stage, so whatever the game changed is handed over in a stage before it, and where
+//rapier moved things and what they ran into are read back in stages after it
+
+use bevy::{app::stage, prelude::*};
+use bevy_rapier2d::{
+    physics::{EventQueue, RapierConfiguration, RapierPhysicsPlugin, RigidBodyHandleComponent},
+    rapier::{
+        dynamics::{JointSet, RigidBodyBuilder, RigidBodyHandle, RigidBodySet},
+        geometry::{ColliderBuilder, ColliderSet, ContactEvent},
+        na::{Isometry2, Vector2},
+    },
+};
+use std::collections::HashMap;
+
+use super::level::BrickMovement;
+use super::theme::Accessibility;
+use super::{
+    hit_brick, hit_paddle, lose_life, serve_ball, Ball, Brick, Collider, GameEvent, GameState,
+    Paddle, RestartGame, Scoreboard, BRICK_SPEED_UP,
+};
+
+const PHYSICS_PUSH_STAGE: &str = "physics_push";
+const PHYSICS_READBACK_STAGE: &str = "physics_readback";
+const PHYSICS_CONTACT_STAGE: &str = "physics_contact";
+
+pub struct RapierBackendPlugin;
+
+impl Plugin for RapierBackendPlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        app.add_plugin(RapierPhysicsPlugin)
+            //it's a top down view as far as physics goes, so nothing falls
+            .add_resource(RapierConfiguration {
+                gravity: Vector2::zeros(),
+                ..Default::default()
+            })
+            .init_resource::<PhysicsBodies>()
+            .add_stage_before(stage::UPDATE, PHYSICS_PUSH_STAGE)
+            .add_stage_after(stage::UPDATE, PHYSICS_READBACK_STAGE)
+            .add_stage_after(PHYSICS_READBACK_STAGE, PHYSICS_CONTACT_STAGE)
+            .add_system(attach_body_system.system())
+            .add_system(track_body_system.system())
+            //bricks are only really gone once the stage that despawned them is over
+            .add_system_to_stage(stage::POST_UPDATE, body_removal_system.system())
+            .add_system_to_stage(PHYSICS_PUSH_STAGE, body_push_system.system())
+            .add_system_to_stage(PHYSICS_READBACK_STAGE, ball_readback_system.system())
+            .add_system_to_stage(PHYSICS_CONTACT_STAGE, contact_system.system());
+    }
+}
+
+//Marks an entity that's been given a body, so it only gets one
+struct PhysicsBody;
+
+/// Resource to get from a rapier body back to the entity it belongs to, and from an entity to its body
+#[derive(Default)]
+struct PhysicsBodies {
+    entities: HashMap<RigidBodyHandle, Entity>,
+    bodies: HashMap<Entity, RigidBodyHandle>,
+}
+
+/// Gives every ball and collider a body once it's spawned
+fn attach_body_system(
+    mut commands: Commands,
+    mut ball_query: Query<Without<PhysicsBody, (Entity, &Ball, &Transform, &Sprite)>>,
+    mut collider_query: Query<Without<PhysicsBody, (Entity, &Collider, &Transform, &Sprite)>>,
+    mut paddle_query: Query<&Paddle>,
+    mut movement_query: Query<&BrickMovement>,
+) {
+    for (entity, ball, transform, sprite) in &mut ball_query.iter() {
+        let position = transform.translation();
+        let body = RigidBodyBuilder::new_dynamic()
+            .translation(position.x(), position.y())
+            .linvel(ball.velocity.x(), ball.velocity.y());
+        //a perfectly elastic ball with nothing to slow it down, like the ball we move ourselves
+        let collider = ColliderBuilder::ball(sprite.size.x() / 2.0)
+            .restitution(1.0)
+            .friction(0.0);
+        commands.insert(entity, (body, collider, PhysicsBody));
+    }
+
+    for (entity, _collider, transform, sprite) in &mut collider_query.iter() {
+        let position = transform.translation();
+        let moves = paddle_query.get::<Paddle>(entity).is_ok()
+            || movement_query.get::<BrickMovement>(entity).is_ok();
+        let body = if moves {
+            RigidBodyBuilder::new_kinematic()
+        } else {
+            RigidBodyBuilder::new_static()
+        }
+        .translation(position.x(), position.y());
+        let collider = ColliderBuilder::cuboid(sprite.size.x() / 2.0, sprite.size.y() / 2.0)
+            .restitution(1.0)
+            .friction(0.0);
+        commands.insert(entity, (body, collider, PhysicsBody));
+    }
+}
+
+/// Keeps track of which body is whose
+fn track_body_system(
+    mut physics_bodies: ResMut<PhysicsBodies>,
+    mut added_query: Query<(Entity, Added<RigidBodyHandleComponent>)>,
+) {
+    for (entity, handle) in &mut added_query.iter() {
+        physics_bodies.entities.insert(handle.handle(), entity);
+        physics_bodies.bodies.insert(entity, handle.handle());
+    }
+}
+
+/// Gets rid of the bodies of despawned bricks, so the ball doesn't carry on bouncing off of them
+fn body_removal_system(
+    mut physics_bodies: ResMut<PhysicsBodies>,
+    mut bodies: ResMut<RigidBodySet>,
+    mut colliders: ResMut<ColliderSet>,
+    mut joints: ResMut<JointSet>,
+    removed_query: Query<&Collider>,
+) {
+    for entity in removed_query.removed::<Collider>() {
+        if let Some(handle) = physics_bodies.bodies.remove(entity) {
+            physics_bodies.entities.remove(&handle);
+            bodies.remove(handle, &mut colliders, &mut joints);
+        }
+    }
+}
+
+/// Copies where rapier moved the ball to, and how fast it's going, back onto the ball
+fn ball_readback_system(
+    game_state: Res<GameState>,
+    accessibility: Res<Accessibility>,
+    bodies: Res<RigidBodySet>,
+    mut ball_query: Query<(&mut Ball, &mut Transform, &RigidBodyHandleComponent)>,
+) {
+    //while the game is paused the body is held still, but the ball keeps the velocity to carry on with
+    if !game_state.is_simulating() {
+        return;
+    }
+
+    for (mut ball, mut transform, handle) in &mut ball_query.iter() {
+        if let Some(body) = bodies.get(handle.handle()) {
+            let velocity = body.linvel();
+            //rapier has the ball going at the slowed down speed, the ball keeps what it'd be going at full speed
+            ball.velocity = Vec3::new(velocity.x, velocity.y, 0.0) / accessibility.game_speed;
+            let position = body.position().translation;
+            let translation = transform.translation_mut();
+            *translation.x_mut() = position.x;
+            *translation.y_mut() = position.y;
+        }
+    }
+}
+
+/// Turns the contacts rapier found into the game's rules
+fn contact_system(
+    mut commands: Commands,
+    events: Res<EventQueue>,
+    physics_bodies: Res<PhysicsBodies>,
+    colliders: Res<ColliderSet>,
+    mut scoreboard: ResMut<Scoreboard>,
+    mut game_state: ResMut<GameState>,
+    mut restart_events: ResMut<Events<RestartGame>>,
+    mut game_events: ResMut<Events<GameEvent>>,
+    mut ball_query: Query<&mut Ball>,
+    mut ball_transform_query: Query<With<Ball, &mut Transform>>,
+    mut collider_query: Query<&Collider>,
+    mut brick_query: Query<&mut Brick>,
+    mut paddle_query: Query<&Paddle>,
+) {
+    let entity_of = |handle| {
+        colliders
+            .get(handle)
+            .and_then(|collider| physics_bodies.entities.get(&collider.parent()))
+            .copied()
+    };
+
+    while let Ok(event) = events.contact_events.try_recv() {
+        //only the moment they touch matters, the same as the regular collision only acting when the ball is heading in
+        let (first, second) = match event {
+            ContactEvent::Started(first, second) => (first, second),
+            ContactEvent::Stopped(..) => continue,
+        };
+        if !game_state.is_simulating() {
+            continue;
+        }
+        let (first, second) = match (entity_of(first), entity_of(second)) {
+            (Some(first), Some(second)) => (first, second),
+            _ => continue,
+        };
+        //work out which one is the ball
+        let (ball_entity, other) = if ball_query.get::<Ball>(first).is_ok() {
+            (first, second)
+        } else if ball_query.get::<Ball>(second).is_ok() {
+            (second, first)
+        } else {
+            continue;
+        };
+        let collider = match collider_query.get::<Collider>(other) {
+            Ok(collider) => *collider,
+            Err(_) => continue,
+        };
+
+        let mut ball = ball_query.get_mut::<Ball>(ball_entity).unwrap();
+        match collider {
+            Collider::Goal { player } => {
+                lose_life(
+                    &mut scoreboard,
+                    &mut game_state,
+                    &mut restart_events,
+                    &mut game_events,
+                    player,
+                );
+                let player_count = scoreboard.players.len();
+                let mut transform = ball_transform_query
+                    .get_mut::<Transform>(ball_entity)
+                    .unwrap();
+                serve_ball(&mut ball, &mut transform, player_count, player);
+            }
+            Collider::Scorable => {
+                let mut brick = brick_query.get_mut::<Brick>(other).ok();
+                let broken = hit_brick(
+                    &mut commands,
+                    &mut scoreboard,
+                    &mut game_events,
+                    other,
+                    brick.as_deref_mut(),
+                    ball.last_hit_by,
+                );
+                if broken {
+                    ball.velocity *= BRICK_SPEED_UP;
+                }
+            }
+            Collider::Solid => {
+                //remember who hit the ball so they get the points for whatever it breaks
+                if let Ok(paddle) = paddle_query.get::<Paddle>(other) {
+                    ball.last_hit_by = paddle.player;
+                    hit_paddle(&mut scoreboard, &mut game_events, paddle.player);
+                }
+            }
+        }
+    }
+}
+
+/// Hands anything the game changed back to rapier before it steps: the ball (served, sped up or frozen) and wherever the
+/// paddles and moving bricks were moved to
+fn body_push_system(
+    game_state: Res<GameState>,
+    accessibility: Res<Accessibility>,
+    mut bodies: ResMut<RigidBodySet>,
+    mut ball_query: Query<(&Ball, &Transform, &RigidBodyHandleComponent)>,
+    mut paddle_query: Query<(&Paddle, &Transform, &RigidBodyHandleComponent)>,
+    mut moving_brick_query: Query<(&BrickMovement, &Transform, &RigidBodyHandleComponent)>,
+) {
+    for (ball, transform, handle) in &mut ball_query.iter() {
+        if let Some(mut body) = bodies.get_mut(handle.handle()) {
+            //the ball was read back from the body after the last step, so it's only somewhere else if the game put it there
+            let position = transform.translation();
+            let body_position = body.position().translation;
+            if body_position.x != position.x() || body_position.y != position.y() {
+                body.set_position(Isometry2::translation(position.x(), position.y()));
+            }
+            //the ball stays put while the game is paused on a screen
+            let velocity = if game_state.is_simulating() {
+                ball.velocity * accessibility.game_speed
+            } else {
+                Vec3::zero()
+            };
+            body.set_linvel(Vector2::new(velocity.x(), velocity.y()), true);
+        }
+    }
+
+    let mut kinematic = Vec::new();
+    for (_paddle, transform, handle) in &mut paddle_query.iter() {
+        kinematic.push((transform.translation(), handle.handle()));
+    }
+    for (_movement, transform, handle) in &mut moving_brick_query.iter() {
+        kinematic.push((transform.translation(), handle.handle()));
+    }
+    for (position, handle) in kinematic {
+        if let Some(mut body) = bodies.get_mut(handle) {
+            body.set_next_kinematic_position(Isometry2::translation(position.x(), position.y()));
+        }
+    }
+}
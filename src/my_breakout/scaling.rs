@@ -0,0 +1,292 @@
+//Keeps the arena the same shape whatever size the window is. Everything in the game is laid out in a fixed logical arena
+//(the 900x600 between the walls, plus the walls), so rather than moving anything the camera is zoomed to fit the arena in
+//the window and bars cover whatever's left over on the sides or top and bottom. UI is laid out in window pixels instead,
+//so each UI node's position, size and font size get scaled from what it was spawned with, and shifted in from the bars.
+//F11 toggles fullscreen
+
+use bevy::{prelude::*, winit::WinitWindows};
+use winit::window::Fullscreen;
+
+use super::console::ConsoleState;
+use super::headless::Headless;
+use super::{BOUNDS, WALL_THICKNESS};
+
+const FULLSCREEN_KEY: KeyCode = KeyCode::F11;
+//big enough to cover past the edges of any window the arena could be fitted into
+const BAR_LENGTH: f32 = 10_000.0;
+//over everything in the arena, even the debug overlay
+const BAR_Z: f32 = 20.0;
+
+/// The logical size of the arena, walls included
+pub fn arena_size() -> Vec2 {
+    Vec2::new(
+        BOUNDS.0 * 2.0 + WALL_THICKNESS,
+        BOUNDS.1 * 2.0 + WALL_THICKNESS,
+    )
+}
+
+/// How the arena is fitted into the window
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ScalingMode {
+    /// Scaled to fit and centered, with bars over the rest of the window
+    Letterbox,
+    /// A logical unit is a pixel, however big the window is
+    Pixels,
+}
+
+/// Resource with how the arena currently fits in the window
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ArenaScale {
+    pub mode: ScalingMode,
+    /// Window pixels per logical unit
+    pub scale: f32,
+    /// The width of the bars at the sides and the height of the ones at the top and bottom, in window pixels
+    pub margin: Vec2,
+}
+
+impl Default for ArenaScale {
+    fn default() -> Self {
+        ArenaScale {
+            mode: ScalingMode::Letterbox,
+            scale: 1.0,
+            margin: Vec2::zero(),
+        }
+    }
+}
+
+impl ArenaScale {
+    fn fit(mode: ScalingMode, window_size: Vec2) -> Self {
+        let arena = arena_size();
+        let scale = match mode {
+            ScalingMode::Letterbox => {
+                (window_size.x() / arena.x()).min(window_size.y() / arena.y())
+            }
+            ScalingMode::Pixels => 1.0,
+        };
+        ArenaScale {
+            mode,
+            scale,
+            margin: ((window_size - arena * scale) / 2.0).max(Vec2::zero()),
+        }
+    }
+
+    /// Where in the arena a window position (like the cursor's, from the bottom left of the window) is
+    pub fn window_to_arena(&self, position: Vec2, window_size: Vec2) -> Vec2 {
+        (position - window_size / 2.0) / self.scale
+    }
+}
+
+/// Marks the camera looking at the arena, as opposed to the UI one
+pub struct ArenaCamera;
+
+pub struct ScalingPlugin;
+
+impl Plugin for ScalingPlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        app.init_resource::<ArenaScale>()
+            .add_startup_system(letterbox_setup.system())
+            .add_system(arena_scale_system.system())
+            .add_system(ui_scale_system.system());
+        //there's no winit window to make fullscreen when headless
+        if app.resources().get::<Headless>().is_none() {
+            app.add_system(fullscreen_system.system());
+        }
+    }
+}
+
+//The bars are sprites just outside of the arena, so the camera zooming in and out keeps them lined up with it
+fn letterbox_setup(mut commands: Commands, mut materials: ResMut<Assets<ColorMaterial>>) {
+    let material = materials.add(Color::rgb(0.1, 0.1, 0.1).into());
+    let arena = arena_size();
+    let sides = [
+        (Vec2::new(-1.0, 0.0), Vec2::new(BAR_LENGTH, BAR_LENGTH)),
+        (Vec2::new(1.0, 0.0), Vec2::new(BAR_LENGTH, BAR_LENGTH)),
+        (Vec2::new(0.0, -1.0), Vec2::new(arena.x(), BAR_LENGTH)),
+        (Vec2::new(0.0, 1.0), Vec2::new(arena.x(), BAR_LENGTH)),
+    ];
+    for (side, size) in sides.iter() {
+        //each bar's inner edge sits on the edge of the arena
+        let position = *side * (arena + *size) / 2.0;
+        commands.spawn(SpriteComponents {
+            material,
+            transform: Transform::from_translation(position.extend(BAR_Z)),
+            sprite: Sprite::new(*size),
+            ..Default::default()
+        });
+    }
+}
+
+fn arena_scale_system(
+    windows: Res<Windows>,
+    mut arena_scale: ResMut<ArenaScale>,
+    mut camera_query: Query<With<ArenaCamera, &mut Transform>>,
+) {
+    let window = match windows.get_primary() {
+        Some(window) => window,
+        None => return,
+    };
+    let fitted = ArenaScale::fit(
+        arena_scale.mode,
+        Vec2::new(window.width as f32, window.height as f32),
+    );
+    if fitted == *arena_scale {
+        return;
+    }
+    *arena_scale = fitted;
+
+    //the camera's scale is how much of the world it takes in, so it's the other way around to the arena's.
+    //Its translation has to stay put, that's what keeps it far enough back to see everything, and only X and Y get scaled,
+    //as scaling Z would move the near and far planes and cut off whatever's in the arena when zoomed out
+    for mut transform in &mut camera_query.iter() {
+        let translation = transform.translation();
+        let scale = 1.0 / fitted.scale;
+        *transform = Transform::new(Mat4::from_scale_rotation_translation(
+            Vec3::new(scale, scale, 1.0),
+            Quat::identity(),
+            translation,
+        ));
+    }
+}
+
+/// Component with how a UI node was laid out when spawned, which is what it gets scaled from
+struct UiLayout {
+    position: Rect<Val>,
+    size: Size<Val>,
+    font_size: Option<f32>,
+    applied: Option<ArenaScale>,
+}
+
+fn ui_scale_system(
+    mut commands: Commands,
+    arena_scale: Res<ArenaScale>,
+    mut new_query: Query<Without<UiLayout, (Entity, &Style, Option<&Text>)>>,
+    mut layout_query: Query<(&mut UiLayout, &mut Style, Option<&mut Text>)>,
+) {
+    for (entity, style, text) in &mut new_query.iter() {
+        commands.insert_one(
+            entity,
+            UiLayout {
+                position: style.position,
+                size: style.size,
+                font_size: text.map(|text| text.style.font_size),
+                applied: None,
+            },
+        );
+    }
+
+    for (mut layout, mut style, text) in &mut layout_query.iter() {
+        if layout.applied == Some(*arena_scale) {
+            continue;
+        }
+        layout.applied = Some(*arena_scale);
+
+        let scale = arena_scale.scale;
+        let margin = arena_scale.margin;
+        let place = |value: Val, margin: f32| match value {
+            Val::Px(pixels) => Val::Px(pixels * scale + margin),
+            other => other,
+        };
+        let resize = |value: Val| match value {
+            Val::Px(pixels) => Val::Px(pixels * scale),
+            other => other,
+        };
+        style.position = Rect {
+            left: place(layout.position.left, margin.x()),
+            right: place(layout.position.right, margin.x()),
+            top: place(layout.position.top, margin.y()),
+            bottom: place(layout.position.bottom, margin.y()),
+        };
+        style.size = Size::new(resize(layout.size.width), resize(layout.size.height));
+        if let (Some(mut text), Some(font_size)) = (text, layout.font_size) {
+            text.style.font_size = font_size * scale;
+        }
+    }
+}
+
+fn fullscreen_system(
+    keyboard_input: Res<Input<KeyCode>>,
+    console: Res<ConsoleState>,
+    windows: Res<Windows>,
+    winit_windows: Res<WinitWindows>,
+) {
+    if console.open || !keyboard_input.just_pressed(FULLSCREEN_KEY) {
+        return;
+    }
+    //bevy only sets the window mode as the window opens, so changing it afterwards goes straight to winit
+    let window = windows
+        .get_primary()
+        .and_then(|window| winit_windows.get_window(window.id));
+    if let Some(window) = window {
+        if window.fullscreen().is_some() {
+            window.set_fullscreen(None);
+        } else {
+            window.set_fullscreen(Some(Fullscreen::Borderless(window.current_monitor())));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn letterbox_fits_the_arena_and_centers_it() {
+        let arena = arena_size();
+        //twice as wide as the arena, so it's the height that limits it
+        let wide = ArenaScale::fit(
+            ScalingMode::Letterbox,
+            Vec2::new(arena.x() * 2.0, arena.y()),
+        );
+        assert_eq!(wide.scale, 1.0);
+        assert_eq!(wide.margin, Vec2::new(arena.x() / 2.0, 0.0));
+
+        let tall = ArenaScale::fit(
+            ScalingMode::Letterbox,
+            Vec2::new(arena.x(), arena.y() * 2.0),
+        );
+        assert_eq!(tall.scale, 1.0);
+        assert_eq!(tall.margin, Vec2::new(0.0, arena.y() / 2.0));
+
+        let doubled = ArenaScale::fit(ScalingMode::Letterbox, arena * 2.0);
+        assert_eq!(doubled.scale, 2.0);
+        assert_eq!(doubled.margin, Vec2::zero());
+
+        let halved = ArenaScale::fit(ScalingMode::Letterbox, arena / 2.0);
+        assert_eq!(halved.scale, 0.5);
+        assert_eq!(halved.margin, Vec2::zero());
+    }
+
+    #[test]
+    fn pixels_keep_one_unit_a_pixel() {
+        let arena = arena_size();
+        let bigger = ArenaScale::fit(ScalingMode::Pixels, arena + Vec2::new(100.0, 40.0));
+        assert_eq!(bigger.scale, 1.0);
+        assert_eq!(bigger.margin, Vec2::new(50.0, 20.0));
+
+        //a window smaller than the arena just cuts it off, there's nothing to put bars over
+        let smaller = ArenaScale::fit(ScalingMode::Pixels, arena - Vec2::new(100.0, 40.0));
+        assert_eq!(smaller.scale, 1.0);
+        assert_eq!(smaller.margin, Vec2::zero());
+    }
+
+    #[test]
+    fn window_positions_map_into_the_arena() {
+        let arena = arena_size();
+        let window_size = Vec2::new(arena.x() * 3.0, arena.y() * 2.0);
+        let fitted = ArenaScale::fit(ScalingMode::Letterbox, window_size);
+        assert_eq!(fitted.scale, 2.0);
+
+        assert_eq!(
+            fitted.window_to_arena(window_size / 2.0, window_size),
+            Vec2::zero()
+        );
+        //the arena's top right corner is in from the right hand bar
+        let top_right = Vec2::new(window_size.x() - fitted.margin.x(), window_size.y());
+        assert_eq!(fitted.window_to_arena(top_right, window_size), arena / 2.0);
+        //and whatever's under the bars is outside of it
+        assert_eq!(
+            fitted.window_to_arena(Vec2::zero(), window_size),
+            Vec2::new(-arena.x() * 0.75, -arena.y() / 2.0)
+        );
+    }
+}
@@ -0,0 +1,205 @@
+//A deterministic, fixed timestep version of the versus game
+//Everything here is plain data stepped by a plain function, with no frame times or entity iteration order involved,
+//so the same starting state and inputs always lead to bit for bit the same result. Online play relies on this both to keep
+//two peers in step and to re-simulate past ticks when a late input turns out to be different than predicted
+
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::{fs, io, path::Path};
+
+use super::level::LevelLayout;
+use super::players::paddle_y;
+use super::{
+    serve, server, BALL_SIZE, BOUNDS, BRICK_SPEED_UP, PADDLE_SIZE, PADDLE_SPEED, WALL_THICKNESS,
+};
+use crate::geometry::{circle_aabb, Aabb};
+use crate::vec3_extension::Vec2Ext;
+
+pub const TICK_SECONDS: f32 = 1.0 / 60.0;
+pub const PLAYER_COUNT: usize = 2;
+
+/// One tick's input for every player: -1 (left), 0 (still) or 1 (right)
+pub type TickInputs = [i8; PLAYER_COUNT];
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct SimulationState {
+    pub paddle_x: [f32; PLAYER_COUNT],
+    pub ball_position: Vec2,
+    pub ball_velocity: Vec2,
+    pub last_hit_by: usize,
+    /// Hits left on each brick of the layout, indexed the same as the layout's bricks
+    pub brick_hits: Vec<u32>,
+    pub scores: [usize; PLAYER_COUNT],
+    pub lives: [u32; PLAYER_COUNT],
+}
+
+impl SimulationState {
+    pub fn new(layout: &LevelLayout, lives: u32) -> Self {
+        let (ball_position, ball_velocity) = serve(PLAYER_COUNT, 0);
+        SimulationState {
+            paddle_x: [0.0; PLAYER_COUNT],
+            ball_position,
+            ball_velocity: ball_velocity.truncate(),
+            last_hit_by: server(PLAYER_COUNT, 0),
+            brick_hits: layout
+                .bricks
+                .iter()
+                .map(|brick| brick.brick_type.hits())
+                .collect(),
+            scores: [0; PLAYER_COUNT],
+            lives: [lives; PLAYER_COUNT],
+        }
+    }
+
+    pub fn brick_alive(&self, layout: &LevelLayout, index: usize) -> bool {
+        !layout.bricks[index].brick_type.is_breakable() || self.brick_hits[index] > 0
+    }
+
+    /// The player left standing once the other has run out of lives
+    pub fn winner(&self) -> Option<usize> {
+        let loser = self.lives.iter().position(|lives| *lives == 0)?;
+        Some((loser + 1) % PLAYER_COUNT)
+    }
+
+    /// A hash of the whole state (FNV-1a over the raw bits), so two peers can cheaply check that they agree
+    pub fn checksum(&self) -> u64 {
+        let mut hash: u64 = 0xcbf2_9ce4_8422_2325;
+        let mut feed = |value: u64| {
+            for byte in value.to_le_bytes().iter() {
+                hash ^= *byte as u64;
+                hash = hash.wrapping_mul(0x0100_0000_01b3);
+            }
+        };
+
+        for x in self.paddle_x.iter() {
+            feed(x.to_bits() as u64);
+        }
+        feed(self.ball_position.x().to_bits() as u64);
+        feed(self.ball_position.y().to_bits() as u64);
+        feed(self.ball_velocity.x().to_bits() as u64);
+        feed(self.ball_velocity.y().to_bits() as u64);
+        feed(self.last_hit_by as u64);
+        for hits in self.brick_hits.iter() {
+            feed(*hits as u64);
+        }
+        for (score, lives) in self.scores.iter().zip(self.lives.iter()) {
+            feed(*score as u64);
+            feed(*lives as u64);
+        }
+        hash
+    }
+}
+
+/// Every tick's inputs of a match, which is all it takes to play it back exactly
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct Replay {
+    pub inputs: Vec<TickInputs>,
+}
+
+impl Replay {
+    /// Reads a replay saved with `save`
+    pub fn load(path: &Path) -> io::Result<Replay> {
+        let contents = fs::read_to_string(path)?;
+        ron::de::from_str(&contents)
+            .map_err(|error| io::Error::new(io::ErrorKind::InvalidData, error.to_string()))
+    }
+
+    /// Writes the replay out as a ron file, creating its directory if needed
+    pub fn save(&self, path: &Path) -> io::Result<()> {
+        let contents = ron::ser::to_string(self)
+            .map_err(|error| io::Error::new(io::ErrorKind::InvalidData, error.to_string()))?;
+
+        if let Some(directory) = path.parent() {
+            fs::create_dir_all(directory)?;
+        }
+        fs::write(path, contents)
+    }
+}
+
+//What the ball can run into, checked in a fixed order so both peers resolve the same collision
+enum Obstacle {
+    Wall,
+    Goal(usize),
+    Paddle(usize),
+    Brick(usize),
+}
+
+/// Advances the game by a single tick
+pub fn step(state: &mut SimulationState, layout: &LevelLayout, inputs: TickInputs) {
+    if state.winner().is_some() {
+        return;
+    }
+
+    let paddle_limit = BOUNDS.0 - PADDLE_SIZE.0 / 2.0;
+    for (x, input) in state.paddle_x.iter_mut().zip(inputs.iter()) {
+        *x = (*x + input.signum() as f32 * PADDLE_SPEED * TICK_SECONDS)
+            .max(-paddle_limit)
+            .min(paddle_limit);
+    }
+
+    let ball_limit = Vec2::new(BOUNDS.0, BOUNDS.1) - Vec2::splat(BALL_SIZE / 2.0);
+    state.ball_position = (state.ball_position + state.ball_velocity * TICK_SECONDS)
+        .clamp_to_aabb(-ball_limit, ball_limit);
+
+    let bounds = Vec2::new(BOUNDS.0 * 2.0, BOUNDS.1 * 2.0);
+    let side_wall_size = Vec2::new(WALL_THICKNESS, bounds.y() + WALL_THICKNESS);
+    let end_wall_size = Vec2::new(bounds.x() + WALL_THICKNESS, WALL_THICKNESS);
+    let mut obstacles = vec![
+        (Obstacle::Wall, Vec2::new(-BOUNDS.0, 0.0), side_wall_size),
+        (Obstacle::Wall, Vec2::new(BOUNDS.0, 0.0), side_wall_size),
+        (Obstacle::Goal(0), Vec2::new(0.0, -BOUNDS.1), end_wall_size),
+        (Obstacle::Goal(1), Vec2::new(0.0, BOUNDS.1), end_wall_size),
+    ];
+    for (player, x) in state.paddle_x.iter().enumerate() {
+        obstacles.push((
+            Obstacle::Paddle(player),
+            Vec2::new(*x, paddle_y(player)),
+            Vec2::new(PADDLE_SIZE.0, PADDLE_SIZE.1),
+        ));
+    }
+    for (index, brick) in layout.bricks.iter().enumerate() {
+        if state.brick_alive(layout, index) {
+            obstacles.push((Obstacle::Brick(index), brick.position, brick.size));
+        }
+    }
+
+    for (obstacle, position, size) in obstacles {
+        let contact = match circle_aabb(
+            state.ball_position,
+            BALL_SIZE / 2.0,
+            &Aabb::from_center_size(position, size),
+        ) {
+            Some(contact) => contact,
+            None => continue,
+        };
+        let reflect = state.ball_velocity.dot(contact.normal) < 0.0;
+
+        match obstacle {
+            Obstacle::Goal(player) => {
+                state.lives[player] = state.lives[player].saturating_sub(1);
+                let (position, velocity) = serve(PLAYER_COUNT, player);
+                state.ball_position = position;
+                state.ball_velocity = velocity.truncate();
+                state.last_hit_by = server(PLAYER_COUNT, player);
+                return;
+            }
+            Obstacle::Paddle(player) => state.last_hit_by = player,
+            Obstacle::Brick(index) if reflect => {
+                if layout.bricks[index].brick_type.is_breakable() {
+                    state.brick_hits[index] = state.brick_hits[index].saturating_sub(1);
+                    if state.brick_hits[index] == 0 {
+                        state.scores[state.last_hit_by] += layout.bricks[index].brick_type.points();
+                        state.ball_velocity *= BRICK_SPEED_UP;
+                    }
+                }
+            }
+            Obstacle::Brick(_) | Obstacle::Wall => {}
+        }
+
+        if reflect {
+            state.ball_velocity = state.ball_velocity.reflect(contact.normal);
+        }
+        state.ball_position += contact.normal * contact.depth;
+        break;
+    }
+}
@@ -0,0 +1,364 @@
+//Paddles, balls and bricks drawn from a sprite sheet instead of as flat colored rectangles. The entities the game plays with
+//are left exactly as they were (collisions go by their Sprite's size) and each gets a skin: a sprite sheet entity following it
+//around, tinted with whatever color the theme gave it. Tough bricks show more cracks with every hit, and a broken brick's skin
+//stays behind long enough to play the brick flying apart.
+//
+//Which sheet is used comes from the palette. A palette without one, or a sheet that isn't there, means no skins and the flat
+//colors show through again
+
+use bevy::prelude::*;
+use std::{collections::HashSet, path::Path};
+
+use super::level::{Brick, BrickType};
+use super::theme::Accessibility;
+use super::{Ball, GameEvent, Paddle};
+
+//how the sheet is laid out: a grid of same sized cells, which get stretched to whatever size they're drawn over
+const SHEET_COLUMNS: usize = 4;
+const SHEET_ROWS: usize = 3;
+const CELL_SIZE: (f32, f32) = (64.0, 32.0);
+const PADDLE_FRAME: u32 = 0;
+const BALL_FRAME: u32 = 1;
+const NORMAL_BRICK_FRAME: u32 = 2;
+const UNBREAKABLE_BRICK_FRAME: u32 = 3;
+//one frame per hit a tough brick can take, each more cracked than the last
+const TOUGH_BRICK_FRAMES: (u32, u32) = (4, 7);
+const BREAK_FRAMES: (u32, u32) = (8, 12);
+const BREAK_FRAME_SECONDS: f32 = 0.06;
+//just in front of what it's skinning
+const SKIN_Z: f32 = 0.01;
+
+pub struct SkinsPlugin;
+
+impl Plugin for SkinsPlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        app.init_resource::<SkinSheet>()
+            .add_system(skin_sheet_system.system())
+            .add_system(skin_spawn_system.system())
+            .add_system(skin_sync_system.system())
+            .add_system(break_animation_system.system());
+    }
+}
+
+/// Resource with the sprite sheet in use, if any
+#[derive(Default)]
+struct SkinSheet {
+    /// The sheet the palette asked for, whether or not it could be loaded
+    requested: Option<&'static str>,
+    atlas: Option<Handle<TextureAtlas>>,
+    /// Set when the sheet changes, until the old skins have been cleared away
+    changed: bool,
+}
+
+/// Component on a skin, with the entity it's drawn over
+struct Skin {
+    target: Entity,
+}
+
+/// Marks an entity that's had a skin made for it
+struct Skinned;
+
+/// Component on the skin of a broken brick while it plays the break animation
+struct Breaking {
+    elapsed: f32,
+}
+
+/// Loads the palette's sheet whenever the palette changes
+fn skin_sheet_system(
+    settings: Res<Accessibility>,
+    asset_server: Res<AssetServer>,
+    mut atlases: ResMut<Assets<TextureAtlas>>,
+    mut sheet: ResMut<SkinSheet>,
+) {
+    let requested = settings.palette().sheet;
+    if requested == sheet.requested {
+        return;
+    }
+    sheet.requested = requested;
+    sheet.changed = true;
+
+    sheet.atlas = available_sheet(requested).and_then(|path| match asset_server.load(path) {
+        Ok(texture) => Some(atlases.add(TextureAtlas::from_grid(
+            texture,
+            Vec2::new(
+                CELL_SIZE.0 * SHEET_COLUMNS as f32,
+                CELL_SIZE.1 * SHEET_ROWS as f32,
+            ),
+            SHEET_COLUMNS,
+            SHEET_ROWS,
+        ))),
+        Err(error) => {
+            log::warn!("Couldn't load {}, using flat colors: {:?}", path, error);
+            None
+        }
+    });
+}
+
+/// The palette's sheet if it's there to be loaded. Without one everything's drawn in flat colors
+fn available_sheet(requested: Option<&str>) -> Option<&str> {
+    requested.filter(|path| {
+        let exists = Path::new(path).exists();
+        if !exists {
+            log::warn!("Couldn't load {}, using flat colors: it's missing", path);
+        }
+        exists
+    })
+}
+
+fn skin_spawn_system(
+    mut commands: Commands,
+    sheet: Res<SkinSheet>,
+    mut paddle_query: Query<Without<Skinned, With<Paddle, Entity>>>,
+    mut ball_query: Query<Without<Skinned, With<Ball, Entity>>>,
+    mut brick_query: Query<Without<Skinned, With<Brick, Entity>>>,
+) {
+    //waiting on the old skins to be cleared away before making new ones
+    let atlas = match sheet.atlas {
+        Some(atlas) if !sheet.changed => atlas,
+        _ => return,
+    };
+
+    let mut targets = Vec::new();
+    targets.extend(&mut paddle_query.iter());
+    targets.extend(&mut ball_query.iter());
+    targets.extend(&mut brick_query.iter());
+    for target in targets {
+        commands
+            .spawn(SpriteSheetComponents {
+                texture_atlas: atlas,
+                //hidden until it's been moved over its target
+                draw: Draw {
+                    is_visible: false,
+                    ..Default::default()
+                },
+                ..Default::default()
+            })
+            .with(Skin { target });
+        commands.insert_one(target, Skinned);
+    }
+}
+
+/// Which frame of the sheet an entity is drawn with
+fn frame(ball: Option<&Ball>, brick: Option<&Brick>) -> u32 {
+    match (ball, brick) {
+        (Some(_ball), _) => BALL_FRAME,
+        (None, Some(brick)) => match brick.brick_type {
+            BrickType::Normal => NORMAL_BRICK_FRAME,
+            BrickType::Unbreakable => UNBREAKABLE_BRICK_FRAME,
+            BrickType::Tough => {
+                let hits_taken = brick.brick_type.hits().saturating_sub(brick.hits_remaining);
+                (TOUGH_BRICK_FRAMES.0 + hits_taken).min(TOUGH_BRICK_FRAMES.1 - 1)
+            }
+        },
+        (None, None) => PADDLE_FRAME,
+    }
+}
+
+/// Keeps each skin over what it's skinning, at its size and in its color and (for bricks) damage. A skin whose brick has
+/// been broken moves on to the break animation, while one whose entity has gone any other way just goes too.
+/// When the sheet changes every skin goes, so everything gets a fresh one from the new sheet (if there is one)
+fn skin_sync_system(
+    mut commands: Commands,
+    mut sheet: ResMut<SkinSheet>,
+    materials: Res<Assets<ColorMaterial>>,
+    game_events: Res<Events<GameEvent>>,
+    mut game_event_reader: Local<EventReader<GameEvent>>,
+    //bricks broken but not yet seen to be gone, as they're only despawned once this frame's systems are done
+    mut broken: Local<HashSet<Entity>>,
+    mut target_query: Query<
+        With<
+            Skinned,
+            (
+                Entity,
+                &Transform,
+                &Sprite,
+                &Handle<ColorMaterial>,
+                &mut Draw,
+                Option<&Ball>,
+                Option<&Brick>,
+            ),
+        >,
+    >,
+    mut skin_query: Query<(
+        Entity,
+        &Skin,
+        &mut Transform,
+        &mut TextureAtlasSprite,
+        &mut Draw,
+    )>,
+) {
+    for event in game_event_reader.iter(&game_events) {
+        match event {
+            GameEvent::BrickBroken { brick, .. } if sheet.atlas.is_some() => {
+                broken.insert(*brick);
+            }
+            _ => {}
+        }
+    }
+
+    if sheet.changed {
+        for (entity, ..) in &mut skin_query.iter() {
+            commands.despawn(entity);
+        }
+        for (entity, _transform, _sprite, _material, mut draw, ..) in &mut target_query.iter() {
+            draw.is_visible = true;
+            commands.remove_one::<Skinned>(entity);
+        }
+        broken.clear();
+        sheet.changed = false;
+        return;
+    }
+
+    for (entity, skin, mut transform, mut sprite, mut skin_draw) in &mut skin_query.iter() {
+        let target = match (
+            target_query.get::<Transform>(skin.target),
+            target_query.get::<Sprite>(skin.target),
+            target_query.get::<Handle<ColorMaterial>>(skin.target),
+        ) {
+            (Ok(transform), Ok(sprite), Ok(material)) => {
+                Some((transform.translation(), sprite.size, *material))
+            }
+            _ => None,
+        };
+        let (position, size, material) = match target {
+            Some(target) => target,
+            None => {
+                if broken.remove(&skin.target) {
+                    commands.remove_one::<Skin>(entity);
+                    commands.insert_one(entity, Breaking { elapsed: 0.0 });
+                    sprite.index = BREAK_FRAMES.0;
+                } else {
+                    commands.despawn(entity);
+                }
+                continue;
+            }
+        };
+
+        if let Ok(mut draw) = target_query.get_mut::<Draw>(skin.target) {
+            draw.is_visible = false;
+        }
+        sprite.index = frame(
+            target_query.get::<Ball>(skin.target).ok().as_deref(),
+            target_query.get::<Brick>(skin.target).ok().as_deref(),
+        );
+        if let Some(material) = materials.get(&material) {
+            sprite.color = material.color;
+        }
+        //the cell is stretched to cover the entity exactly, whatever shape it is
+        *transform = Transform::new(Mat4::from_scale_rotation_translation(
+            Vec3::new(size.x() / CELL_SIZE.0, size.y() / CELL_SIZE.1, 1.0),
+            Quat::identity(),
+            position + Vec3::new(0.0, 0.0, SKIN_Z),
+        ));
+        skin_draw.is_visible = true;
+    }
+}
+
+/// The frame a broken brick's skin shows the given time after it broke, or None once the animation's over
+fn break_frame(elapsed: f32) -> Option<u32> {
+    let frame = BREAK_FRAMES.0 + (elapsed / BREAK_FRAME_SECONDS) as u32;
+    if frame < BREAK_FRAMES.1 {
+        Some(frame)
+    } else {
+        None
+    }
+}
+
+fn break_animation_system(
+    mut commands: Commands,
+    time: Res<Time>,
+    mut query: Query<(Entity, &mut Breaking, &mut TextureAtlasSprite)>,
+) {
+    for (entity, mut breaking, mut sprite) in &mut query.iter() {
+        breaking.elapsed += time.delta_seconds;
+        match break_frame(breaking.elapsed) {
+            Some(frame) => sprite.index = frame,
+            None => commands.despawn(entity),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::{env, fs, process};
+
+    fn brick(brick_type: BrickType, hits_remaining: u32) -> Brick {
+        Brick {
+            brick_type,
+            hits_remaining,
+            color: None,
+        }
+    }
+
+    #[test]
+    fn tough_bricks_crack_with_every_hit() {
+        let frames: Vec<u32> = (1..=3)
+            .rev()
+            .map(|hits_remaining| frame(None, Some(&brick(BrickType::Tough, hits_remaining))))
+            .collect();
+        assert_eq!(frames, vec![4, 5, 6]);
+        //never past the last cracked frame, into the break animation
+        assert_eq!(frame(None, Some(&brick(BrickType::Tough, 0))), 6);
+
+        assert_eq!(
+            frame(None, Some(&brick(BrickType::Normal, 1))),
+            NORMAL_BRICK_FRAME
+        );
+        assert_eq!(
+            frame(None, Some(&brick(BrickType::Unbreakable, 0))),
+            UNBREAKABLE_BRICK_FRAME
+        );
+        assert_eq!(frame(None, None), PADDLE_FRAME);
+    }
+
+    #[test]
+    fn break_animation_plays_each_frame_then_ends() {
+        let step = BREAK_FRAME_SECONDS;
+        assert_eq!(break_frame(0.0), Some(BREAK_FRAMES.0));
+        assert_eq!(break_frame(step * 0.5), Some(BREAK_FRAMES.0));
+        assert_eq!(break_frame(step * 1.5), Some(BREAK_FRAMES.0 + 1));
+        assert_eq!(break_frame(step * 3.5), Some(BREAK_FRAMES.1 - 1));
+        assert_eq!(break_frame(step * 4.5), None);
+        //a long frame skips ahead rather than dragging the animation out
+        assert_eq!(break_frame(1.0), None);
+    }
+
+    #[test]
+    fn missing_sheet_means_flat_colors() {
+        assert_eq!(available_sheet(None), None);
+        assert_eq!(
+            available_sheet(Some("assets/textures/no_such_sheet.png")),
+            None
+        );
+
+        let path = env::temp_dir().join(format!("breakout-sheet-{}.png", process::id()));
+        fs::write(&path, []).unwrap();
+        let path = path.to_str().unwrap();
+        let available = available_sheet(Some(path));
+        let _ = fs::remove_file(path);
+        assert_eq!(available, Some(path));
+    }
+
+    //the number of skins made for a brick, with and without a sheet to make them from
+    fn skins_made(atlas: Option<Handle<TextureAtlas>>) -> usize {
+        let mut builder = App::build();
+        builder
+            .add_resource(SkinSheet {
+                atlas,
+                ..Default::default()
+            })
+            .add_system(skin_spawn_system.system());
+        let mut app = builder.app;
+        app.world.spawn((brick(BrickType::Normal, 1),));
+        app.update();
+        let skins = app.world.query::<&Skin>().iter().count();
+        skins
+    }
+
+    #[test]
+    fn nothing_is_skinned_without_a_sheet() {
+        assert_eq!(skins_made(None), 0);
+        assert_eq!(skins_made(Some(Handle::new())), 1);
+    }
+}
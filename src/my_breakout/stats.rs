@@ -0,0 +1,501 @@
+//Numbers on how a game went, for comparing playtests when tuning things like how much the ball speeds up per brick.
+//Stats are gathered from the game's events along with a look at the ball and keyboard every frame, start over whenever the
+//game restarts, and get shown on the game over screen as well as saved to the stats directory, as JSON unless the
+//stats_format console command (which an unattended game's script can run) asks for CSV.
+//The attract mode demo isn't anybody playing so it's left out
+
+use bevy::prelude::*;
+use serde::Serialize;
+use std::{
+    collections::BTreeMap,
+    fs, io,
+    path::{Path, PathBuf},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use super::console::{missing, parse_arg, AddConsoleCommand, ConsoleCommand, ConsoleState};
+use super::level::LevelProgress;
+use super::locale::Localization;
+use super::theme::ColorRole;
+use super::{Ball, GameEvent, GameState, RestartGame, BALL_SPEED, BRICK_SPEED_UP, PADDLE_SPEED};
+
+const TEXT_LINES: usize = 6;
+const TEXT_SIZE: f32 = 24.0;
+
+/// The file format stats get saved in
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum StatsFormat {
+    Json,
+    /// A `stat,value` row per stat
+    Csv,
+}
+
+impl StatsFormat {
+    const ALL: [StatsFormat; 2] = [StatsFormat::Json, StatsFormat::Csv];
+
+    fn name(self) -> &'static str {
+        match self {
+            StatsFormat::Json => "json",
+            StatsFormat::Csv => "csv",
+        }
+    }
+}
+
+/// Resource with where a finished game's stats get saved
+pub struct StatsExport {
+    pub directory: PathBuf,
+    pub format: StatsFormat,
+}
+
+impl Default for StatsExport {
+    fn default() -> Self {
+        StatsExport {
+            directory: PathBuf::from("stats"),
+            format: StatsFormat::Json,
+        }
+    }
+}
+
+/// The constants the game was tuned with, saved along with the stats so playtests of different tunings can be told apart
+#[derive(Clone, Debug, Serialize)]
+pub struct Tuning {
+    pub ball_speed: f32,
+    pub brick_speed_up: f32,
+    pub paddle_speed: f32,
+}
+
+impl Default for Tuning {
+    fn default() -> Self {
+        Tuning {
+            ball_speed: BALL_SPEED,
+            brick_speed_up: BRICK_SPEED_UP,
+            paddle_speed: PADDLE_SPEED,
+        }
+    }
+}
+
+/// Resource with the current game's stats
+#[derive(Clone, Debug, Default, Serialize)]
+pub struct SessionStats {
+    pub tuning: Tuning,
+    /// Broken bricks counted by brick type
+    pub bricks_broken: BTreeMap<String, u32>,
+    pub paddle_hits: u32,
+    pub lives_lost: u32,
+    /// The most bricks broken without the ball going back to a paddle (or being lost)
+    pub longest_combo: u32,
+    pub average_ball_speed: f32,
+    pub peak_ball_speed: f32,
+    pub seconds_played: f32,
+    /// How long each level took, the last one being however far the game got into it before it ended
+    pub level_seconds: Vec<f32>,
+    /// How many times each key was pressed
+    pub key_presses: BTreeMap<String, u32>,
+    //speed summed over every frame by how long the frame was, for averaging over time played
+    #[serde(skip)]
+    ball_speed_seconds: f32,
+    #[serde(skip)]
+    saved_to: Option<PathBuf>,
+}
+
+impl SessionStats {
+    pub fn bricks_broken_total(&self) -> u32 {
+        self.bricks_broken.values().sum()
+    }
+
+    fn record(&mut self, event: &GameEvent) {
+        match event {
+            GameEvent::BrickBroken {
+                brick_type, combo, ..
+            } => {
+                let name = brick_type.map_or("Other".to_string(), |brick_type| {
+                    format!("{:?}", brick_type)
+                });
+                *self.bricks_broken.entry(name).or_insert(0) += 1;
+                self.longest_combo = self.longest_combo.max(*combo);
+            }
+            GameEvent::PaddleHit { .. } => self.paddle_hits += 1,
+            GameEvent::LifeLost { .. } => self.lives_lost += 1,
+            GameEvent::LevelCleared { .. } | GameEvent::GameEnded { .. } => {}
+        }
+    }
+
+    fn to_csv(&self) -> String {
+        let mut rows = vec!["stat,value".to_string()];
+        rows.push(format!("tuning_ball_speed,{}", self.tuning.ball_speed));
+        rows.push(format!(
+            "tuning_brick_speed_up,{}",
+            self.tuning.brick_speed_up
+        ));
+        rows.push(format!("tuning_paddle_speed,{}", self.tuning.paddle_speed));
+        for (brick_type, count) in self.bricks_broken.iter() {
+            rows.push(format!("bricks_broken_{},{}", brick_type, count));
+        }
+        rows.push(format!("paddle_hits,{}", self.paddle_hits));
+        rows.push(format!("lives_lost,{}", self.lives_lost));
+        rows.push(format!("longest_combo,{}", self.longest_combo));
+        rows.push(format!("average_ball_speed,{}", self.average_ball_speed));
+        rows.push(format!("peak_ball_speed,{}", self.peak_ball_speed));
+        rows.push(format!("seconds_played,{}", self.seconds_played));
+        for (level, seconds) in self.level_seconds.iter().enumerate() {
+            rows.push(format!("level_{}_seconds,{}", level + 1, seconds));
+        }
+        for (key, count) in self.key_presses.iter() {
+            rows.push(format!("key_presses_{},{}", key, count));
+        }
+        rows.join("\n") + "\n"
+    }
+
+    /// Saves the stats, noting where they went, or says why they couldn't be
+    fn save_or_warn(&mut self, export: &StatsExport) {
+        match self.save(export) {
+            Ok(path) => self.saved_to = Some(path),
+            Err(error) => log::warn!(
+                "Couldn't save stats to {}: {}",
+                export.directory.display(),
+                error
+            ),
+        }
+    }
+
+    /// Writes the stats to a new file in the export directory, named after when the game ended
+    fn save(&self, export: &StatsExport) -> io::Result<PathBuf> {
+        fs::create_dir_all(&export.directory)?;
+        let (contents, extension) = match export.format {
+            StatsFormat::Json => (
+                serde_json::to_string_pretty(self)
+                    .map_err(|error| io::Error::new(io::ErrorKind::Other, error))?,
+                "json",
+            ),
+            StatsFormat::Csv => (self.to_csv(), "csv"),
+        };
+        let timestamp = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |time| time.as_secs());
+        let path = unused_path(&export.directory, timestamp, extension);
+        fs::write(&path, contents)?;
+        Ok(path)
+    }
+}
+
+//a couple of games can end in the same second, so count up until there's a free name
+fn unused_path(directory: &Path, timestamp: u64, extension: &str) -> PathBuf {
+    let mut path = directory.join(format!("session-{}.{}", timestamp, extension));
+    let mut attempt = 1;
+    while path.exists() {
+        attempt += 1;
+        path = directory.join(format!("session-{}-{}.{}", timestamp, attempt, extension));
+    }
+    path
+}
+
+pub struct StatsPlugin;
+
+impl Plugin for StatsPlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        app.init_resource::<SessionStats>()
+            .init_resource::<StatsExport>()
+            .add_startup_system(stats_setup.system())
+            .add_system(stats_event_system.system())
+            .add_system(stats_sample_system.system())
+            .add_system(stats_text_system.system())
+            .add_console_command(StatsFormatCommand);
+    }
+}
+
+struct StatsFormatCommand;
+
+impl ConsoleCommand for StatsFormatCommand {
+    fn name(&self) -> &'static str {
+        "stats_format"
+    }
+
+    fn usage(&self) -> &'static str {
+        "stats_format <json|csv> - the format the stats get saved in once the game ends"
+    }
+
+    fn run(
+        &self,
+        args: &[&str],
+        _world: &mut World,
+        resources: &mut Resources,
+    ) -> Result<String, String> {
+        let name: String = parse_arg(args, self.usage())?;
+        let format = StatsFormat::ALL
+            .iter()
+            .copied()
+            .find(|format| format.name() == name)
+            .ok_or_else(|| format!("no format called {}, usage: {}", name, self.usage()))?;
+        resources
+            .get_mut::<StatsExport>()
+            .ok_or_else(missing::<StatsExport>)?
+            .format = format;
+        Ok(format!("Stats will be saved as {}", format.name()))
+    }
+}
+
+struct StatsText {
+    line: usize,
+}
+
+fn stats_setup(mut commands: Commands, asset_server: Res<AssetServer>) {
+    let font = asset_server.load("assets/fonts/FiraSans-Bold.ttf").unwrap();
+    for line in 0..TEXT_LINES {
+        commands
+            .spawn(TextComponents {
+                text: Text {
+                    font,
+                    value: "".to_string(),
+                    style: TextStyle {
+                        color: Color::rgb(1.0, 1.0, 1.0),
+                        font_size: TEXT_SIZE,
+                    },
+                },
+                style: Style {
+                    position_type: PositionType::Absolute,
+                    //under the game over message from the title screen
+                    position: Rect {
+                        top: Val::Px(320.0 + line as f32 * TEXT_SIZE),
+                        left: Val::Px(200.0),
+                        ..Default::default()
+                    },
+                    ..Default::default()
+                },
+                ..Default::default()
+            })
+            .with(StatsText { line })
+            .with(ColorRole::Text);
+    }
+}
+
+/// Counts up what happens, and saves the stats once as the game ends
+fn stats_event_system(
+    game_state: Res<GameState>,
+    export: Res<StatsExport>,
+    mut restart_reader: Local<EventReader<RestartGame>>,
+    restart_events: Res<Events<RestartGame>>,
+    mut game_event_reader: Local<EventReader<GameEvent>>,
+    game_events: Res<Events<GameEvent>>,
+    mut stats: ResMut<SessionStats>,
+) {
+    if restart_reader.iter(&restart_events).count() > 0 {
+        *stats = SessionStats::default();
+    }
+    for event in game_event_reader.iter(&game_events) {
+        if *game_state == GameState::Attract {
+            continue;
+        }
+        stats.record(event);
+        //saved here rather than in a system of its own so the events that ended the game are sure to be counted
+        if let GameEvent::GameEnded { .. } = event {
+            stats.save_or_warn(&export);
+        }
+    }
+}
+
+/// Keeps track of the ball's speed, how long everything's taking and what's being pressed
+fn stats_sample_system(
+    time: Res<Time>,
+    game_state: Res<GameState>,
+    keyboard_input: Res<Input<KeyCode>>,
+    console: Res<ConsoleState>,
+    level_progress: Res<LevelProgress>,
+    mut stats: ResMut<SessionStats>,
+    mut ball_query: Query<&Ball>,
+) {
+    if *game_state != GameState::Playing {
+        return;
+    }
+
+    //typing into the console isn't playing
+    let typed = if console.open {
+        Vec::new()
+    } else {
+        keyboard_input.get_just_pressed().collect()
+    };
+    for key in typed {
+        *stats.key_presses.entry(format!("{:?}", key)).or_insert(0) += 1;
+    }
+
+    let delta_seconds = time.delta_seconds;
+    stats.seconds_played += delta_seconds;
+    let level = level_progress.level as usize;
+    if stats.level_seconds.len() <= level {
+        stats.level_seconds.resize(level + 1, 0.0);
+    }
+    stats.level_seconds[level] += delta_seconds;
+
+    //with a few balls in play it's the fastest one that counts
+    let mut speed: f32 = 0.0;
+    for ball in &mut ball_query.iter() {
+        speed = speed.max(ball.velocity.length());
+    }
+    stats.peak_ball_speed = stats.peak_ball_speed.max(speed);
+    stats.ball_speed_seconds += speed * delta_seconds;
+    if stats.seconds_played > 0.0 {
+        stats.average_ball_speed = stats.ball_speed_seconds / stats.seconds_played;
+    }
+}
+
+fn stats_text_system(
+    localization: Res<Localization>,
+    game_state: Res<GameState>,
+    stats: Res<SessionStats>,
+    mut query: Query<(&StatsText, &mut Text)>,
+) {
+    let lines = if matches!(*game_state, GameState::GameOver | GameState::Cleared) {
+        let number = |value: u32| localization.number(value as u64);
+        let bricks: Vec<String> = stats
+            .bricks_broken
+            .iter()
+            .map(|(brick_type, count)| {
+                localization.format(
+                    "stats.brick_count",
+                    &[
+                        ("count", number(*count)),
+                        (
+                            "brick_type",
+                            localization.text(&format!("brick_type.{}", brick_type)),
+                        ),
+                    ],
+                )
+            })
+            .collect();
+        vec![
+            localization.format(
+                "stats.bricks",
+                &[
+                    ("total", number(stats.bricks_broken_total())),
+                    ("by_type", bricks.join(", ")),
+                ],
+            ),
+            localization.format(
+                "stats.hits",
+                &[
+                    ("hits", number(stats.paddle_hits)),
+                    ("combo", number(stats.longest_combo)),
+                ],
+            ),
+            localization.format(
+                "stats.ball_speed",
+                &[
+                    ("average", localization.decimal(stats.average_ball_speed, 0)),
+                    ("peak", localization.decimal(stats.peak_ball_speed, 0)),
+                ],
+            ),
+            localization.format(
+                "stats.lives",
+                &[
+                    ("lives", number(stats.lives_lost)),
+                    ("seconds", localization.decimal(stats.seconds_played, 0)),
+                    (
+                        "levels",
+                        localization.plural("levels", stats.level_seconds.len() as u64),
+                    ),
+                ],
+            ),
+            localization.format(
+                "stats.keys",
+                &[("count", number(stats.key_presses.values().sum::<u32>()))],
+            ),
+            stats.saved_to.as_ref().map_or("".to_string(), |path| {
+                localization.format("stats.saved", &[("path", path.display().to_string())])
+            }),
+        ]
+    } else {
+        Vec::new()
+    };
+
+    for (stats_text, mut text) in &mut query.iter() {
+        text.value = lines.get(stats_text.line).cloned().unwrap_or_default();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::{env, process};
+
+    #[test]
+    fn csv_has_a_row_per_stat() {
+        let mut stats = SessionStats::default();
+        stats.bricks_broken.insert("Tough".to_string(), 3);
+        stats.bricks_broken.insert("Normal".to_string(), 12);
+        stats.paddle_hits = 9;
+        stats.lives_lost = 2;
+        stats.longest_combo = 5;
+        stats.level_seconds = vec![61.5, 20.0];
+        stats.key_presses.insert("Left".to_string(), 40);
+
+        let csv = stats.to_csv();
+        let rows: Vec<&str> = csv.lines().collect();
+        assert_eq!(rows[0], "stat,value");
+        for row in [
+            "bricks_broken_Normal,12",
+            "bricks_broken_Tough,3",
+            "paddle_hits,9",
+            "lives_lost,2",
+            "longest_combo,5",
+            "level_1_seconds,61.5",
+            "level_2_seconds,20",
+            "key_presses_Left,40",
+        ]
+        .iter()
+        {
+            assert!(rows.contains(row), "no {} row in\n{}", row, csv);
+        }
+        //every row is a name and a value, with nothing that'd need quoting
+        assert!(rows.iter().all(|row| row.split(',').count() == 2));
+        assert!(csv.ends_with('\n'));
+    }
+
+    #[test]
+    fn unused_path_counts_up_past_taken_names() {
+        let directory = env::temp_dir().join(format!("breakout-stats-{}", process::id()));
+        fs::create_dir_all(&directory).unwrap();
+
+        let first = unused_path(&directory, 100, "csv");
+        assert_eq!(first, directory.join("session-100.csv"));
+        fs::write(&first, "").unwrap();
+        let second = unused_path(&directory, 100, "csv");
+        assert_eq!(second, directory.join("session-100-2.csv"));
+        fs::write(&second, "").unwrap();
+        assert_eq!(
+            unused_path(&directory, 100, "csv"),
+            directory.join("session-100-3.csv")
+        );
+        //another extension or second is free to use the plain name
+        assert_eq!(
+            unused_path(&directory, 100, "json"),
+            directory.join("session-100.json")
+        );
+        assert_eq!(
+            unused_path(&directory, 101, "csv"),
+            directory.join("session-101.csv")
+        );
+
+        fs::remove_dir_all(&directory).unwrap();
+    }
+
+    #[test]
+    fn stats_format_command_switches_the_format() {
+        let mut world = World::default();
+        let mut resources = Resources::default();
+        resources.insert(StatsExport::default());
+
+        assert!(StatsFormatCommand
+            .run(&["csv"], &mut world, &mut resources)
+            .is_ok());
+        assert_eq!(
+            resources.get::<StatsExport>().unwrap().format,
+            StatsFormat::Csv
+        );
+        assert!(StatsFormatCommand
+            .run(&["xml"], &mut world, &mut resources)
+            .is_err());
+        assert_eq!(
+            resources.get::<StatsExport>().unwrap().format,
+            StatsFormat::Csv
+        );
+    }
+}
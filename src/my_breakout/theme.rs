@@ -0,0 +1,715 @@
+//Colors and accessibility options. Nothing in the game picks its own color anymore: entities say what they are with a
+//ColorRole (bricks go by their type) and the palette in use decides what color that is, so switching palettes recolors
+//everything already on screen. Bricks given a color of their own by their level, like the versus walls, keep it. Alongside the standard colors there's a high contrast palette and one built from the
+//Okabe-Ito colors, which stay distinct with the common kinds of color blindness. Since color alone still isn't a great way
+//to tell bricks apart, tougher bricks can also get a pattern drawn over them.
+//
+//The other options are for anyone finding the game too quick or the ball too hard to follow: slowing the ball down and
+//making it bigger. All of it is changed from the console and saved so it sticks between runs.
+//Online games leave the ball alone since both ends have to simulate the same thing
+
+use bevy::{prelude::*, render::pass::ClearColor};
+use serde::{Deserialize, Serialize};
+use std::{
+    fs, io,
+    path::{Path, PathBuf},
+};
+
+use super::console::{missing, parse_arg, AddConsoleCommand, ConsoleCommand};
+use super::level::{Brick, BrickType};
+use super::locale::system_language;
+use super::players::player_color;
+use super::{Ball, GameState, BALL_SIZE};
+
+const SETTINGS_PATH: &str = "save/settings.ron";
+const SHEET_PATH: &str = "assets/textures/sheet.png";
+//between the bricks and the ball
+const PATTERN_Z: f32 = 0.5;
+const MIN_GAME_SPEED: f32 = 0.25;
+const MAX_BALL_SCALE: f32 = 3.0;
+
+/// The color for everything the palette covers
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Palette {
+    pub background: Color,
+    pub wall: Color,
+    pub ball: Color,
+    /// Indexed by player id, also used for their scoreboard
+    pub paddles: [Color; 2],
+    pub normal_brick: Color,
+    pub tough_brick: Color,
+    pub unbreakable_brick: Color,
+    pub text: Color,
+    pub title: Color,
+    /// Drawn over bricks to tell their types apart without relying on color
+    pub pattern: Color,
+    /// The see through areas of the arena that push the ball about, gravity wells and speed zones
+    pub field: Color,
+    pub portal: Color,
+    /// The colors the editor can give a brick in place of its type's, in the order they're cycled through
+    pub brick_colors: [Color; 6],
+    /// The sprite sheet paddles, balls and bricks are drawn from, tinted with the colors above. None draws them in flat colors
+    pub sheet: Option<&'static str>,
+}
+
+impl Palette {
+    pub fn standard() -> Self {
+        Palette {
+            background: Color::rgb(0.7, 0.7, 0.7),
+            wall: Color::rgb(0.5, 0.5, 0.5),
+            ball: Color::rgb(0.8, 0.2, 0.2),
+            paddles: [player_color(0), player_color(1)],
+            normal_brick: Color::rgb(0.2, 0.2, 0.8),
+            tough_brick: Color::rgb(0.5, 0.1, 0.6),
+            unbreakable_brick: Color::rgb(0.4, 0.4, 0.4),
+            text: Color::rgb(0.2, 0.2, 0.2),
+            title: Color::rgb(0.8, 0.2, 0.2),
+            pattern: Color::rgba(0.0, 0.0, 0.0, 0.35),
+            field: Color::rgba(0.2, 0.6, 0.3, 0.3),
+            portal: Color::rgb(0.1, 0.7, 0.7),
+            brick_colors: [
+                Color::rgb(0.8, 0.2, 0.2),
+                Color::rgb(0.9, 0.6, 0.1),
+                Color::rgb(0.9, 0.9, 0.2),
+                Color::rgb(0.2, 0.7, 0.3),
+                Color::rgb(0.2, 0.7, 0.8),
+                Color::rgb(0.9, 0.4, 0.7),
+            ],
+            sheet: Some(SHEET_PATH),
+        }
+    }
+
+    /// Bright colors on black, which read best without any shading from a sprite sheet
+    pub fn high_contrast() -> Self {
+        Palette {
+            background: Color::rgb(0.0, 0.0, 0.0),
+            wall: Color::rgb(0.85, 0.85, 0.85),
+            ball: Color::rgb(1.0, 1.0, 0.0),
+            paddles: [Color::rgb(0.0, 1.0, 1.0), Color::rgb(1.0, 0.4, 1.0)],
+            normal_brick: Color::rgb(0.2, 0.6, 1.0),
+            tough_brick: Color::rgb(1.0, 0.5, 0.0),
+            unbreakable_brick: Color::rgb(0.55, 0.55, 0.55),
+            text: Color::rgb(1.0, 1.0, 1.0),
+            title: Color::rgb(1.0, 1.0, 0.0),
+            pattern: Color::rgba(0.0, 0.0, 0.0, 0.6),
+            field: Color::rgba(0.0, 1.0, 0.0, 0.35),
+            portal: Color::rgb(0.6, 0.4, 1.0),
+            brick_colors: [
+                Color::rgb(1.0, 0.2, 0.2),
+                Color::rgb(1.0, 0.5, 0.0),
+                Color::rgb(1.0, 1.0, 0.0),
+                Color::rgb(0.0, 1.0, 0.0),
+                Color::rgb(0.0, 1.0, 1.0),
+                Color::rgb(1.0, 0.4, 1.0),
+            ],
+            sheet: None,
+        }
+    }
+
+    /// Taken from the Okabe-Ito palette, https://jfly.uni-koeln.de/color/
+    pub fn colorblind_safe() -> Self {
+        Palette {
+            background: Color::rgb(0.94, 0.94, 0.94),
+            wall: Color::rgb(0.35, 0.35, 0.35),
+            ball: Color::rgb(0.835, 0.369, 0.0),
+            paddles: [Color::rgb(0.0, 0.447, 0.698), Color::rgb(0.902, 0.624, 0.0)],
+            normal_brick: Color::rgb(0.337, 0.706, 0.914),
+            tough_brick: Color::rgb(0.8, 0.475, 0.655),
+            unbreakable_brick: Color::rgb(0.6, 0.6, 0.6),
+            text: Color::rgb(0.1, 0.1, 0.1),
+            title: Color::rgb(0.835, 0.369, 0.0),
+            pattern: Color::rgba(0.0, 0.0, 0.0, 0.35),
+            field: Color::rgba(0.0, 0.62, 0.451, 0.3),
+            portal: Color::rgb(0.941, 0.894, 0.259),
+            //the rest of the Okabe-Ito colors
+            brick_colors: [
+                Color::rgb(0.902, 0.624, 0.0),
+                Color::rgb(0.337, 0.706, 0.914),
+                Color::rgb(0.0, 0.62, 0.451),
+                Color::rgb(0.941, 0.894, 0.259),
+                Color::rgb(0.0, 0.447, 0.698),
+                Color::rgb(0.835, 0.369, 0.0),
+            ],
+            sheet: Some(SHEET_PATH),
+        }
+    }
+
+    pub fn brick(&self, brick_type: BrickType) -> Color {
+        match brick_type {
+            BrickType::Normal => self.normal_brick,
+            BrickType::Tough => self.tough_brick,
+            BrickType::Unbreakable => self.unbreakable_brick,
+        }
+    }
+
+    pub fn role(&self, role: ColorRole) -> Color {
+        match role {
+            ColorRole::Paddle { player } => self.paddles[player.min(1)],
+            ColorRole::Ball => self.ball,
+            ColorRole::Wall => self.wall,
+            ColorRole::Text => self.text,
+            ColorRole::Title => self.title,
+            ColorRole::Field => self.field,
+            ColorRole::Portal => self.portal,
+        }
+    }
+}
+
+/// The palettes there are to choose from
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum PaletteKind {
+    Standard,
+    HighContrast,
+    ColorblindSafe,
+}
+
+impl PaletteKind {
+    pub const ALL: [PaletteKind; 3] = [
+        PaletteKind::Standard,
+        PaletteKind::HighContrast,
+        PaletteKind::ColorblindSafe,
+    ];
+
+    /// What it's called in the console
+    pub fn name(self) -> &'static str {
+        match self {
+            PaletteKind::Standard => "standard",
+            PaletteKind::HighContrast => "high_contrast",
+            PaletteKind::ColorblindSafe => "colorblind",
+        }
+    }
+
+    pub fn palette(self) -> Palette {
+        match self {
+            PaletteKind::Standard => Palette::standard(),
+            PaletteKind::HighContrast => Palette::high_contrast(),
+            PaletteKind::ColorblindSafe => Palette::colorblind_safe(),
+        }
+    }
+}
+
+/// Component saying which of the palette's colors an entity's material (or text) is. Bricks don't need one, they go by their type
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ColorRole {
+    Paddle { player: usize },
+    Ball,
+    Wall,
+    Text,
+    Title,
+    Field,
+    Portal,
+}
+
+/// Resource with the accessibility options (and the language, which is as much about being able to play), saved between runs
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Accessibility {
+    pub palette: PaletteKind,
+    /// Whether bricks get a pattern per type drawn over them
+    pub patterns: bool,
+    /// How fast the ball moves compared to normal, the paddles keep their usual speed
+    pub game_speed: f32,
+    /// How big the ball is drawn and collides compared to normal
+    pub ball_scale: f32,
+    /// Whether balls leave a trail behind them that grows with their speed
+    pub trail: bool,
+    /// The language the game's text is in, the name of one of the files in assets/locales
+    pub language: String,
+    #[serde(skip)]
+    path: PathBuf,
+}
+
+impl Default for Accessibility {
+    fn default() -> Self {
+        Accessibility {
+            palette: PaletteKind::Standard,
+            patterns: false,
+            game_speed: 1.0,
+            ball_scale: 1.0,
+            trail: true,
+            language: system_language(),
+            path: PathBuf::new(),
+        }
+    }
+}
+
+impl Accessibility {
+    /// Reads the settings file, going with the defaults if there isn't one yet
+    pub fn load(path: &Path) -> io::Result<Accessibility> {
+        let mut settings = if path.exists() {
+            let contents = fs::read_to_string(path)?;
+            ron::de::from_str(&contents)
+                .map_err(|error| io::Error::new(io::ErrorKind::InvalidData, error.to_string()))?
+        } else {
+            Accessibility::default()
+        };
+        settings.path = path.to_path_buf();
+        Ok(settings)
+    }
+
+    /// Like load, but a settings file that can't be read is warned about and replaced with the defaults the next time a
+    /// setting's changed, rather than leaving the game stuck with settings it can never save
+    pub fn load_or_default(path: &Path) -> Accessibility {
+        Accessibility::load(path).unwrap_or_else(|error| {
+            log::warn!(
+                "Couldn't load {}, going with the default settings: {}",
+                path.display(),
+                error
+            );
+            Accessibility {
+                path: path.to_path_buf(),
+                ..Default::default()
+            }
+        })
+    }
+
+    pub fn save_or_warn(&self) {
+        let saved = ron::ser::to_string_pretty(self, ron::ser::PrettyConfig::default())
+            .map_err(|error| io::Error::new(io::ErrorKind::InvalidData, error.to_string()))
+            .and_then(|contents| {
+                if let Some(directory) = self.path.parent() {
+                    fs::create_dir_all(directory)?;
+                }
+                fs::write(&self.path, contents)
+            });
+        if let Err(error) = saved {
+            log::warn!(
+                "Couldn't save settings to {}: {}",
+                self.path.display(),
+                error
+            );
+        }
+    }
+
+    pub fn palette(&self) -> Palette {
+        self.palette.palette()
+    }
+
+    /// The size of the ball, which for an online game has to be the one the simulation uses
+    pub fn ball_size(&self, game_state: GameState) -> f32 {
+        if game_state == GameState::Online {
+            BALL_SIZE
+        } else {
+            BALL_SIZE * self.ball_scale
+        }
+    }
+}
+
+pub struct ThemePlugin;
+
+impl Plugin for ThemePlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        let settings = Accessibility::load_or_default(&PathBuf::from(SETTINGS_PATH));
+        app.add_resource(ClearColor(settings.palette().background))
+            .add_resource(settings)
+            .add_startup_system(pattern_setup.system())
+            .add_system(theme_system.system())
+            .add_system(ball_size_system.system())
+            .add_system(pattern_spawn_system.system())
+            .add_system(pattern_sync_system.system())
+            .add_console_command(PaletteCommand)
+            .add_console_command(PatternsCommand)
+            .add_console_command(GameSpeedCommand)
+            .add_console_command(BallSizeCommand);
+    }
+}
+
+/// Resource with the material every pattern is drawn with
+struct PatternMaterial(Handle<ColorMaterial>);
+
+/// Component on a piece of a brick's pattern, with where it goes as a fraction of the brick's size
+struct PatternOverlay {
+    brick: Entity,
+    brick_type: BrickType,
+    offset: Vec2,
+    size: Vec2,
+}
+
+/// Component on a brick whose pattern has been spawned, and for which type
+struct Patterned(BrickType);
+
+/// The pieces of each brick type's pattern, as (offset, size) fractions of the brick
+fn pattern(brick_type: BrickType) -> &'static [((f32, f32), (f32, f32))] {
+    match brick_type {
+        BrickType::Normal => &[],
+        //a stripe across the middle
+        BrickType::Tough => &[((0.0, 0.0), (1.0, 0.25))],
+        //a bar at each end, like it's bolted down
+        BrickType::Unbreakable => &[((-0.4, 0.0), (0.08, 1.0)), ((0.4, 0.0), (0.08, 1.0))],
+    }
+}
+
+fn pattern_setup(
+    mut commands: Commands,
+    settings: Res<Accessibility>,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+) {
+    let material = materials.add(settings.palette().pattern.into());
+    commands.insert_resource(PatternMaterial(material));
+}
+
+/// Keeps everything in the palette's colors
+fn theme_system(
+    settings: Res<Accessibility>,
+    pattern_material: Res<PatternMaterial>,
+    mut clear_color: ResMut<ClearColor>,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+    mut material_query: Query<(&ColorRole, &Handle<ColorMaterial>)>,
+    mut brick_query: Query<(&Brick, &Handle<ColorMaterial>)>,
+    mut text_query: Query<(&ColorRole, &mut Text)>,
+) {
+    let palette = settings.palette();
+    if clear_color.0 != palette.background {
+        clear_color.0 = palette.background;
+    }
+
+    //only touching a material when its color is actually wrong, since changing one has it sent to the GPU again
+    let mut recolor = |handle: &Handle<ColorMaterial>, color: Color| {
+        if materials
+            .get(handle)
+            .map_or(false, |material| material.color != color)
+        {
+            materials.get_mut(handle).unwrap().color = color;
+        }
+    };
+    for (role, material) in &mut material_query.iter() {
+        recolor(&material, palette.role(*role));
+    }
+    for (brick, material) in &mut brick_query.iter() {
+        recolor(
+            &material,
+            brick
+                .color
+                .unwrap_or_else(|| palette.brick(brick.brick_type)),
+        );
+    }
+    recolor(&pattern_material.0, palette.pattern);
+
+    for (role, mut text) in &mut text_query.iter() {
+        let color = palette.role(*role);
+        if text.style.color != color {
+            text.style.color = color;
+        }
+    }
+}
+
+fn ball_size_system(
+    settings: Res<Accessibility>,
+    game_state: Res<GameState>,
+    mut ball_query: Query<With<Ball, &mut Sprite>>,
+) {
+    let size = Vec2::splat(settings.ball_size(*game_state));
+    for mut sprite in &mut ball_query.iter() {
+        if sprite.size != size {
+            sprite.size = size;
+        }
+    }
+}
+
+/// Gives every brick that hasn't got one the pattern for its type
+fn pattern_spawn_system(
+    mut commands: Commands,
+    settings: Res<Accessibility>,
+    pattern_material: Res<PatternMaterial>,
+    mut brick_query: Query<Without<Patterned, (Entity, &Brick)>>,
+) {
+    for (entity, brick) in &mut brick_query.iter() {
+        for ((x, y), (width, height)) in pattern(brick.brick_type).iter() {
+            commands
+                .spawn(SpriteComponents {
+                    material: pattern_material.0,
+                    draw: Draw {
+                        is_visible: settings.patterns,
+                        ..Default::default()
+                    },
+                    ..Default::default()
+                })
+                .with(PatternOverlay {
+                    brick: entity,
+                    brick_type: brick.brick_type,
+                    offset: Vec2::new(*x, *y),
+                    size: Vec2::new(*width, *height),
+                });
+        }
+        commands.insert_one(entity, Patterned(brick.brick_type));
+    }
+}
+
+/// Keeps each pattern over its brick, wherever the brick goes. A pattern goes with its brick, and when the brick's type changes
+/// (as it can in the editor) the brick gets the new type's pattern instead
+fn pattern_sync_system(
+    mut commands: Commands,
+    settings: Res<Accessibility>,
+    mut patterned_query: Query<(Entity, &Brick, &Patterned)>,
+    mut brick_query: Query<(&Brick, &Transform, &Sprite)>,
+    mut overlay_query: Query<(
+        Entity,
+        &PatternOverlay,
+        &mut Transform,
+        &mut Sprite,
+        &mut Draw,
+    )>,
+) {
+    for (entity, brick, patterned) in &mut patterned_query.iter() {
+        if brick.brick_type != patterned.0 {
+            commands.remove_one::<Patterned>(entity);
+        }
+    }
+
+    for (entity, overlay, mut transform, mut sprite, mut draw) in &mut overlay_query.iter() {
+        let (brick_transform, brick_sprite) = match (
+            brick_query.get::<Brick>(overlay.brick),
+            brick_query.get::<Transform>(overlay.brick),
+            brick_query.get::<Sprite>(overlay.brick),
+        ) {
+            (Ok(brick), Ok(transform), Ok(sprite)) if brick.brick_type == overlay.brick_type => {
+                (transform, sprite)
+            }
+            _ => {
+                commands.despawn(entity);
+                continue;
+            }
+        };
+
+        let position =
+            brick_transform.translation().truncate() + overlay.offset * brick_sprite.size;
+        *transform = Transform::from_translation(position.extend(PATTERN_Z));
+        sprite.size = overlay.size * brick_sprite.size;
+        draw.is_visible = settings.patterns;
+    }
+}
+
+fn save_settings(
+    resources: &mut Resources,
+    change: impl FnOnce(&mut Accessibility),
+) -> Result<(), String> {
+    let mut settings = resources
+        .get_mut::<Accessibility>()
+        .ok_or_else(missing::<Accessibility>)?;
+    change(&mut settings);
+    settings.save_or_warn();
+    Ok(())
+}
+
+struct PaletteCommand;
+
+impl ConsoleCommand for PaletteCommand {
+    fn name(&self) -> &'static str {
+        "palette"
+    }
+
+    fn usage(&self) -> &'static str {
+        "palette <standard|high_contrast|colorblind> - switches the colors everything is drawn in"
+    }
+
+    fn run(
+        &self,
+        args: &[&str],
+        _world: &mut World,
+        resources: &mut Resources,
+    ) -> Result<String, String> {
+        let name: String = parse_arg(args, self.usage())?;
+        let kind = PaletteKind::ALL
+            .iter()
+            .copied()
+            .find(|kind| kind.name() == name)
+            .ok_or_else(|| format!("no palette called {}, usage: {}", name, self.usage()))?;
+        save_settings(resources, |settings| settings.palette = kind)?;
+        Ok(format!("Switched to the {} palette", kind.name()))
+    }
+}
+
+struct PatternsCommand;
+
+impl ConsoleCommand for PatternsCommand {
+    fn name(&self) -> &'static str {
+        "patterns"
+    }
+
+    fn usage(&self) -> &'static str {
+        "patterns - toggles drawing a pattern per brick type over the bricks"
+    }
+
+    fn run(
+        &self,
+        _args: &[&str],
+        _world: &mut World,
+        resources: &mut Resources,
+    ) -> Result<String, String> {
+        let mut patterns = false;
+        save_settings(resources, |settings| {
+            settings.patterns = !settings.patterns;
+            patterns = settings.patterns;
+        })?;
+        Ok(format!(
+            "Brick patterns {}",
+            if patterns { "on" } else { "off" }
+        ))
+    }
+}
+
+struct GameSpeedCommand;
+
+impl ConsoleCommand for GameSpeedCommand {
+    fn name(&self) -> &'static str {
+        "game_speed"
+    }
+
+    fn usage(&self) -> &'static str {
+        "game_speed <multiplier> - slows the ball down, from 0.25 up to the usual 1"
+    }
+
+    fn run(
+        &self,
+        args: &[&str],
+        _world: &mut World,
+        resources: &mut Resources,
+    ) -> Result<String, String> {
+        let game_speed: f32 = parse_arg(args, self.usage())?;
+        if game_speed < MIN_GAME_SPEED || game_speed > 1.0 {
+            return Err(format!("usage: {}", self.usage()));
+        }
+        save_settings(resources, |settings| settings.game_speed = game_speed)?;
+        Ok(format!("The ball moves at {}x speed", game_speed))
+    }
+}
+
+struct BallSizeCommand;
+
+impl ConsoleCommand for BallSizeCommand {
+    fn name(&self) -> &'static str {
+        "ball_size"
+    }
+
+    fn usage(&self) -> &'static str {
+        "ball_size <multiplier> - makes the ball bigger, from the usual 1 up to 3"
+    }
+
+    fn run(
+        &self,
+        args: &[&str],
+        _world: &mut World,
+        resources: &mut Resources,
+    ) -> Result<String, String> {
+        let ball_scale: f32 = parse_arg(args, self.usage())?;
+        if ball_scale < 1.0 || ball_scale > MAX_BALL_SCALE {
+            return Err(format!("usage: {}", self.usage()));
+        }
+        save_settings(resources, |settings| settings.ball_scale = ball_scale)?;
+        Ok(format!("The ball is {}x its usual size", ball_scale))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::{env, process};
+
+    fn settings_path(name: &str) -> PathBuf {
+        env::temp_dir()
+            .join(format!("breakout-theme-{}-{}", name, process::id()))
+            .join("settings.ron")
+    }
+
+    fn cleanup(path: &Path) {
+        let _ = fs::remove_dir_all(path.parent().unwrap());
+    }
+
+    #[test]
+    fn settings_round_trip() {
+        let path = settings_path("round-trip");
+        let mut settings = Accessibility::load(&path).unwrap();
+        settings.palette = PaletteKind::ColorblindSafe;
+        settings.patterns = true;
+        settings.game_speed = 0.5;
+        settings.ball_scale = 2.0;
+        settings.trail = false;
+        settings.language = "de".to_string();
+        settings.save_or_warn();
+
+        let loaded = Accessibility::load(&path).unwrap();
+        cleanup(&path);
+        assert_eq!(loaded.palette, PaletteKind::ColorblindSafe);
+        assert!(loaded.patterns);
+        assert_eq!(loaded.game_speed, 0.5);
+        assert_eq!(loaded.ball_scale, 2.0);
+        assert!(!loaded.trail);
+        assert_eq!(loaded.language, "de");
+    }
+
+    #[test]
+    fn older_settings_files_get_defaults_for_whatever_they_lack() {
+        let path = settings_path("older");
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(&path, "(palette: HighContrast, game_speed: 0.5)").unwrap();
+
+        let loaded = Accessibility::load(&path).unwrap();
+        cleanup(&path);
+        let defaults = Accessibility::default();
+        assert_eq!(loaded.palette, PaletteKind::HighContrast);
+        assert_eq!(loaded.game_speed, 0.5);
+        assert_eq!(loaded.ball_scale, defaults.ball_scale);
+        assert_eq!(loaded.trail, defaults.trail);
+        assert_eq!(loaded.language, defaults.language);
+    }
+
+    #[test]
+    fn unreadable_settings_are_replaced() {
+        let path = settings_path("unreadable");
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(&path, "not settings at all").unwrap();
+
+        let mut settings = Accessibility::load_or_default(&path);
+        assert_eq!(settings.palette, PaletteKind::Standard);
+        settings.palette = PaletteKind::HighContrast;
+        settings.save_or_warn();
+
+        let loaded = Accessibility::load(&path);
+        cleanup(&path);
+        assert_eq!(loaded.unwrap().palette, PaletteKind::HighContrast);
+    }
+
+    #[test]
+    fn commands_reject_out_of_range_values() {
+        let path = settings_path("commands");
+        let mut world = World::default();
+        let mut resources = Resources::default();
+        resources.insert(Accessibility::load(&path).unwrap());
+
+        assert!(GameSpeedCommand
+            .run(&["0.1"], &mut world, &mut resources)
+            .is_err());
+        assert!(BallSizeCommand
+            .run(&["4"], &mut world, &mut resources)
+            .is_err());
+        assert!(BallSizeCommand
+            .run(&["0.5"], &mut world, &mut resources)
+            .is_err());
+        {
+            let settings = resources.get::<Accessibility>().unwrap();
+            assert_eq!(settings.game_speed, 1.0);
+            assert_eq!(settings.ball_scale, 1.0);
+        }
+
+        assert!(GameSpeedCommand
+            .run(&["0.5"], &mut world, &mut resources)
+            .is_ok());
+        assert!(BallSizeCommand
+            .run(&["2"], &mut world, &mut resources)
+            .is_ok());
+        let saved = Accessibility::load(&path).unwrap();
+        cleanup(&path);
+        assert_eq!(saved.game_speed, 0.5);
+        assert_eq!(saved.ball_scale, 2.0);
+    }
+
+    #[test]
+    fn online_games_keep_the_usual_ball_size() {
+        let settings = Accessibility {
+            ball_scale: 2.0,
+            ..Default::default()
+        };
+        assert_eq!(settings.ball_size(GameState::Playing), BALL_SIZE * 2.0);
+        assert_eq!(settings.ball_size(GameState::Online), BALL_SIZE);
+    }
+}
@@ -0,0 +1,120 @@
+//The title screen, and the attract mode demo it falls into when nobody has touched the keyboard for a while
+
+use bevy::prelude::*;
+
+use super::console::ConsoleState;
+use super::locale::Localization;
+use super::theme::ColorRole;
+use super::{GameState, RestartGame, Scoreboard};
+
+//How long the title screen waits for someone to start a game before the computer starts playing a demo
+const IDLE_SECONDS: f32 = 10.0;
+
+pub struct TitleScreen {
+    idle_timer: Timer,
+}
+
+impl Default for TitleScreen {
+    fn default() -> Self {
+        TitleScreen {
+            idle_timer: Timer::from_seconds(IDLE_SECONDS, false),
+        }
+    }
+}
+
+pub struct TitleText;
+
+pub fn title_setup(mut commands: Commands, asset_server: Res<AssetServer>) {
+    commands
+        .spawn(TextComponents {
+            text: Text {
+                font: asset_server.load("assets/fonts/FiraSans-Bold.ttf").unwrap(),
+                value: "".to_string(),
+                style: TextStyle {
+                    color: Color::rgb(1.0, 1.0, 1.0),
+                    font_size: 50.0,
+                },
+            },
+            style: Style {
+                position_type: PositionType::Absolute,
+                position: Rect {
+                    top: Val::Px(250.0),
+                    left: Val::Px(200.0),
+                    ..Default::default()
+                },
+                ..Default::default()
+            },
+            ..Default::default()
+        })
+        .with(TitleText)
+        .with(ColorRole::Title);
+}
+
+pub fn title_system(
+    time: Res<Time>,
+    keyboard_input: Res<Input<KeyCode>>,
+    console: Res<ConsoleState>,
+    mut title_screen: ResMut<TitleScreen>,
+    mut game_state: ResMut<GameState>,
+    mut restart_events: ResMut<Events<RestartGame>>,
+) {
+    //keys typed into the console don't count as pressed for the screens
+    let pressed = |key| !console.open && keyboard_input.just_pressed(key);
+    match *game_state {
+        GameState::Title => {
+            if pressed(KeyCode::Space) {
+                restart_events.send(RestartGame);
+                *game_state = GameState::Playing;
+                return;
+            }
+
+            title_screen.idle_timer.tick(time.delta_seconds);
+            if title_screen.idle_timer.finished {
+                restart_events.send(RestartGame);
+                *game_state = GameState::Attract;
+            }
+        }
+        GameState::Attract => {
+            //any key at all gets us out of the demo
+            if !console.open && keyboard_input.get_just_pressed().next().is_some() {
+                restart_events.send(RestartGame);
+                title_screen.idle_timer.reset();
+                *game_state = GameState::Title;
+            }
+        }
+        GameState::Cleared | GameState::GameOver => {
+            if pressed(KeyCode::Space) {
+                restart_events.send(RestartGame);
+                *game_state = GameState::Playing;
+            }
+        }
+        GameState::Playing | GameState::Editing | GameState::Online => {}
+    }
+}
+
+pub fn title_text_system(
+    localization: Res<Localization>,
+    game_state: Res<GameState>,
+    scoreboard: Res<Scoreboard>,
+    mut query: Query<(&TitleText, &mut Text)>,
+) {
+    let points = || localization.plural("points", scoreboard.players[0].score as u64);
+    for (_title_text, mut text) in &mut query.iter() {
+        text.value = match *game_state {
+            GameState::Title => localization.text("title.start"),
+            GameState::Attract => localization.text("title.attract"),
+            GameState::Cleared => localization.format("title.cleared", &[("points", points())]),
+            GameState::GameOver if scoreboard.players.len() > 1 => {
+                //whoever still has lives left won
+                let winner = scoreboard
+                    .players
+                    .iter()
+                    .position(|player| player.lives > 0)
+                    .unwrap_or(0);
+                localization.format("title.winner", &[("player", (winner + 1).to_string())])
+            }
+            GameState::GameOver => localization.format("title.game_over", &[("points", points())]),
+            GameState::Playing | GameState::Editing | GameState::Online => "".to_string(),
+        };
+    }
+}
@@ -0,0 +1,268 @@
+//A trail of fading, shrinking copies of each ball where it's just been, getting longer the faster the ball goes so it's easy
+//to see it picking up speed with every brick. Like the debug overlay's lines the copies come from a pool of sprites laid out
+//fresh every frame, with whatever's left over hidden, so nothing gets spawned or despawned while the ball is moving.
+//Where the ball's been is sampled at a fixed rate rather than once a frame, so the trail's the same length at any frame rate.
+//It can be turned off in the settings
+
+use bevy::prelude::*;
+use std::collections::{HashMap, VecDeque};
+
+use super::console::{missing, AddConsoleCommand, ConsoleCommand};
+use super::theme::Accessibility;
+use super::{Ball, BALL_SPEED};
+
+//the longest a trail can get, which is reached at three times the serving speed
+const MAX_COPIES: usize = 12;
+const COPIES_AT_SERVING_SPEED: f32 = MAX_COPIES as f32 / 3.0;
+//how see through the copy right behind the ball is, with the rest fading out from there
+const START_ALPHA: f32 = 0.5;
+//how often a ball's position is sampled for its trail, the copies being this far apart in time
+const SAMPLE_SECONDS: f32 = 1.0 / 60.0;
+//a ball moving further than this in one frame was put back to be served, rather than actually moving there
+const TELEPORT_DISTANCE: f32 = 100.0;
+//just behind the ball
+const TRAIL_Z_OFFSET: f32 = -0.1;
+
+pub struct TrailPlugin;
+
+impl Plugin for TrailPlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        app.add_system(trail_system.system())
+            .add_console_command(TrailCommand);
+    }
+}
+
+//Marks a sprite belonging to the pool of trail copies. Each one has a material of its own so it can fade on its own
+struct TrailCopy;
+
+/// Where a copy goes and how it looks
+struct Afterimage {
+    position: Vec3,
+    size: Vec2,
+    color: Color,
+    texture: Option<Handle<Texture>>,
+}
+
+/// Where a ball's been, sampled every SAMPLE_SECONDS
+#[derive(Default)]
+struct Trail {
+    /// Most recent first
+    samples: VecDeque<Vec3>,
+    last_position: Option<Vec3>,
+    //how long it's been since the last sample was taken
+    since_sample: f32,
+}
+
+impl Trail {
+    fn record(&mut self, position: Vec3, delta_seconds: f32) {
+        let previous = match self.last_position.replace(position) {
+            Some(previous) if (position - previous).length() <= TELEPORT_DISTANCE => previous,
+            //just served (or never seen before), so it hasn't been anywhere yet
+            _ => {
+                self.samples.clear();
+                self.since_sample = 0.0;
+                return;
+            }
+        };
+        if delta_seconds <= 0.0 {
+            return;
+        }
+
+        //a sample for every SAMPLE_SECONDS that went by this frame, placed along the way the ball went. Anything further back
+        //than the longest trail would be thrown away anyway
+        self.since_sample =
+            (self.since_sample + delta_seconds).min(SAMPLE_SECONDS * MAX_COPIES as f32);
+        while self.since_sample >= SAMPLE_SECONDS {
+            self.since_sample -= SAMPLE_SECONDS;
+            let progress = (1.0 - self.since_sample / delta_seconds).max(0.0);
+            self.samples
+                .push_front(previous + (position - previous) * progress);
+        }
+        self.samples.truncate(MAX_COPIES);
+    }
+}
+
+/// How many copies trail behind a ball going at the given speed
+fn trail_length(speed: f32) -> usize {
+    let length = ((speed / BALL_SPEED) * COPIES_AT_SERVING_SPEED).round() as usize;
+    length.min(MAX_COPIES)
+}
+
+/// Lays the pool of copies out behind every ball
+fn trail_system(
+    mut commands: Commands,
+    time: Res<Time>,
+    settings: Res<Accessibility>,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+    mut trails: Local<HashMap<Entity, Trail>>,
+    mut ball_query: Query<(Entity, &Ball, &Transform, &Sprite, &Handle<ColorMaterial>)>,
+    mut copy_query: Query<
+        With<
+            TrailCopy,
+            (
+                &mut Transform,
+                &mut Sprite,
+                &mut Draw,
+                &Handle<ColorMaterial>,
+            ),
+        >,
+    >,
+) {
+    let mut copies = Vec::new();
+    let mut balls_seen = Vec::new();
+    for (entity, ball, transform, sprite, material) in &mut ball_query.iter() {
+        balls_seen.push(entity);
+        let trail = trails.entry(entity).or_insert_with(Trail::default);
+        trail.record(transform.translation(), time.delta_seconds);
+
+        if !settings.trail {
+            continue;
+        }
+        let (color, texture) = match materials.get(&material) {
+            Some(material) => (material.color, material.texture),
+            None => continue,
+        };
+        let length = trail_length(ball.velocity.length());
+        for (index, position) in trail.samples.iter().take(length).enumerate() {
+            let fade = 1.0 - (index + 1) as f32 / (length + 1) as f32;
+            let mut color = color;
+            color.a *= START_ALPHA * fade;
+            copies.push(Afterimage {
+                position: *position + Vec3::new(0.0, 0.0, TRAIL_Z_OFFSET),
+                size: sprite.size * fade,
+                color,
+                texture,
+            });
+        }
+    }
+    trails.retain(|entity, _trail| balls_seen.contains(entity));
+
+    let mut copies = copies.into_iter();
+    for (mut transform, mut sprite, mut draw, material) in &mut copy_query.iter() {
+        match copies.next() {
+            Some(copy) => {
+                *transform = Transform::from_translation(copy.position);
+                sprite.size = copy.size;
+                //a copy mostly keeps the same fade from one frame to the next, and changing its material has it sent to the GPU
+                //again, so it's only touched when it actually looks different
+                let changed = materials.get(&material).map_or(false, |material| {
+                    material.color != copy.color || material.texture != copy.texture
+                });
+                if changed {
+                    let material = materials.get_mut(&material).unwrap();
+                    material.color = copy.color;
+                    material.texture = copy.texture;
+                }
+                draw.is_visible = true;
+            }
+            None => draw.is_visible = false,
+        }
+    }
+
+    //the pool ran out, so grow it. The new copies get laid out from next frame on
+    for copy in copies {
+        commands
+            .spawn(SpriteComponents {
+                material: materials.add(ColorMaterial {
+                    color: copy.color,
+                    texture: copy.texture,
+                }),
+                sprite: Sprite::new(Vec2::zero()),
+                draw: Draw {
+                    is_visible: false,
+                    ..Default::default()
+                },
+                ..Default::default()
+            })
+            .with(TrailCopy);
+    }
+}
+
+struct TrailCommand;
+
+impl ConsoleCommand for TrailCommand {
+    fn name(&self) -> &'static str {
+        "trail"
+    }
+
+    fn usage(&self) -> &'static str {
+        "trail - toggles the trail behind each ball"
+    }
+
+    fn run(
+        &self,
+        _args: &[&str],
+        _world: &mut World,
+        resources: &mut Resources,
+    ) -> Result<String, String> {
+        let mut settings = resources
+            .get_mut::<Accessibility>()
+            .ok_or_else(missing::<Accessibility>)?;
+        settings.trail = !settings.trail;
+        settings.save_or_warn();
+        Ok(format!(
+            "Ball trail {}",
+            if settings.trail { "on" } else { "off" }
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    //a ball heading right at the serving speed for the given time, at the given frame rate
+    fn trail_after(seconds: f32, frames_per_second: f32) -> Trail {
+        let mut trail = Trail::default();
+        let delta_seconds = 1.0 / frames_per_second;
+        let frames = (seconds * frames_per_second).round() as usize;
+        for frame in 0..=frames {
+            let position = Vec3::new(frame as f32 * delta_seconds * BALL_SPEED, 0.0, 0.0);
+            trail.record(position, delta_seconds);
+        }
+        trail
+    }
+
+    #[test]
+    fn faster_balls_get_longer_trails() {
+        assert_eq!(trail_length(0.0), 0);
+        assert_eq!(trail_length(BALL_SPEED), COPIES_AT_SERVING_SPEED as usize);
+        assert!(trail_length(BALL_SPEED * 2.0) > trail_length(BALL_SPEED));
+        assert_eq!(trail_length(BALL_SPEED * 3.0), MAX_COPIES);
+    }
+
+    #[test]
+    fn trails_are_capped() {
+        assert_eq!(trail_length(BALL_SPEED * 100.0), MAX_COPIES);
+        assert_eq!(trail_after(5.0, 60.0).samples.len(), MAX_COPIES);
+    }
+
+    #[test]
+    fn trails_are_as_long_at_any_frame_rate() {
+        let slow = trail_after(1.0, 30.0);
+        let fast = trail_after(1.0, 144.0);
+        assert_eq!(slow.samples.len(), MAX_COPIES);
+        assert_eq!(fast.samples.len(), MAX_COPIES);
+        //the oldest copy's the same distance back either way, give or take when the last sample was taken
+        let back =
+            |trail: &Trail| trail.last_position.unwrap().x() - trail.samples.back().unwrap().x();
+        let expected = MAX_COPIES as f32 * SAMPLE_SECONDS * BALL_SPEED;
+        let tolerance = 1.5 * SAMPLE_SECONDS * BALL_SPEED;
+        assert!((back(&slow) - expected).abs() <= tolerance);
+        assert!((back(&fast) - expected).abs() <= tolerance);
+    }
+
+    #[test]
+    fn serving_clears_the_trail() {
+        let mut trail = trail_after(1.0, 60.0);
+        assert!(!trail.samples.is_empty());
+        let jumped = trail.last_position.unwrap() + Vec3::new(0.0, TELEPORT_DISTANCE * 2.0, 0.0);
+        trail.record(jumped, 1.0 / 60.0);
+        assert!(trail.samples.is_empty());
+
+        //and it starts again from where it was served
+        trail.record(jumped + Vec3::new(5.0, 0.0, 0.0), 1.0 / 60.0);
+        trail.record(jumped + Vec3::new(10.0, 0.0, 0.0), 1.0 / 60.0);
+        assert!(trail.samples.iter().all(|sample| sample.y() == jumped.y()));
+    }
+}
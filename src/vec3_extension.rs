@@ -1,7 +1,35 @@
-use bevy::prelude::Vec3;
+use bevy::prelude::{Vec2, Vec3};
 pub trait Vec3Ext {
-    ///Multiple the a Vec3 by the given scalar
+    ///Multiply the Vec3 by the given scalar
     fn scalar_multiply(&mut self, scalar: f32);
+
+    ///Mirror the vector about the surface with the given normal, like a ball bouncing off of a wall. The normal needn't be unit length,
+    ///and a zero normal has no surface to mirror about so the vector comes back unchanged
+    fn reflect(self, normal: Vec3) -> Vec3;
+
+    ///Scale the vector down to the given length if it's any longer
+    fn clamp_length_max(self, max: f32) -> Vec3;
+
+    ///Scale the vector so its length is between min and max, keeping its direction. A zero vector has no direction so stays zero
+    fn clamp_length(self, min: f32, max: f32) -> Vec3;
+
+    ///Rotate the vector counter clockwise around the z axis by the given angle in radians
+    fn rotate_z(self, angle: f32) -> Vec3;
+
+    ///The angle in radians between the two vectors, from 0 to PI
+    fn angle_to(self, other: Vec3) -> f32;
+
+    ///The part of the vector that goes along the given one
+    fn project_onto(self, onto: Vec3) -> Vec3;
+
+    ///The part of the vector that's perpendicular to the given one, so projection + rejection gives back the vector
+    fn reject_from(self, from: Vec3) -> Vec3;
+
+    ///Clamp each component to lie within the box from min to max
+    fn clamp_to_aabb(self, min: Vec3, max: Vec3) -> Vec3;
+
+    ///Whether every component is within epsilon of the other vector's
+    fn approx_eq(self, other: Vec3, epsilon: f32) -> bool;
 }
 
 impl Vec3Ext for Vec3 {
@@ -10,4 +38,316 @@ impl Vec3Ext for Vec3 {
         *self.y_mut() *= scalar;
         *self.z_mut() *= scalar;
     }
+
+    fn reflect(self, normal: Vec3) -> Vec3 {
+        if normal.length_squared() == 0.0 {
+            return self;
+        }
+        let normal = normal.normalize();
+        self - 2.0 * self.dot(normal) * normal
+    }
+
+    fn clamp_length_max(self, max: f32) -> Vec3 {
+        self.clamp_length(0.0, max)
+    }
+
+    fn clamp_length(self, min: f32, max: f32) -> Vec3 {
+        let length = self.length();
+        if length == 0.0 {
+            self
+        } else if length < min {
+            self * (min / length)
+        } else if length > max {
+            self * (max / length)
+        } else {
+            self
+        }
+    }
+
+    fn rotate_z(self, angle: f32) -> Vec3 {
+        self.truncate().rotate(angle).extend(self.z())
+    }
+
+    fn angle_to(self, other: Vec3) -> f32 {
+        let lengths = self.length() * other.length();
+        if lengths == 0.0 {
+            return 0.0;
+        }
+        //rounding can push the cosine just past 1 for (anti)parallel vectors which acos doesn't take kindly to
+        (self.dot(other) / lengths).max(-1.0).min(1.0).acos()
+    }
+
+    fn project_onto(self, onto: Vec3) -> Vec3 {
+        let length_squared = onto.length_squared();
+        if length_squared == 0.0 {
+            return Vec3::zero();
+        }
+        onto * (self.dot(onto) / length_squared)
+    }
+
+    fn reject_from(self, from: Vec3) -> Vec3 {
+        self - self.project_onto(from)
+    }
+
+    fn clamp_to_aabb(self, min: Vec3, max: Vec3) -> Vec3 {
+        self.max(min).min(max)
+    }
+
+    fn approx_eq(self, other: Vec3, epsilon: f32) -> bool {
+        (self.x() - other.x()).abs() <= epsilon
+            && (self.y() - other.y()).abs() <= epsilon
+            && (self.z() - other.z()).abs() <= epsilon
+    }
+}
+
+pub trait Vec2Ext {
+    ///Multiply the Vec2 by the given scalar
+    fn scalar_multiply(&mut self, scalar: f32);
+
+    ///Mirror the vector about the surface with the given normal, like a ball bouncing off of a wall. The normal needn't be unit length,
+    ///and a zero normal has no surface to mirror about so the vector comes back unchanged
+    fn reflect(self, normal: Vec2) -> Vec2;
+
+    ///Scale the vector down to the given length if it's any longer
+    fn clamp_length_max(self, max: f32) -> Vec2;
+
+    ///Scale the vector so its length is between min and max, keeping its direction. A zero vector has no direction so stays zero
+    fn clamp_length(self, min: f32, max: f32) -> Vec2;
+
+    ///Rotate the vector counter clockwise by the given angle in radians
+    fn rotate(self, angle: f32) -> Vec2;
+
+    ///The angle in radians between the two vectors, from 0 to PI
+    fn angle_to(self, other: Vec2) -> f32;
+
+    ///The part of the vector that goes along the given one
+    fn project_onto(self, onto: Vec2) -> Vec2;
+
+    ///The part of the vector that's perpendicular to the given one, so projection + rejection gives back the vector
+    fn reject_from(self, from: Vec2) -> Vec2;
+
+    ///Clamp each component to lie within the box from min to max
+    fn clamp_to_aabb(self, min: Vec2, max: Vec2) -> Vec2;
+
+    ///Whether every component is within epsilon of the other vector's
+    fn approx_eq(self, other: Vec2, epsilon: f32) -> bool;
+}
+
+impl Vec2Ext for Vec2 {
+    fn scalar_multiply(&mut self, scalar: f32) {
+        *self.x_mut() *= scalar;
+        *self.y_mut() *= scalar;
+    }
+
+    fn reflect(self, normal: Vec2) -> Vec2 {
+        if normal.length_squared() == 0.0 {
+            return self;
+        }
+        let normal = normal.normalize();
+        self - 2.0 * self.dot(normal) * normal
+    }
+
+    fn clamp_length_max(self, max: f32) -> Vec2 {
+        self.clamp_length(0.0, max)
+    }
+
+    fn clamp_length(self, min: f32, max: f32) -> Vec2 {
+        let length = self.length();
+        if length == 0.0 {
+            self
+        } else if length < min {
+            self * (min / length)
+        } else if length > max {
+            self * (max / length)
+        } else {
+            self
+        }
+    }
+
+    fn rotate(self, angle: f32) -> Vec2 {
+        let (sin, cos) = angle.sin_cos();
+        Vec2::new(
+            self.x() * cos - self.y() * sin,
+            self.x() * sin + self.y() * cos,
+        )
+    }
+
+    fn angle_to(self, other: Vec2) -> f32 {
+        let lengths = self.length() * other.length();
+        if lengths == 0.0 {
+            return 0.0;
+        }
+        (self.dot(other) / lengths).max(-1.0).min(1.0).acos()
+    }
+
+    fn project_onto(self, onto: Vec2) -> Vec2 {
+        let length_squared = onto.length_squared();
+        if length_squared == 0.0 {
+            return Vec2::zero();
+        }
+        onto * (self.dot(onto) / length_squared)
+    }
+
+    fn reject_from(self, from: Vec2) -> Vec2 {
+        self - self.project_onto(from)
+    }
+
+    fn clamp_to_aabb(self, min: Vec2, max: Vec2) -> Vec2 {
+        self.max(min).min(max)
+    }
+
+    fn approx_eq(self, other: Vec2, epsilon: f32) -> bool {
+        (self.x() - other.x()).abs() <= epsilon && (self.y() - other.y()).abs() <= epsilon
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::f32::consts::PI;
+
+    const EPSILON: f32 = 1e-3;
+
+    //a spread of directions and lengths, from tiny to large, to check the properties hold over
+    fn samples() -> Vec<Vec2> {
+        (0..64)
+            .map(|i| {
+                let angle = i as f32 * 0.37;
+                let length = 0.01 + (i % 7) as f32 * 13.0;
+                Vec2::new(length, 0.0).rotate(angle)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn reflecting_keeps_the_length_and_twice_gives_back_the_vector() {
+        for vector in samples() {
+            for normal in samples() {
+                let reflected = vector.reflect(normal);
+                assert!((reflected.length() - vector.length()).abs() <= EPSILON);
+                assert!(reflected.reflect(normal).approx_eq(vector, EPSILON));
+
+                let vector = vector.extend(1.0);
+                let reflected = vector.reflect(normal.extend(0.0));
+                assert!((reflected.length() - vector.length()).abs() <= EPSILON);
+                assert!(reflected
+                    .reflect(normal.extend(0.0))
+                    .approx_eq(vector, EPSILON));
+            }
+        }
+    }
+
+    #[test]
+    fn reflecting_flips_only_the_part_along_the_normal() {
+        let bounced = Vec2::new(3.0, -4.0).reflect(Vec2::new(0.0, 2.0));
+        assert!(bounced.approx_eq(Vec2::new(3.0, 4.0), EPSILON));
+        let bounced = Vec3::new(3.0, -4.0, 1.0).reflect(Vec3::new(-5.0, 0.0, 0.0));
+        assert!(bounced.approx_eq(Vec3::new(-3.0, -4.0, 1.0), EPSILON));
+    }
+
+    #[test]
+    fn reflecting_about_a_zero_normal_changes_nothing() {
+        assert_eq!(
+            Vec2::new(3.0, -4.0).reflect(Vec2::zero()),
+            Vec2::new(3.0, -4.0)
+        );
+        assert_eq!(
+            Vec3::new(3.0, -4.0, 1.0).reflect(Vec3::zero()),
+            Vec3::new(3.0, -4.0, 1.0)
+        );
+    }
+
+    #[test]
+    fn clamping_to_a_box_lands_inside_it_and_leaves_inside_points_alone() {
+        let min = Vec2::new(-10.0, -20.0);
+        let max = Vec2::new(30.0, 5.0);
+        for point in samples() {
+            let clamped = point.clamp_to_aabb(min, max);
+            assert!(clamped.x() >= min.x() && clamped.x() <= max.x());
+            assert!(clamped.y() >= min.y() && clamped.y() <= max.y());
+            assert_eq!(clamped.clamp_to_aabb(min, max), clamped);
+            if point.x() >= min.x()
+                && point.x() <= max.x()
+                && point.y() >= min.y()
+                && point.y() <= max.y()
+            {
+                assert_eq!(clamped, point);
+            }
+
+            let point = point.extend(point.x() - point.y());
+            let clamped = point.clamp_to_aabb(min.extend(-1.0), max.extend(1.0));
+            assert!(clamped.z() >= -1.0 && clamped.z() <= 1.0);
+            assert_eq!(clamped.truncate(), point.truncate().clamp_to_aabb(min, max));
+        }
+    }
+
+    #[test]
+    fn rotating_keeps_the_length_and_undoes() {
+        for vector in samples() {
+            for step in 0..8 {
+                let angle = step as f32 * 0.9 - 3.0;
+                let rotated = vector.rotate(angle);
+                assert!((rotated.length() - vector.length()).abs() <= EPSILON);
+                assert!(rotated.rotate(-angle).approx_eq(vector, EPSILON));
+                assert!(
+                    (rotated.angle_to(vector) - angle.abs().min(2.0 * PI - angle.abs())).abs()
+                        <= EPSILON
+                );
+
+                let rotated = vector.extend(2.0).rotate_z(angle);
+                assert_eq!(rotated.z(), 2.0);
+                assert!(rotated.truncate().approx_eq(vector.rotate(angle), EPSILON));
+            }
+        }
+        assert!(Vec2::new(1.0, 0.0)
+            .rotate(PI / 2.0)
+            .approx_eq(Vec2::new(0.0, 1.0), EPSILON));
+    }
+
+    #[test]
+    fn projection_and_rejection_add_back_up_to_the_vector() {
+        for vector in samples() {
+            for onto in samples() {
+                let projection = vector.project_onto(onto);
+                let rejection = vector.reject_from(onto);
+                assert!((projection + rejection).approx_eq(vector, EPSILON));
+                //the projection runs along the other vector and the rejection across it
+                assert!(
+                    projection.reject_from(onto).length() <= EPSILON * vector.length().max(1.0)
+                );
+                assert!(
+                    rejection.dot(onto).abs() <= EPSILON * vector.length().max(1.0) * onto.length()
+                );
+
+                let vector = vector.extend(-vector.x());
+                let onto = onto.extend(1.0);
+                let sum = vector.project_onto(onto) + vector.reject_from(onto);
+                assert!(sum.approx_eq(vector, EPSILON));
+            }
+            assert_eq!(vector.project_onto(Vec2::zero()), Vec2::zero());
+            assert_eq!(vector.reject_from(Vec2::zero()), vector);
+        }
+    }
+
+    #[test]
+    fn clamping_the_length_keeps_the_direction() {
+        for vector in samples() {
+            let clamped = vector.clamp_length(5.0, 20.0);
+            assert!(clamped.length() >= 5.0 - EPSILON && clamped.length() <= 20.0 + EPSILON);
+            assert!(clamped.dot(vector) > 0.0);
+            assert!(clamped.reject_from(vector).length() <= EPSILON);
+            assert!(vector.clamp_length_max(20.0).length() <= 20.0 + EPSILON);
+        }
+        assert_eq!(Vec2::zero().clamp_length(5.0, 20.0), Vec2::zero());
+    }
+
+    #[test]
+    fn scalar_multiply_scales_every_component() {
+        let mut vector = Vec2::new(1.5, -2.0);
+        vector.scalar_multiply(2.0);
+        assert_eq!(vector, Vec2::new(3.0, -4.0));
+        let mut vector = Vec3::new(1.5, -2.0, 0.5);
+        vector.scalar_multiply(-2.0);
+        assert_eq!(vector, Vec3::new(-3.0, 4.0, -1.0));
+    }
 }